@@ -0,0 +1,918 @@
+use expr::{Expr, BinOp, UnOp, Builtin, Dec, Pattern, Span};
+use expr::Expr::*;
+use state::{State, Binding};
+use symbol::{Interner, Symbol};
+use type_error::{TypeError, Diagnostic};
+use types::Type;
+use std::collections::HashMap;
+
+// the type of a name in scope, with the variables it is polymorphic over
+#[derive(Clone, Debug)]
+struct Scheme {
+  vars: Vec<usize>,
+  ty: Type,
+  bike: bool,
+}
+
+fn mono(ty: Type, bike: bool) -> Scheme {
+  Scheme { vars: Vec::new(), ty, bike }
+}
+
+fn unspan(e: &Expr) -> &Expr {
+  match *e {
+    At(_, ref e1) | Framed(_, ref e1) => unspan(e1),
+    ref e => e,
+  }
+}
+
+fn name_of(e: &Expr) -> Option<Symbol> {
+  match *unspan(e) {
+    Var(x) | Local(x, _, _) => Some(x),
+    _ => None,
+  }
+}
+
+// a battle whose last branch has no run is () when no branch is taken
+fn has_run(e: &Expr) -> bool {
+  match *unspan(e) {
+    Undefined => false,
+    Ternary(_, _, ref e3) => has_run(e3),
+    _ => true,
+  }
+}
+
+fn substitute(t: &Type, map: &[(usize, Type)]) -> Type {
+  match *t {
+    Type::Var(v) => match map.iter().find(|m| m.0 == v) {
+      Some(m) => m.1.clone(),
+      None => Type::Var(v),
+    },
+    Type::Party(ref t) => Type::Party(Box::new(substitute(t, map))),
+    Type::Dex(ref k, ref t) => Type::Dex(Box::new(substitute(k, map)), Box::new(substitute(t, map))),
+    Type::Attack(ref ps, ref r) => {
+      Type::Attack(ps.iter().map(|p| substitute(p, map)).collect(), Box::new(substitute(r, map)))
+    },
+    ref t => t.clone(),
+  }
+}
+
+// Hindley-Milner inference over a parsed program. Every error is kept, with
+// the span of the innermost Expr::At around it, and checking carries on
+pub struct Checker {
+  // what each type variable has been solved to
+  subst: Vec<Option<Type>>,
+  scopes: Vec<HashMap<Symbol, Scheme>>,
+  // the interpreter's names, for the constructors a type declares
+  symbols: Interner,
+  cards: HashMap<String, Vec<(String, Type)>>,
+  // each constructor's type and number of fields
+  ctors: HashMap<String, (String, usize)>,
+  // each type's constructors in the order it declares them, for evolve
+  variants: HashMap<String, Vec<(String, usize)>>,
+  // the return type of each enclosing attack, for flee
+  returns: Vec<Type>,
+  span: Span,
+  errors: Vec<(Span, TypeError)>,
+}
+
+impl Checker {
+  pub fn new(symbols: &Interner) -> Checker {
+    Checker {
+      subst: Vec::new(),
+      scopes: vec!(HashMap::new()),
+      symbols: symbols.clone(),
+      cards: HashMap::new(),
+      ctors: HashMap::new(),
+      variants: HashMap::new(),
+      returns: Vec::new(),
+      span: Span::default(),
+      errors: Vec::new(),
+    }
+  }
+
+  // a checker that knows the types of everything bound in a session
+  pub fn with_state(state: &State, symbols: &Interner) -> Checker {
+    let mut checker = Checker::new(symbols);
+
+    for (name, fields) in state.cards.iter() {
+      let fields = fields.iter().map(|x| (x.clone(), checker.fresh())).collect();
+      checker.cards.insert(name.clone(), fields);
+    }
+
+    for (name, variants) in state.types.iter() {
+      for (c, fields) in variants.iter() {
+        checker.ctors.insert(c.clone(), (name.clone(), fields.len()));
+      }
+
+      checker.variants.insert(name.clone(), variants.iter().map(|v| (v.0.clone(), v.1.len())).collect());
+    }
+
+    for frame in state.mem.iter() {
+      // attacks go last so their bodies see the types of everything else
+      let mut bindings: Vec<(Symbol, &Binding)> = State::bindings(frame).collect();
+      bindings.sort_by_key(|b| (matches!(*b.1, Binding::Const(ref v) if v.is_func()), b.0.to_string()));
+
+      for b in bindings {
+        let scheme = match b.1 {
+          Binding::Const(ref v) => {
+            let ty = checker.infer(v);
+            checker.generalize(&ty)
+          },
+          Binding::Bike(ref vs, _) => {
+            let ty = match vs.last() {
+              Some(v) => checker.infer(v),
+              None => checker.fresh(),
+            };
+
+            mono(ty, true)
+          },
+        };
+
+        checker.bind(b.0, scheme);
+      }
+    }
+
+    // values were made by code that already ran
+    checker.errors.clear();
+    checker
+  }
+
+  // the type of e; anything wrong with it is left in errors
+  pub fn check(&mut self, e: &Expr) -> Type {
+    let ty = self.infer(e);
+    self.resolve(&ty)
+  }
+
+  // every error so far, placed in the source the spans point into
+  pub fn errors(&self, source: &str) -> Vec<Diagnostic> {
+    self.errors.iter().map(|(span, error)| {
+      let (line, column) = span.line_col(source);
+      Diagnostic { line, column, error: error.clone() }
+    }).collect()
+  }
+
+  fn fresh(&mut self) -> Type {
+    self.subst.push(None);
+    Type::Var(self.subst.len() - 1)
+  }
+
+  fn resolve(&self, t: &Type) -> Type {
+    match *t {
+      Type::Var(v) => match self.subst[v] {
+        Some(ref t) => self.resolve(t),
+        None => Type::Var(v),
+      },
+      Type::Party(ref t) => Type::Party(Box::new(self.resolve(t))),
+      Type::Dex(ref k, ref t) => Type::Dex(Box::new(self.resolve(k)), Box::new(self.resolve(t))),
+      Type::Attack(ref ps, ref r) => {
+        Type::Attack(ps.iter().map(|p| self.resolve(p)).collect(), Box::new(self.resolve(r)))
+      },
+      ref t => t.clone(),
+    }
+  }
+
+  fn error(&mut self, err: TypeError) {
+    self.errors.push((self.span, err));
+  }
+
+  fn unify_types(&mut self, t1: &Type, t2: &Type) -> Result<(), TypeError> {
+    match (self.resolve(t1), self.resolve(t2)) {
+      (Type::Var(u), Type::Var(v)) if u == v => Ok(()),
+      (Type::Var(v), t) | (t, Type::Var(v)) => {
+        if t.occurs(v) {
+          return Err(TypeError::InfiniteType(Type::Var(v), t));
+        }
+
+        self.subst[v] = Some(t);
+        Ok(())
+      },
+      (Type::Party(a), Type::Party(b)) => self.unify_types(&a, &b),
+      (Type::Dex(k1, v1), Type::Dex(k2, v2)) => {
+        self.unify_types(&k1, &k2)?;
+        self.unify_types(&v1, &v2)
+      },
+      (Type::Attack(ref ps1, ref r1), Type::Attack(ref ps2, ref r2)) if ps1.len() == ps2.len() => {
+        for (p1, p2) in ps1.iter().zip(ps2.iter()) {
+          self.unify_types(p1, p2)?;
+        }
+
+        self.unify_types(r1, r2)
+      },
+      (t1, t2) if t1 == t2 => Ok(()),
+      (t1, t2) => Err(TypeError::Mismatch(t1, t2)),
+    }
+  }
+
+  fn unify(&mut self, expected: &Type, found: &Type) {
+    match self.unify_types(expected, found) {
+      Ok(()) => {},
+      Err(err @ TypeError::InfiniteType(_, _)) => self.error(err),
+      // the whole types say more than the parts that differ
+      Err(_) => {
+        let err = TypeError::Mismatch(self.resolve(expected), self.resolve(found));
+        self.error(err);
+      },
+    }
+  }
+
+  // unifies, blaming e for a mismatch
+  fn unify_at(&mut self, e: &Expr, expected: &Type, found: &Type) {
+    let outer = self.span;
+
+    if let At(span, _) = *e {
+      self.span = span;
+    }
+
+    self.unify(expected, found);
+    self.span = outer;
+  }
+
+  fn expect(&mut self, e: &Expr, expected: &Type) -> Type {
+    let ty = self.infer(e);
+    self.unify_at(e, expected, &ty);
+    ty
+  }
+
+  fn lookup(&self, x: Symbol) -> Option<Scheme> {
+    self.scopes.iter().rev().find_map(|scope| scope.get(&x)).cloned()
+  }
+
+  fn bind(&mut self, x: Symbol, scheme: Scheme) {
+    if let Some(scope) = self.scopes.last_mut() {
+      scope.insert(x, scheme);
+    }
+  }
+
+  fn generalize(&self, ty: &Type) -> Scheme {
+    let ty = self.resolve(ty);
+    let mut env = Vec::new();
+
+    for scheme in self.scopes.iter().flat_map(|scope| scope.values()) {
+      let mut vars = Vec::new();
+      self.resolve(&scheme.ty).vars(&mut vars);
+      env.extend(vars.into_iter().filter(|v| !scheme.vars.contains(v)));
+    }
+
+    for ret in self.returns.iter() {
+      self.resolve(ret).vars(&mut env);
+    }
+
+    let mut vars = Vec::new();
+    ty.vars(&mut vars);
+    vars.retain(|v| !env.contains(v));
+
+    Scheme { vars, ty, bike: false }
+  }
+
+  fn instantiate(&mut self, scheme: &Scheme) -> Type {
+    let map: Vec<(usize, Type)> = scheme.vars.iter().map(|v| (*v, self.fresh())).collect();
+    substitute(&self.resolve(&scheme.ty), &map)
+  }
+
+  fn var(&mut self, x: Symbol) -> Type {
+    match self.lookup(x) {
+      Some(scheme) => self.instantiate(&scheme),
+      // attacks see their caller's bindings, which may be bound after them
+      None if !self.returns.is_empty() => self.fresh(),
+      None => {
+        self.error(TypeError::UnboundVariable(x.to_string()));
+        self.fresh()
+      },
+    }
+  }
+
+  // the type of a bike named by e, for assignment and the mutating builtins
+  fn bike(&mut self, e: &Expr, give: bool) -> Type {
+    let x = match name_of(e) {
+      Some(x) => x,
+      None => return self.infer(e),
+    };
+
+    match self.lookup(x) {
+      Some(ref scheme) if !scheme.bike => {
+        let x = x.to_string();
+        self.error(if give { TypeError::GiveFromConst(x) } else { TypeError::AssignToConst(x) });
+        self.instantiate(scheme)
+      },
+      _ => self.var(x),
+    }
+  }
+
+  // what no arm of an evolve over a ty covers, as patterns to add
+  fn missing_patterns(&self, ty: &Type, arms: &[(Pattern, Expr)]) -> Vec<String> {
+    if arms.iter().any(|arm| arm.0.is_irrefutable()) {
+      return Vec::new();
+    }
+
+    match self.resolve(ty) {
+      Type::Named(ref name) => match self.variants.get(name) {
+        Some(variants) => variants.iter().filter(|variant| {
+          !arms.iter().any(|arm| match arm.0 {
+            Pattern::Ctor(ref c, ref ps) => *c == variant.0 && ps.iter().all(|p| p.is_irrefutable()),
+            _ => false,
+          })
+        }).map(|&(ref c, n)| match n {
+          0 => c.clone(),
+          n => format!("{}({})", c, vec!("_"; n).join(", ")),
+        }).collect(),
+        None => Vec::new(),
+      },
+      Type::Bool => vec!(true, false).into_iter().filter(|b| {
+        !arms.iter().any(|arm| match arm.0 {
+          Pattern::Lit(ref l) => *unspan(l) == Bool(*b),
+          _ => false,
+        })
+      }).map(|b| format!("{}", Bool(b))).collect(),
+      // nothing is known yet about what is matched
+      Type::Var(_) => Vec::new(),
+      _ => vec!("_".to_string()),
+    }
+  }
+
+  fn scoped<F: FnOnce(&mut Checker) -> Type>(&mut self, f: F) -> Type {
+    self.scopes.push(HashMap::new());
+    let ty = f(self);
+    self.scopes.pop();
+    ty
+  }
+
+  // an annotation with its names looked up, since the parser cannot tell cards from types
+  fn annotation(&mut self, t: &Option<Type>) -> Type {
+    match *t {
+      Some(ref t) => self.declared(t),
+      None => self.fresh(),
+    }
+  }
+
+  fn declared(&mut self, t: &Type) -> Type {
+    match *t {
+      Type::Named(ref name) if self.cards.contains_key(name) => Type::Card(name.clone()),
+      Type::Named(ref name) if self.ctors.values().any(|c| c.0 == *name) => t.clone(),
+      Type::Named(ref name) => {
+        self.error(TypeError::UnknownType(name.clone()));
+        self.fresh()
+      },
+      Type::Party(ref t) => Type::Party(Box::new(self.declared(t))),
+      Type::Dex(ref k, ref t) => Type::Dex(Box::new(self.declared(k)), Box::new(self.declared(t))),
+      Type::Attack(ref ps, ref r) => {
+        let ps = ps.iter().map(|p| self.declared(p)).collect();
+        Type::Attack(ps, Box::new(self.declared(r)))
+      },
+      ref t => t.clone(),
+    }
+  }
+
+  fn func(&mut self, name: &Option<Box<Expr>>, body: &Expr, params: &[Expr], ts: &[Option<Type>], result: &Option<Box<Type>>) -> Type {
+    let ps: Vec<Type> = (0..params.len()).map(|i| self.annotation(ts.get(i).unwrap_or(&None))).collect();
+    let ret = match *result {
+      Some(ref t) => self.declared(t),
+      None => self.fresh(),
+    };
+    let ty = Type::Attack(ps.clone(), Box::new(ret.clone()));
+
+    self.returns.push(ret.clone());
+    self.scoped(|checker| {
+      // recursive calls see the attack before it is generalized
+      if let Some(x) = name.as_ref().and_then(|x| name_of(x)) {
+        checker.bind(x, mono(ty.clone(), false));
+      }
+
+      // parameters are bikes
+      for (p, t) in params.iter().zip(ps) {
+        if let Some(x) = name_of(p) {
+          checker.bind(x, mono(t, true));
+        }
+      }
+
+      checker.expect(body, &ret)
+    });
+    self.returns.pop();
+
+    ty
+  }
+
+  fn pattern(&mut self, p: &Pattern, ty: &Type) {
+    match *p {
+      Pattern::Wildcard => {},
+      Pattern::Bind(x) => self.bind(x, mono(ty.clone(), false)),
+      Pattern::Lit(ref l) => {
+        let lt = self.infer(l);
+        self.unify(ty, &lt);
+      },
+      Pattern::Ctor(ref c, ref ps) => {
+        match self.ctors.get(c).cloned() {
+          Some((name, n)) => {
+            self.unify(ty, &Type::Named(name));
+
+            if n != ps.len() {
+              self.error(TypeError::WrongNumberOfArgs(n, ps.len()));
+            }
+          },
+          None => self.error(TypeError::UnboundVariable(c.clone())),
+        }
+
+        // fields are not declared with types, so each one could be anything
+        for p in ps.iter() {
+          let field = self.fresh();
+          self.pattern(p, &field);
+        }
+      },
+    }
+  }
+
+  fn call(&mut self, f: &Expr, args: &[Expr]) -> Type {
+    let tf = self.infer(f);
+    let targs: Vec<Type> = args.iter().map(|arg| self.infer(arg)).collect();
+
+    match self.resolve(&tf) {
+      Type::Attack(ref ps, ref r) if ps.len() != args.len() => {
+        self.error(TypeError::WrongNumberOfArgs(ps.len(), args.len()));
+        (**r).clone()
+      },
+      Type::Attack(ps, r) => {
+        for ((p, t), arg) in ps.iter().zip(targs.iter()).zip(args.iter()) {
+          self.unify_at(arg, p, t);
+        }
+
+        *r
+      },
+      _ => {
+        let r = self.fresh();
+        self.unify(&tf, &Type::Attack(targs, Box::new(r.clone())));
+        r
+      },
+    }
+  }
+
+  fn builtin(&mut self, b: &Builtin, args: &[Expr]) -> Type {
+    if args.len() != b.arity() {
+      self.error(TypeError::WrongNumberOfArgs(b.arity(), args.len()));
+
+      for arg in args.iter() {
+        self.infer(arg);
+      }
+
+      return self.fresh();
+    }
+
+    let (k, v) = (self.fresh(), self.fresh());
+    let dex = Type::Dex(Box::new(k.clone()), Box::new(v.clone()));
+
+    match *b {
+      Builtin::Len => {
+        let ty = self.infer(&args[0]);
+
+        match self.resolve(&ty) {
+          Type::Str | Type::Party(_) | Type::Dex(_, _) | Type::Var(_) => {},
+          ty => self.error(TypeError::NoLength(ty)),
+        }
+
+        Type::Int
+      },
+      Builtin::Substr => {
+        self.expect(&args[0], &Type::Str);
+        self.expect(&args[1], &Type::Int);
+        self.expect(&args[2], &Type::Int);
+        Type::Str
+      },
+      Builtin::CharAt => {
+        self.expect(&args[0], &Type::Str);
+        self.expect(&args[1], &Type::Int);
+        Type::Str
+      },
+      Builtin::Push | Builtin::Pop => {
+        let ty = self.bike(&args[0], false);
+        self.unify_at(&args[0], &Type::Party(Box::new(v.clone())), &ty);
+
+        if *b == Builtin::Pop {
+          return v;
+        }
+
+        self.expect(&args[1], &v);
+        Type::Unit
+      },
+      Builtin::Get | Builtin::Has => {
+        self.expect(&args[0], &dex);
+        self.expect(&args[1], &k);
+
+        if *b == Builtin::Has {
+          return Type::Bool;
+        }
+
+        v
+      },
+      Builtin::Keys => {
+        self.expect(&args[0], &dex);
+        Type::Party(Box::new(k))
+      },
+      Builtin::Set | Builtin::Remove => {
+        let ty = self.bike(&args[0], false);
+        self.unify_at(&args[0], &dex, &ty);
+        self.expect(&args[1], &k);
+
+        if *b == Builtin::Remove {
+          return v;
+        }
+
+        self.expect(&args[2], &v);
+        Type::Unit
+      },
+    }
+  }
+
+  fn card(&mut self, name: &str, fields: &[(String, Expr)], base: Option<&Expr>) -> Type {
+    let declared = match self.cards.get(name).cloned() {
+      Some(declared) => declared,
+      None => {
+        self.error(TypeError::CardNotFound(name.to_string()));
+
+        for e in fields.iter().map(|field| &field.1).chain(base) {
+          self.infer(e);
+        }
+
+        return self.fresh();
+      },
+    };
+
+    for (x, e) in fields.iter() {
+      match declared.iter().find(|field| field.0 == *x) {
+        Some(field) => {
+          self.expect(e, &field.1);
+        },
+        None => {
+          self.error(TypeError::FieldNotFound(name.to_string(), x.clone()));
+          self.infer(e);
+        },
+      }
+    }
+
+    let ty = Type::Card(name.to_string());
+
+    match base {
+      Some(b) => {
+        self.expect(b, &ty);
+      },
+      None => {
+        for (x, _) in declared.iter().filter(|field| !fields.iter().any(|f| f.0 == field.0)) {
+          self.error(TypeError::MissingField(name.to_string(), x.clone()));
+        }
+      },
+    }
+
+    ty
+  }
+
+  fn field(&mut self, e: &Expr, x: &str) -> Type {
+    let ty = self.infer(e);
+
+    match self.resolve(&ty) {
+      Type::Card(name) => {
+        match self.cards.get(&name).and_then(|fields| fields.iter().find(|field| field.0 == x)) {
+          Some(field) => field.1.clone(),
+          None => {
+            self.error(TypeError::FieldNotFound(name, x.to_string()));
+            self.fresh()
+          },
+        }
+      },
+      Type::Module(_) => self.fresh(),
+      Type::Var(_) => {
+        // a field only one card has says which card it must be
+        let owners: Vec<(String, Type)> = self.cards.iter()
+          .filter_map(|(name, fields)| fields.iter().find(|field| field.0 == x).map(|field| (name.clone(), field.1.clone())))
+          .collect();
+
+        match owners.len() {
+          1 => {
+            self.unify_at(e, &Type::Card(owners[0].0.clone()), &ty);
+            owners[0].1.clone()
+          },
+          _ => self.fresh(),
+        }
+      },
+      ty => {
+        self.error(TypeError::NotACard(ty));
+        self.fresh()
+      },
+    }
+  }
+
+  fn infer(&mut self, e: &Expr) -> Type {
+    match *e {
+      Int(_) => Type::Int,
+      Bool(_) => Type::Bool,
+      Str(_) => Type::Str,
+      Undefined => Type::Unit,
+      Var(x) | Local(x, _, _) => self.var(x),
+      Framed(_, ref e1) => self.infer(e1),
+      At(span, ref e1) => {
+        let outer = self.span;
+        self.span = span;
+        let ty = self.infer(e1);
+        self.span = outer;
+        ty
+      },
+      Uop(UnOp::Not, ref e1) => {
+        self.expect(e1, &Type::Bool);
+        Type::Bool
+      },
+      Uop(UnOp::Neg, ref e1) => {
+        self.expect(e1, &Type::Int);
+        Type::Int
+      },
+      Bop(BinOp::Seq, ref e1, ref e2) => {
+        self.infer(e1);
+        self.infer(e2)
+      },
+      Bop(BinOp::Assign, ref e1, ref e2) => {
+        let t2 = self.infer(e2);
+
+        match name_of(e1) {
+          Some(_) => {
+            let t1 = self.bike(e1, false);
+            self.unify_at(e2, &t1, &t2);
+          },
+          None => {
+            self.infer(e1);
+          },
+        }
+
+        t2
+      },
+      // anything added to a move is printed into it
+      Bop(BinOp::Plus, ref e1, ref e2) => {
+        let (t1, t2) = (self.infer(e1), self.infer(e2));
+
+        if self.resolve(&t1) == Type::Str || self.resolve(&t2) == Type::Str {
+          return Type::Str;
+        }
+
+        self.unify_at(e1, &Type::Int, &t1);
+        self.unify_at(e2, &Type::Int, &t2);
+        Type::Int
+      },
+      Bop(BinOp::Eq, ref e1, ref e2) | Bop(BinOp::Ne, ref e1, ref e2) => {
+        let t1 = self.infer(e1);
+        self.expect(e2, &t1);
+        Type::Bool
+      },
+      Bop(BinOp::And, ref e1, ref e2) | Bop(BinOp::Or, ref e1, ref e2) => {
+        self.expect(e1, &Type::Bool);
+        self.expect(e2, &Type::Bool);
+        Type::Bool
+      },
+      Bop(BinOp::Lt, ref e1, ref e2) | Bop(BinOp::Gt, ref e1, ref e2) |
+        Bop(BinOp::Leq, ref e1, ref e2) | Bop(BinOp::Geq, ref e1, ref e2) => {
+        self.expect(e1, &Type::Int);
+        self.expect(e2, &Type::Int);
+        Type::Bool
+      },
+      Bop(_, ref e1, ref e2) => {
+        self.expect(e1, &Type::Int);
+        self.expect(e2, &Type::Int);
+        Type::Int
+      },
+      Ternary(ref c, ref e2, ref e3) => {
+        self.expect(c, &Type::Bool);
+        let t2 = self.infer(e2);
+
+        if !has_run(e3) {
+          self.infer(e3);
+          return Type::Unit;
+        }
+
+        self.expect(e3, &t2);
+        t2
+      },
+      While(_, _, ref c, _, ref body, _, ref run) => {
+        self.expect(c, &Type::Bool);
+        let ty = self.infer(body);
+
+        if **run == Undefined {
+          return Type::Unit;
+        }
+
+        self.expect(run, &ty);
+        ty
+      },
+      For(_, ref x, ref iter, ref body, ref running) => {
+        let item = match *unspan(iter) {
+          Range(ref e1, ref e2, ref e3) => {
+            self.expect(e1, &Type::Int);
+            self.expect(e2, &Type::Int);
+            self.expect(e3, &Type::Int);
+            Type::Int
+          },
+          _ => {
+            let ty = self.infer(iter);
+
+            match self.resolve(&ty) {
+              Type::Party(t) => *t,
+              Type::Dex(k, _) => *k,
+              Type::Str => Type::Str,
+              Type::Var(_) => self.fresh(),
+              ty => {
+                self.error(TypeError::NotIterable(ty));
+                self.fresh()
+              },
+            }
+          },
+        };
+
+        self.scoped(|checker| {
+          checker.bind(*x, mono(item, false));
+          checker.infer(body)
+        });
+
+        if let Some(ref e3) = *running {
+          self.infer(e3);
+        }
+
+        Type::Unit
+      },
+      Range(ref e1, ref e2, ref e3) => {
+        self.expect(e1, &Type::Int);
+        self.expect(e2, &Type::Int);
+        self.expect(e3, &Type::Int);
+        Type::Unit
+      },
+      Decl(ref dec, ref x, ref t, ref e2, ref e3) => {
+        let mut t2 = self.infer(e2);
+
+        if t.is_some() {
+          let t = self.annotation(t);
+          self.unify_at(e2, &t, &t2);
+          t2 = t;
+        }
+
+        let scheme = match *dec {
+          // pokeballs never change, so they can be polymorphic
+          Dec::DConst => self.generalize(&t2),
+          Dec::DVar => mono(t2, true),
+        };
+
+        self.scoped(|checker| {
+          if let Some(x) = name_of(x) {
+            checker.bind(x, scheme);
+          }
+
+          checker.infer(e3)
+        })
+      },
+      Func(ref name, ref body, ref params, ref ts, ref result) => self.func(name, body, params, ts, result),
+      FnCall(ref f, ref args) => self.call(f, args),
+      // whatever binds a builtin's name by the time it is called is called instead
+      Builtin(ref b, ref args) => match self.symbols.lookup(b.name()).filter(|x| self.lookup(*x).is_some()) {
+        Some(x) => self.call(&Var(x), args),
+        None => self.builtin(b, args),
+      },
+      Scope(ref e1, _) | Rope(ref e1) => self.infer(e1),
+      Print(ref e1) => {
+        self.infer(e1);
+        Type::Unit
+      },
+      PrintVarName(ref e1) => {
+        self.infer(e1);
+        Type::Unit
+      },
+      Give(ref e1) => self.bike(e1, true),
+      PartyLit(ref es) | Party(ref es) => {
+        let item = self.fresh();
+
+        for e in es.iter() {
+          self.expect(e, &item);
+        }
+
+        Type::Party(Box::new(item))
+      },
+      DexLit(ref entries) | Dex(ref entries) => {
+        let (k, v) = (self.fresh(), self.fresh());
+
+        for (ek, ev) in entries.iter() {
+          self.expect(ek, &k);
+          self.expect(ev, &v);
+        }
+
+        Type::Dex(Box::new(k), Box::new(v))
+      },
+      Index(ref e1, ref e2) => {
+        let ty = self.infer(e1);
+
+        match self.resolve(&ty) {
+          Type::Dex(k, v) => {
+            self.expect(e2, &k);
+            *v
+          },
+          Type::Party(item) => {
+            self.expect(e2, &Type::Int);
+            *item
+          },
+          Type::Var(_) => {
+            let item = self.fresh();
+            self.unify_at(e1, &Type::Party(Box::new(item.clone())), &ty);
+            self.expect(e2, &Type::Int);
+            item
+          },
+          ty => {
+            self.error(TypeError::NotIndexable(ty));
+            self.infer(e2);
+            self.fresh()
+          },
+        }
+      },
+      CardDecl(ref name, ref fields, ref e3) => {
+        let fields = fields.iter().map(|x| (x.clone(), self.fresh())).collect();
+        self.cards.insert(name.clone(), fields);
+        self.infer(e3)
+      },
+      CardLit(ref name, ref fields, ref base) => self.card(name, fields, base.as_ref().map(|b| &**b)),
+      Card(ref name, ref fields) => {
+        let declared = self.cards.get(name).cloned().unwrap_or_default();
+
+        for (x, v) in fields.iter() {
+          if let Some(field) = declared.iter().find(|field| field.0 == *x) {
+            self.expect(v, &field.1);
+          }
+        }
+
+        Type::Card(name.clone())
+      },
+      Field(ref e1, ref x) => self.field(e1, x),
+      TypeDecl(ref name, ref variants, ref e3) => {
+        self.variants.insert(name.clone(), variants.iter().map(|v| (v.0.clone(), v.1.len())).collect());
+
+        self.scoped(|checker| {
+          for (c, fields) in variants.iter() {
+            checker.ctors.insert(c.clone(), (name.clone(), fields.len()));
+            let ty = checker.ctor(name, fields.len());
+            let scheme = checker.generalize(&ty);
+            let c = checker.symbols.intern(c);
+            checker.bind(c, scheme);
+          }
+
+          checker.infer(e3)
+        })
+      },
+      Ctor(ref name, _, n) => self.ctor(name, n),
+      Variant(ref name, _, _) => Type::Named(name.clone()),
+      Evolve(ref e1, ref arms) => {
+        let ty = self.infer(e1);
+        let result = self.fresh();
+
+        for (p, body) in arms.iter() {
+          self.scoped(|checker| {
+            checker.pattern(p, &ty);
+            checker.expect(body, &result)
+          });
+        }
+
+        let missing = self.missing_patterns(&ty, arms);
+        if !missing.is_empty() {
+          self.error(TypeError::NonExhaustiveMatch(missing));
+        }
+
+        result
+      },
+      Flee(ref e1) => {
+        let ty = self.infer(e1);
+
+        if let Some(ret) = self.returns.last().cloned() {
+          self.unify_at(e1, &ret, &ty);
+        }
+
+        self.fresh()
+      },
+      Faint(ref e1) => {
+        self.infer(e1);
+        self.fresh()
+      },
+      Escape(ref body, ref rope, ref finally) => {
+        let ty = self.infer(body);
+
+        if let Some((ref x, ref e2)) = *rope {
+          self.scoped(|checker| {
+            // a faint can carry any value, and meowth's own errors a move
+            let caught = checker.fresh();
+            checker.bind(*x, mono(caught, false));
+            checker.expect(e2, &ty)
+          });
+        }
+
+        if let Some(ref e3) = *finally {
+          self.infer(e3);
+        }
+
+        ty
+      },
+      Break(_) | Continue(_) | Unwind(_) => self.fresh(),
+      Import(ref path) | Module(ref path, _) => Type::Module(path.clone()),
+    }
+  }
+
+  fn ctor(&mut self, name: &str, n: usize) -> Type {
+    match n {
+      0 => Type::Named(name.to_string()),
+      n => Type::Attack((0..n).map(|_| self.fresh()).collect(), Box::new(Type::Named(name.to_string()))),
+    }
+  }
+}
@@ -0,0 +1,82 @@
+use expr::Expr;
+use interpreter::{Interpreter, Result};
+use runtime_error::RuntimeError;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Progress {
+  // out of steps for now, call run_for again to carry on
+  Pending,
+  Done(Expr),
+}
+
+// a program part way through evaluation, advanced a few steps at a time so
+// many scripts can share a frame budget
+pub struct Execution {
+  interpreter: Interpreter,
+  e: Expr,
+  steps: usize,
+  error: Option<RuntimeError>,
+}
+
+pub fn start(input: &str) -> Result<Execution> {
+  Interpreter::new().start(input).map_err(|(_, err)| err)
+}
+
+impl Execution {
+  pub fn new(interpreter: Interpreter, e: Expr) -> Execution {
+    Execution {
+      interpreter,
+      e,
+      steps: 0,
+      error: None,
+    }
+  }
+
+  // takes at most `steps` steps; a failed execution keeps its error
+  pub fn run_for(&mut self, steps: usize) -> Result<Progress> {
+    let symbols = self.interpreter.symbols().clone();
+    symbols.view(|| self.steps_for(steps))
+  }
+
+  fn steps_for(&mut self, steps: usize) -> Result<Progress> {
+    if let Some(ref err) = self.error {
+      return Err(err.clone());
+    }
+
+    for _ in 0..steps {
+      if self.e.is_value() {
+        break;
+      }
+
+      match self.interpreter.step(self.e.clone()) {
+        Ok(e) => {
+          self.e = e;
+          self.steps += 1;
+        },
+        Err(err) => {
+          self.error = Some(err.clone());
+          return Err(err);
+        },
+      }
+    }
+
+    if self.e.is_value() {
+      Ok(Progress::Done(self.e.clone()))
+    } else {
+      Ok(Progress::Pending)
+    }
+  }
+
+  // steps taken so far, across every call to run_for
+  pub fn steps(&self) -> usize {
+    self.steps
+  }
+
+  pub fn interpreter(&self) -> &Interpreter {
+    &self.interpreter
+  }
+
+  pub fn into_interpreter(self) -> Interpreter {
+    self.interpreter
+  }
+}
@@ -1,14 +1,18 @@
 use expr::Expr::*;
+use printer::source;
 use runtime_error::RuntimeError;
+use serde::{Serialize, Deserialize};
+use symbol::Symbol;
+use types::Type;
 use std::fmt;
 
-#[derive(Clone, Debug, PartialEq)] 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)] 
 pub enum UnOp {
   Not,
   Neg,
 }
 
-#[derive(Clone, Debug, PartialEq)] 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)] 
 pub enum BinOp {
   Plus,
   Minus,
@@ -27,49 +31,202 @@ pub enum BinOp {
   Assign,
 }
 
-#[derive(Clone, Debug, PartialEq)] 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)] 
+pub enum Builtin {
+  Len,
+  Substr,
+  CharAt,
+  Push,
+  Pop,
+  Get,
+  Set,
+  Has,
+  Remove,
+  Keys,
+}
+
+impl Builtin {
+  pub const ALL: &'static [Builtin] = &[
+    Builtin::Len, Builtin::Substr, Builtin::CharAt, Builtin::Push, Builtin::Pop,
+    Builtin::Get, Builtin::Set, Builtin::Has, Builtin::Remove, Builtin::Keys,
+  ];
+
+  // the builtin a call by this name makes, when nothing binds the name
+  pub fn from_name(name: &str) -> Option<Builtin> {
+    Builtin::ALL.iter().find(|b| b.name() == name).cloned()
+  }
+
+  pub fn name(&self) -> &'static str {
+    match *self {
+      Builtin::Len => "len",
+      Builtin::Substr => "substr",
+      Builtin::CharAt => "char_at",
+      Builtin::Push => "push",
+      Builtin::Pop => "pop",
+      Builtin::Get => "get",
+      Builtin::Set => "set",
+      Builtin::Has => "has",
+      Builtin::Remove => "remove",
+      Builtin::Keys => "keys",
+    }
+  }
+
+  pub fn arity(&self) -> usize {
+    match *self {
+      Builtin::Len => 1,
+      Builtin::Substr => 3,
+      Builtin::CharAt => 2,
+      Builtin::Push => 2,
+      Builtin::Pop => 1,
+      Builtin::Get => 2,
+      Builtin::Set => 3,
+      Builtin::Has => 2,
+      Builtin::Remove => 2,
+      Builtin::Keys => 1,
+    }
+  }
+
+  // mutating builtins take the name of a bike rather than its value
+  pub fn mutates_first_arg(&self) -> bool {
+    match *self {
+      Builtin::Push | Builtin::Pop | Builtin::Set | Builtin::Remove => true,
+      _ => false,
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)] 
 pub enum Dec {
   DVar,
   DConst
 }
 
-#[derive(Clone, Debug, PartialEq)] 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)] 
+pub enum Pattern {
+  Wildcard,
+  Bind(Symbol),
+  Lit(Expr),
+  Ctor(String, Vec<Pattern>),
+}
+
+impl Pattern {
+  pub fn is_irrefutable(&self) -> bool {
+    match *self {
+      Pattern::Wildcard | Pattern::Bind(_) => true,
+      _ => false,
+    }
+  }
+}
+
+// where an expression was written, as byte offsets into its source
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+  pub start: usize,
+  pub end: usize,
+}
+
+impl Span {
+  // 1-based line and column of the start, counting characters
+  pub fn line_col(&self, source: &str) -> (usize, usize) {
+    let before = &source[..self.start.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+
+    (line, column)
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)] 
 pub enum Expr {
   Int(isize),
   Bool(bool),
-  Var(String),
+  Str(String),
+  Var(Symbol),
+  // a variable the resolver found a slot for: its name, how many frames down
+  // from the top its binding is, and where in that frame
+  Local(Symbol, usize, usize),
   Undefined,
   Bop(BinOp, Box<Expr>, Box<Expr>),
   Uop(UnOp, Box<Expr>),
   Ternary(Box<Expr>, Box<Expr>, Box<Expr>),
-  While(Box<Expr>, Box<Expr>, Box<Expr>, Box<Expr>, Box<Expr>),
-  Decl(Dec, Box<Expr>, Box<Expr>, Box<Expr>),
-  Func(Option<Box<Expr>>, Box<Expr>, Vec<Expr>),
+  While(Option<String>, Box<Expr>, Box<Expr>, Box<Expr>, Box<Expr>, Option<Box<Expr>>, Box<Expr>),
+  // the name, its annotation if it has one, its value and the rest of the block
+  Decl(Dec, Box<Expr>, Option<Type>, Box<Expr>, Box<Expr>),
+  // name, body, params, and the annotations of each param and of the result
+  Func(Option<Box<Expr>>, Box<Expr>, Vec<Expr>, Vec<Option<Type>>, Option<Box<Type>>),
   FnCall(Box<Expr>, Vec<Expr>),
-  Scope(Box<Expr>),
+  // a running attack's body, and the annotation its result must meet
+  Scope(Box<Expr>, Option<Box<Type>>),
   Print(Box<Expr>),
   PrintVarName(Box<Expr>),
   Give(Box<Expr>),
+  Builtin(Builtin, Vec<Expr>),
+  PartyLit(Vec<Expr>),
+  Party(Vec<Expr>),
+  Index(Box<Expr>, Box<Expr>),
+  DexLit(Vec<(Expr, Expr)>),
+  Dex(Vec<(Expr, Expr)>),
+  CardDecl(String, Vec<String>, Box<Expr>),
+  CardLit(String, Vec<(String, Expr)>, Option<Box<Expr>>),
+  Card(String, Vec<(String, Expr)>),
+  Field(Box<Expr>, String),
+  TypeDecl(String, Vec<(String, Vec<String>)>, Box<Expr>),
+  Ctor(String, String, usize),
+  Variant(String, String, Vec<Expr>),
+  Evolve(Box<Expr>, Vec<(Pattern, Expr)>),
+  Flee(Box<Expr>),
+  Break(Option<String>),
+  Continue(Option<String>),
+  Range(Box<Expr>, Box<Expr>, Box<Expr>),
+  For(Option<String>, Symbol, Box<Expr>, Box<Expr>, Option<Box<Expr>>),
+  Escape(Box<Expr>, Option<(Symbol, Box<Expr>)>, Option<Box<Expr>>),
+  Rope(Box<Expr>),
+  Faint(Box<Expr>),
+  // only lives while an error unwinds through a finally, never in a value
+  #[serde(skip)]
+  Unwind(Box<RuntimeError>),
+  Import(String),
+  Module(String, Vec<(String, Expr)>),
+  // only made by parse_with_spans, for checks that point back at the source
+  At(Span, Box<Expr>),
+  // a body the resolver laid out a frame for, with the names of its slots in order
+  Framed(Vec<Symbol>, Box<Expr>),
 }
 
 impl Expr {
   pub fn is_func(&self) -> bool {
     match *self {
-      Func(_, _, _) => true,
+      Func(_, _, _, _, _) => true,
+      _ => false,
+    }
+  }
+
+  pub fn is_ctor(&self) -> bool {
+    match *self {
+      Ctor(_, _, _) => true,
       _ => false,
     }
   }
 
   pub fn is_var(&self) -> bool {
     match *self {
-      Var(_) => true,
+      Var(_) | Local(_, _, _) => true,
+      _ => false,
+    }
+  }
+
+  pub fn is_dex(&self) -> bool {
+    match *self {
+      Dex(_) => true,
       _ => false,
     }
   }
 
   pub fn is_value(&self) -> bool {
     match *self {
-      Int(_) | Bool(_) | Func(_, _, _) | Undefined => true,
+      Range(ref e1, ref e2, ref e3) => e1.is_value() && e2.is_value() && e3.is_value(),
+      Int(_) | Bool(_) | Str(_) | Func(_, _, _, _, _) | Party(_) | Dex(_) | Card(_, _) |
+        Ctor(_, _, _) | Variant(_, _, _) | Module(_, _) | Undefined => true,
       _ => false,
     }
   }
@@ -81,9 +238,37 @@ impl Expr {
     }
   }
 
-  pub fn to_var(&self) -> Result<String, RuntimeError> {
+  pub fn to_str(&self) -> Result<String, RuntimeError> {
+    match *self {
+      Str(ref s) => Ok(s.clone()),
+      _ => Err(RuntimeError::InvalidTypeConversion("str".to_string(), self.clone())),
+    }
+  }
+
+  pub fn to_party(&self) -> Result<Vec<Expr>, RuntimeError> {
     match *self {
-      Var(ref x) => Ok(x.clone()),
+      Party(ref es) => Ok(es.clone()),
+      _ => Err(RuntimeError::InvalidTypeConversion("party".to_string(), self.clone())),
+    }
+  }
+
+  pub fn to_dex(&self) -> Result<Vec<(Expr, Expr)>, RuntimeError> {
+    match *self {
+      Dex(ref entries) => Ok(entries.clone()),
+      _ => Err(RuntimeError::InvalidTypeConversion("dex".to_string(), self.clone())),
+    }
+  }
+
+  pub fn to_card(&self) -> Result<(String, Vec<(String, Expr)>), RuntimeError> {
+    match *self {
+      Card(ref name, ref fields) => Ok((name.clone(), fields.clone())),
+      _ => Err(RuntimeError::InvalidTypeConversion("card".to_string(), self.clone())),
+    }
+  }
+
+  pub fn to_var(&self) -> Result<Symbol, RuntimeError> {
+    match *self {
+      Var(x) | Local(x, _, _) => Ok(x),
       _ => Err(RuntimeError::InvalidTypeConversion("var".to_string(), self.clone())),
     }
   }
@@ -96,14 +281,82 @@ impl Expr {
   }
 }
 
+// strings are quoted when printed inside a collection
+fn fmt_item(e: &Expr, f: &mut fmt::Formatter) -> fmt::Result {
+  match *e {
+    Str(ref s) => write!(f, "{:?}", s),
+    _ => write!(f, "{}", e),
+  }
+}
+
 impl fmt::Display for Expr {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match *self {
       Int(n) => write!(f, "{}", n),
       Bool(true) => write!(f, "win"),
       Bool(false) => write!(f, "lose"),
+      Str(ref s) => write!(f, "{}", s),
+      Party(ref es) => {
+        write!(f, "[")?;
+
+        for (i, e) in es.iter().enumerate() {
+          if i > 0 {
+            write!(f, ", ")?;
+          }
+
+          fmt_item(e, f)?;
+        }
+
+        write!(f, "]")
+      },
+      Dex(ref entries) if entries.is_empty() => write!(f, "dex {{}}"),
+      Dex(ref entries) => {
+        write!(f, "dex {{ ")?;
+
+        for (i, (k, v)) in entries.iter().enumerate() {
+          if i > 0 {
+            write!(f, ", ")?;
+          }
+
+          fmt_item(k, f)?;
+          write!(f, ": ")?;
+          fmt_item(v, f)?;
+        }
+
+        write!(f, " }}")
+      },
+      Module(ref path, _) => write!(f, "module {:?}", path),
+      Variant(_, ref name, ref vs) if vs.is_empty() => write!(f, "{}", name),
+      Variant(_, ref name, ref vs) => {
+        write!(f, "{}(", name)?;
+
+        for (i, v) in vs.iter().enumerate() {
+          if i > 0 {
+            write!(f, ", ")?;
+          }
+
+          fmt_item(v, f)?;
+        }
+
+        write!(f, ")")
+      },
+      Card(ref name, ref fields) => {
+        write!(f, "{} {{ ", name)?;
+
+        for (i, (x, v)) in fields.iter().enumerate() {
+          if i > 0 {
+            write!(f, ", ")?;
+          }
+
+          write!(f, "{}: ", x)?;
+          fmt_item(v, f)?;
+        }
+
+        write!(f, " }}")
+      },
       Undefined => write!(f, "()"),
-      _ => write!(f, "cannot print this thing: {:?}", self)
+      // anything else still has to run, so it is shown as code
+      _ => write!(f, "{}", source(self)),
     }
   }
 }
@@ -0,0 +1,367 @@
+use parser::lexer::{tokenize_with_trivia, Trivia, KEYWORDS, CONTEXTUAL};
+use parser::parser::parse;
+use parser::parser_error::ParserError;
+use parser::token::Token;
+use printer::quote;
+use symbol::Interner;
+
+const INDENT: &str = "  ";
+
+// a lossless syntax tree: brackets hold everything up to their match, and
+// every token keeps the comments and line breaks that came before it
+enum Tree {
+  Leaf(Vec<Trivia>, Token),
+  // trivia before the opener, opener, contents, trivia before the closer, closer
+  Group(Vec<Trivia>, Token, Vec<Tree>, Vec<Trivia>, Token),
+}
+
+fn is_opener(token: &Token) -> bool {
+  matches!(*token, Token::LParen | Token::LBracket | Token::LSquare)
+}
+
+fn is_closer(token: &Token) -> bool {
+  matches!(*token, Token::RParen | Token::RBracket | Token::RSquare)
+}
+
+fn build(tokens: Vec<(Vec<Trivia>, Token)>) -> (Vec<Tree>, Vec<Trivia>) {
+  let mut open: Vec<(Vec<Trivia>, Token, Vec<Tree>)> = Vec::new();
+  let mut trees = Vec::new();
+  let mut end = Vec::new();
+
+  for (trivia, token) in tokens {
+    let tree = if token == Token::EOF {
+      end = trivia;
+      continue;
+    } else if is_opener(&token) {
+      open.push((trivia, token, Vec::new()));
+      continue;
+    } else if is_closer(&token) && !open.is_empty() {
+      let (open_trivia, opener, children) = open.pop().unwrap();
+      Tree::Group(open_trivia, opener, children, trivia, token)
+    } else {
+      Tree::Leaf(trivia, token)
+    };
+
+    match open.last_mut() {
+      Some(group) => group.2.push(tree),
+      None => trees.push(tree),
+    }
+  }
+
+  // the parser can stop before the end of the input, leaving brackets unclosed
+  while let Some((trivia, opener, children)) = open.pop() {
+    let mut flat = vec!(Tree::Leaf(trivia, opener));
+    flat.extend(children);
+
+    match open.last_mut() {
+      Some(group) => group.2.extend(flat),
+      None => trees.extend(flat),
+    }
+  }
+
+  (trees, end)
+}
+
+fn leading(tree: &Tree) -> &[Trivia] {
+  match *tree {
+    Tree::Leaf(ref trivia, _) | Tree::Group(ref trivia, _, _, _, _) => trivia,
+  }
+}
+
+fn is_token(tree: &Tree, token: &Token) -> bool {
+  matches!(*tree, Tree::Leaf(_, ref t) if t == token)
+}
+
+// a block its author spread over lines, or put comments in, gets a line per statement
+fn is_broken(children: &[Tree], close: &[Trivia]) -> bool {
+  children.iter().map(leading).chain(Some(close)).any(|trivia| !trivia.is_empty())
+}
+
+fn text(token: &Token, symbols: &Interner) -> String {
+  if let Some(k) = KEYWORDS.iter().find(|k| k.1 == *token) {
+    return k.0.to_string();
+  }
+
+  let text = match *token {
+    Token::Var(x) => return symbols.name(x).map_or_else(String::new, |name| name.to_string()),
+    Token::Int(n) => return n.to_string(),
+    Token::Str(ref s) => return quote(s),
+    Token::Plus => "+",
+    Token::Minus => "-",
+    Token::Times => "*",
+    Token::Div => "/",
+    Token::Mod => "%",
+    Token::LParen => "(",
+    Token::RParen => ")",
+    Token::Ne => "!=",
+    Token::Leq => "<=",
+    Token::Lt => "<",
+    Token::Not => "!",
+    Token::And => "&&",
+    Token::Or => "||",
+    Token::Seq => ";",
+    Token::Ternary => "?",
+    Token::Else => ":",
+    Token::Assign => "=",
+    Token::LBracket => "{",
+    Token::RBracket => "}",
+    Token::LSquare => "[",
+    Token::RSquare => "]",
+    Token::Comma => ",",
+    Token::Dot => ".",
+    Token::DotDot => "..",
+    Token::Pipe => "|",
+    Token::Arrow => "=>",
+    Token::Returns => "->",
+    _ => "",
+  };
+
+  text.to_string()
+}
+
+// whether a token can end an operand, so what follows is an operator, an index or a call
+fn ends_value(token: &Token) -> bool {
+  matches!(*token,
+    Token::Var(_) | Token::Int(_) | Token::Str(_) | Token::Bool(_) |
+    Token::RParen | Token::RSquare | Token::RBracket | Token::Break | Token::Continue)
+}
+
+// tokens that take their arguments with no space before the (
+fn is_callee(token: &Token) -> bool {
+  matches!(*token,
+    Token::Var(_) | Token::Print | Token::PrintVarName | Token::Give | Token::RBracket)
+}
+
+struct Writer {
+  out: String,
+  indent: usize,
+  line_start: bool,
+  // a line comment was written, so the next token starts a new line
+  need_newline: bool,
+  // the first token of a statement is next and goes on a line of its own
+  statement_start: bool,
+  prev: Option<Token>,
+  before_prev: Option<Token>,
+  unary_minus: bool,
+  // unanswered ? in each open bracket, so a : can tell a ternary from a field
+  ternaries: Vec<usize>,
+  // what names were interned in while parsing
+  symbols: Interner,
+}
+
+impl Writer {
+  fn new(symbols: Interner) -> Writer {
+    Writer {
+      out: String::new(),
+      indent: 0,
+      line_start: true,
+      need_newline: false,
+      statement_start: false,
+      prev: None,
+      before_prev: None,
+      unary_minus: false,
+      ternaries: vec!(0),
+      symbols,
+    }
+  }
+
+  fn newline(&mut self) {
+    let len = self.out.trim_end_matches(' ').len();
+    self.out.truncate(len);
+    self.out.push('\n');
+    self.line_start = true;
+    self.need_newline = false;
+  }
+
+  fn ensure_newline(&mut self) {
+    if !self.line_start {
+      self.newline();
+    }
+  }
+
+  // never at the start of the file or of a block, and never two in a row
+  fn blank_line(&mut self) {
+    self.ensure_newline();
+
+    if !(self.out.is_empty() || self.out.ends_with("{\n") || self.out.ends_with("\n\n")) {
+      self.out.push('\n');
+    }
+  }
+
+  fn write(&mut self, text: &str, space: bool) {
+    if self.need_newline {
+      self.newline();
+    }
+
+    if self.line_start {
+      for _ in 0..self.indent {
+        self.out.push_str(INDENT);
+      }
+    } else if space {
+      self.out.push(' ');
+    }
+
+    self.out.push_str(text);
+    self.line_start = false;
+  }
+
+  fn trivia(&mut self, trivia: &[Trivia]) {
+    let mut newlines = 0;
+
+    for t in trivia.iter() {
+      match *t {
+        Trivia::Newlines(n) => newlines += n,
+        Trivia::Comment(ref comment) => {
+          if newlines == 0 && !self.line_start && !self.need_newline {
+            // a comment after code stays on its line
+            self.write(comment, true);
+          } else {
+            if newlines > 1 {
+              self.blank_line();
+            } else {
+              self.ensure_newline();
+            }
+
+            self.write(comment, false);
+          }
+
+          self.need_newline = true;
+          newlines = 0;
+        },
+      }
+    }
+
+    if self.statement_start {
+      self.statement_start = false;
+
+      if newlines > 1 {
+        self.blank_line();
+      } else {
+        self.ensure_newline();
+      }
+    }
+  }
+
+  // in, by and as lex as names, but are written like the keywords they mostly are
+  fn is_word(&self, token: &Token) -> bool {
+    match *token {
+      Token::Var(x) => self.symbols.name(x).is_some_and(|name| CONTEXTUAL.contains(&&*name)),
+      _ => false,
+    }
+  }
+
+  fn ends_value(&self, token: &Token) -> bool {
+    ends_value(token) && !self.is_word(token)
+  }
+
+  fn space_before(&self, next: &Token) -> bool {
+    let prev = match self.prev {
+      Some(ref prev) => prev,
+      None => return false,
+    };
+
+    match *next {
+      Token::RParen | Token::RSquare | Token::Comma | Token::Seq | Token::Dot => return false,
+      Token::DotDot => return !self.ends_value(prev),
+      Token::Else => return self.ternaries.last().is_some_and(|n| *n > 0),
+      // a loop label is not called, as in defend outer (...)
+      Token::LParen if is_callee(prev) && !self.is_word(prev) && self.before_prev != Some(Token::While) => return false,
+      Token::LSquare if self.ends_value(prev) || *prev == Token::Dex => return false,
+      Token::RBracket if is_opener(prev) => return false,
+      _ => {},
+    }
+
+    match *prev {
+      Token::LParen | Token::LSquare | Token::Dot | Token::DotDot | Token::Not => false,
+      Token::Minus => !self.unary_minus,
+      _ => true,
+    }
+  }
+
+  fn token(&mut self, token: &Token) {
+    let space = self.space_before(token);
+
+    match *token {
+      Token::Ternary => {
+        if let Some(n) = self.ternaries.last_mut() {
+          *n += 1;
+        }
+      },
+      Token::Else => {
+        if let Some(n) = self.ternaries.last_mut() {
+          *n = n.saturating_sub(1);
+        }
+      },
+      Token::Minus => self.unary_minus = !self.prev.as_ref().is_some_and(|prev| self.ends_value(prev)),
+      _ => {},
+    }
+
+    let text = text(token, &self.symbols);
+    self.write(&text, space);
+    self.before_prev = self.prev.take();
+    self.prev = Some(token.clone());
+  }
+
+  fn tree(&mut self, tree: &Tree) {
+    match *tree {
+      Tree::Leaf(ref trivia, ref token) => {
+        self.trivia(trivia);
+        self.token(token);
+      },
+      Tree::Group(ref trivia, ref opener, ref children, ref close, ref closer) => {
+        self.trivia(trivia);
+        self.token(opener);
+        self.ternaries.push(0);
+
+        if *opener == Token::LBracket && is_broken(children, close) {
+          self.indent += 1;
+          self.statements(children);
+          self.trivia(close);
+          self.indent -= 1;
+          self.ensure_newline();
+        } else {
+          for child in children.iter() {
+            self.tree(child);
+          }
+
+          self.trivia(close);
+        }
+
+        self.ternaries.pop();
+        self.token(closer);
+      },
+    }
+  }
+
+  // one statement per line, split after each ; or, in a list, each ,
+  fn statements(&mut self, trees: &[Tree]) {
+    let separator = if trees.iter().any(|t| is_token(t, &Token::Seq)) { Token::Seq } else { Token::Comma };
+    let mut start = true;
+
+    for tree in trees.iter() {
+      self.statement_start = start;
+      self.tree(tree);
+      start = is_token(tree, &separator);
+    }
+  }
+}
+
+// reprints a program with canonical spacing and indentation, keeping its
+// comments and single blank lines
+pub fn format(input: &str) -> Result<String, ParserError> {
+  let symbols = Interner::new();
+  parse(input, &symbols)?;
+
+  let (trees, end) = build(tokenize_with_trivia(input, &symbols)?);
+  let mut writer = Writer::new(symbols);
+
+  writer.statements(&trees);
+  writer.trivia(&end);
+
+  let mut out = writer.out.trim_end().to_string();
+  if !out.is_empty() {
+    out.push('\n');
+  }
+
+  Ok(out)
+}
@@ -1,38 +1,373 @@
-use parser::parser::{parse};
-use expr::Expr;
+use parser::parser::{parse, parse_with_spans};
+use expr::{Expr, Builtin, Pattern};
 use expr::Expr::*;
 use expr::UnOp::*;
 use expr::BinOp::*;
 use expr::Dec::*;
-use state::State;
+use state::{State, Binding, ERROR_CARD};
+use execution::Execution;
 use runtime_error::RuntimeError;
+use trace::{rule, TraceStep, Tracer};
+use checker::Checker;
+use resolver::resolve;
+use symbol::{Interner, Symbol};
+use types::Type;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::mem;
+use std::path::PathBuf;
 use std::result;
 
 pub type Result<T> = result::Result<T, RuntimeError>;
 
+pub const DEFAULT_PARTY_CAPACITY: usize = 6;
+
 pub struct Interpreter {
   pub state: State,
+  party_capacity: usize,
+  search_paths: Vec<PathBuf>,
+  // evaluated modules by canonical path, so each file runs once
+  modules: HashMap<PathBuf, Expr>,
+  // modules part way through evaluation, for cycle detection
+  loading: Vec<PathBuf>,
+  tracer: Option<Tracer>,
+  // how many steps are on the stack, and the rule fired below the current one
+  depth: usize,
+  fired: Option<String>,
+  // whether programs are type checked before they run
+  typecheck: bool,
+  // every name lexed so far, so each is allocated once however often it is
+  // used; a Symbol is its index in here
+  symbols: Interner,
+}
+
+// mutating builtins leave their first argument as the name of a bike
+fn builtin_args_ready(b: &Builtin, es: &[Expr]) -> bool {
+  es.iter().enumerate().all(|(i, e)| {
+    e.is_value() || (i == 0 && b.mutates_first_arg() && e.is_var())
+  })
+}
+
+fn dex_get(entries: &[(Expr, Expr)], k: &Expr) -> Result<Expr> {
+  match entries.iter().find(|entry| entry.0 == *k) {
+    Some(entry) => Ok(entry.1.clone()),
+    None => Err(RuntimeError::KeyNotFound(k.clone())),
+  }
+}
+
+// keys keep the position of their first insertion
+fn dex_insert(entries: &mut Vec<(Expr, Expr)>, k: Expr, v: Expr) {
+  match entries.iter().position(|entry| entry.0 == k) {
+    Some(i) => entries[i].1 = v,
+    None => entries.push((k, v)),
+  }
+}
+
+fn match_pattern(p: &Pattern, v: &Expr, bindings: &mut Vec<(Symbol, Expr)>) -> bool {
+  match *p {
+    Pattern::Wildcard => true,
+    Pattern::Bind(x) => {
+      bindings.push((x, v.clone()));
+      true
+    },
+    Pattern::Lit(ref l) => *l == *v,
+    Pattern::Ctor(ref c, ref ps) => match *v {
+      Variant(_, ref vc, ref vs) if c == vc && ps.len() == vs.len() => {
+        ps.iter().zip(vs.iter()).all(|(p, v)| match_pattern(p, v, bindings))
+      },
+      _ => false,
+    },
+  }
+}
+
+// what a rope sees when an error reaches it; control flow is never caught
+fn caught(err: &RuntimeError) -> Option<Expr> {
+  match *err {
+    RuntimeError::Raised(ref v) => Some(v.clone()),
+    RuntimeError::Flee(_) | RuntimeError::Break(_) | RuntimeError::Continue(_) => None,
+    ref err => Some(Card(ERROR_CARD.to_string(), vec!(
+      ("kind".to_string(), Str(err.kind().to_string())),
+      ("message".to_string(), Str(err.to_string())),
+    ))),
+  }
+}
+
+// module attacks are called from the importer's frames, so each one
+// rebinds the rest of its module before its body runs
+fn with_prelude(v: &Expr, exports: &[(Symbol, Expr)]) -> Expr {
+  match *v {
+    Func(ref name, ref body, ref params, ref annotations, ref result) => {
+      let (names, body) = framed(body);
+      let body = exports.iter()
+        .filter(|export| !params.contains(&Var(export.0)))
+        .fold(body.clone(), |e, export| {
+          Decl(DConst, Box::new(Var(export.0)), None, Box::new(export.1.clone()), Box::new(e))
+        });
+
+      Func(name.clone(), Box::new(Framed(names.to_vec(), Box::new(body))), params.clone(), annotations.clone(), result.clone())
+    },
+    ref v => v.clone(),
+  }
+}
+
+// whether a value is of an annotated type. Attacks are only checked for how
+// many params they take, since what they return is not known until they run
+fn conforms(v: &Expr, t: &Type) -> bool {
+  match (v, t) {
+    (Int(_), Type::Int) | (Bool(_), Type::Bool) | (Str(_), Type::Str) | (Undefined, Type::Unit) => true,
+    (Party(vs), Type::Party(t)) => vs.iter().all(|v| conforms(v, t)),
+    (Dex(entries), Type::Dex(k, t)) => entries.iter().all(|e| conforms(&e.0, k) && conforms(&e.1, t)),
+    (Func(_, _, xs, _, _), Type::Attack(ps, _)) => xs.len() == ps.len(),
+    (Ctor(_, _, n), Type::Attack(ps, _)) => *n == ps.len(),
+    (Card(name, _), Type::Named(t)) | (Card(name, _), Type::Card(t)) => name == t,
+    (Variant(name, _, _), Type::Named(t)) => name == t,
+    (_, Type::Var(_)) => true,
+    _ => false,
+  }
+}
+
+fn check_annotation<X: fmt::Display>(x: X, v: &Expr, t: &Option<Type>) -> Result<()> {
+  match *t {
+    Some(ref t) if !conforms(v, t) => Err(RuntimeError::AnnotationMismatch(x.to_string(), Box::new(t.clone()), v.clone())),
+    _ => Ok(()),
+  }
+}
+
+// a result annotation is checked once the attack's frame is gone, whether
+// its body finished or fled
+fn check_result(v: &Expr, t: &Option<Box<Type>>) -> Result<()> {
+  match *t {
+    Some(ref t) => check_annotation("the result", v, &Some(*t.clone())),
+    None => Ok(()),
+  }
+}
+
+// the names the resolver laid out a body's frame with, and the body itself
+fn framed(e: &Expr) -> (&[Symbol], &Expr) {
+  match *e {
+    Framed(ref names, ref body) => (names, body),
+    ref e => (&[], e),
+  }
+}
+
+// where the resolver put a new binding in the top frame
+fn top_index(x: &Expr) -> Option<usize> {
+  match *x {
+    Local(_, 0, index) => Some(index),
+    _ => None,
+  }
+}
+
+// splits the next item of a for loop from the rest of what it iterates over
+fn next_item(iter: &Expr) -> Result<Option<(Expr, Expr)>> {
+  let next = match *iter {
+    Range(ref v1, ref v2, ref v3) => {
+      let (start, end, step) = (v1.to_int()?, v2.to_int()?, v3.to_int()?.abs());
+
+      if step == 0 {
+        return Err(RuntimeError::ZeroStep);
+      }
+
+      // ranges count towards their end in whichever direction it lies
+      let next = match start {
+        n if n < end => Some(::std::cmp::min(n + step, end)),
+        n if n > end => Some(::std::cmp::max(n - step, end)),
+        _ => None,
+      };
+
+      next.map(|n| (Int(start), Range(Box::new(Int(n)), v2.clone(), v3.clone())))
+    },
+    Party(ref es) => es.split_first().map(|(e, rest)| (e.clone(), Party(rest.to_vec()))),
+    Dex(ref entries) => entries.split_first().map(|(entry, rest)| (entry.0.clone(), Dex(rest.to_vec()))),
+    Str(ref s) => {
+      let mut chars = s.chars();
+      chars.next().map(|c| (Str(c.to_string()), Str(chars.collect())))
+    },
+    _ => return Err(RuntimeError::NotIterable(iter.clone())),
+  };
+
+  Ok(next)
 }
 
 impl Interpreter {
   pub fn new() -> Interpreter {
     Interpreter {
       state: State::new(),
+      party_capacity: DEFAULT_PARTY_CAPACITY,
+      search_paths: vec!(PathBuf::from(".")),
+      modules: HashMap::new(),
+      loading: Vec::new(),
+      tracer: None,
+      depth: 0,
+      fired: None,
+      typecheck: false,
+      symbols: Interner::new(),
     }
   }
 
+  pub fn set_search_paths(&mut self, paths: Vec<PathBuf>) {
+    self.search_paths = paths;
+  }
+
+  pub fn add_search_path<P: Into<PathBuf>>(&mut self, path: P) {
+    self.search_paths.push(path.into());
+  }
+
+  // calls tracer with every rule that fires, at the redex and for the whole program
+  pub fn set_tracer<F: FnMut(&TraceStep) + 'static>(&mut self, tracer: F) {
+    self.tracer = Some(Box::new(tracer));
+  }
+
+  pub fn clear_tracer(&mut self) {
+    self.tracer = None;
+  }
+
+  pub fn set_typecheck(&mut self, typecheck: bool) {
+    self.typecheck = typecheck;
+  }
+
+  // what names are interned in, to be put in view to show or save symbols
+  pub fn symbols(&self) -> &Interner {
+    &self.symbols
+  }
+
+  // the type of a program given what is bound so far, or every type error in it
+  pub fn check(&self, input: &str) -> Result<Type> {
+    self.symbols.view(|| {
+      let e = parse_with_spans(input, &self.symbols)?;
+      let mut checker = Checker::with_state(&self.state, &self.symbols);
+      let ty = checker.check(&e);
+      let errors = checker.errors(input);
+
+      if !errors.is_empty() {
+        return Err(RuntimeError::TypeErrors(errors));
+      }
+
+      Ok(ty)
+    })
+  }
+
+  pub fn set_party_capacity(&mut self, n: usize) {
+    self.party_capacity = n;
+  }
+
+  fn party(&self, es: Vec<Expr>) -> Result<Expr> {
+    if es.len() > self.party_capacity {
+      return Err(RuntimeError::PartyFull(self.party_capacity));
+    }
+
+    Ok(Party(es))
+  }
+
+  fn card(&self, name: &str, fields: &[(String, Expr)], base: Option<&Expr>) -> Result<Expr> {
+    let declared = self.state.card_fields(name.to_string())?;
+
+    let base_fields = match base {
+      Some(b) => match b.to_card()? {
+        (ref base_name, ref base_fields) if base_name == name => base_fields.clone(),
+        _ => return Err(RuntimeError::InvalidTypeConversion(name.to_string(), b.clone())),
+      },
+      None => Vec::new(),
+    };
+
+    if let Some(field) = fields.iter().find(|field| !declared.contains(&field.0)) {
+      return Err(RuntimeError::FieldNotFound(name.to_string(), field.0.clone()));
+    }
+
+    let values: Result<Vec<(String, Expr)>> = declared.into_iter().map(|x| {
+      // later fields win over earlier ones and over the base card
+      let v = fields.iter().rev().chain(base_fields.iter()).find(|field| field.0 == x);
+
+      match v {
+        Some(field) => Ok((x, field.1.clone())),
+        None => Err(RuntimeError::MissingField(name.to_string(), x)),
+      }
+    }).collect();
+
+    Ok(Card(name.to_string(), values?))
+  }
+
+  fn evolve(&mut self, v: &Expr, arms: &[(Pattern, Expr)]) -> Result<Expr> {
+    for (p, body) in arms.iter() {
+      let mut bindings = Vec::new();
+
+      if match_pattern(p, v, &mut bindings) {
+        // the arm runs in a frame of its own, holding the matched parts as pokeballs
+        let (names, body) = framed(body);
+        self.state.begin_scope(names);
+
+        for (x, v) in bindings {
+          self.state.alloc_const(x, v)?;
+        }
+
+        return Ok(Rope(Box::new(body.clone())));
+      }
+    }
+
+    // the checker reports the arms missing, here only the value is known
+    Err(RuntimeError::NonExhaustiveMatch(vec!(format!("{}", v))))
+  }
+
   pub fn step(&mut self, e: Expr) -> Result<Expr> {
+    if self.tracer.is_none() {
+      return self.reduce(e);
+    }
+
+    self.depth += 1;
+    self.fired = None;
+    let before = e.clone();
+    let result = self.reduce(e);
+    self.depth -= 1;
+
+    match result {
+      Ok(ref after) => {
+        // the innermost step that returns without a rule firing below it is the redex
+        let rule = match self.fired.take() {
+          None => {
+            let rule = rule(&before);
+            self.trace(self.depth, &rule, &before, after);
+            rule
+          },
+          Some(rule) => {
+            if self.depth == 0 {
+              self.trace(0, &rule, &before, after);
+            }
+            rule
+          },
+        };
+
+        self.fired = Some(rule);
+      },
+      // whoever catches the error is where the rule fires
+      Err(_) => self.fired = None,
+    }
+
+    result
+  }
+
+  fn trace(&mut self, depth: usize, rule: &str, before: &Expr, after: &Expr) {
+    if let Some(ref mut tracer) = self.tracer {
+      tracer(&TraceStep { depth, rule, before, after });
+    }
+  }
+
+  fn reduce(&mut self, e: Expr) -> Result<Expr> {
     debug!("step(e) : {:?}", e);
     debug!("step(self.state) : {:?}", self.state.mem);
 
     let e1 = match e.clone() {
       Var(x) => {
-        self.state.get(x.clone())?
+        self.state.get(x)?
+      },
+      Local(x, depth, index) => {
+        self.state.get_local(x, depth, index)?
       },
       /**
        * Values are ineligible for step
        */
-      Int(_) | Bool(_) | Func(_, _, _) | Undefined => {
+      Int(_) | Bool(_) | Str(_) | Func(_, _, _, _, _) | Party(_) | Dex(_) | Card(_, _) |
+        Ctor(_, _, _) | Variant(_, _, _) | Module(_, _) | Undefined => {
         debug!("stepping on a value {:?}", e);
         return Err(RuntimeError::SteppingOnValue(e));
       },
@@ -61,6 +396,10 @@ impl Interpreter {
         let n1 = e1.to_int()?;
         let n2 = e2.to_int()?;
 
+        if n2 == 0 {
+          return Err(RuntimeError::DivisionByZero);
+        }
+
         // rust % gives the remainder, not modulus
         let result = ((n1 % n2) + n2) % n2;
 
@@ -79,7 +418,11 @@ impl Interpreter {
         Bool(e1.to_int()? >= e2.to_int()?)
       },
       Bop(Plus, ref e1, ref e2) if e1.is_value() && e2.is_value() => {
-        Int(e1.to_int()? + e2.to_int()?)
+        match (*e1.clone(), *e2.clone()) {
+          (Str(s1), v2) => Str(format!("{}{}", s1, v2)),
+          (v1, Str(s2)) => Str(format!("{}{}", v1, s2)),
+          (v1, v2) => Int(v1.to_int()? + v2.to_int()?),
+        }
       },
       Bop(Minus, ref e1, ref e2) if e1.is_value() && e2.is_value() => {
         Int(e1.to_int()? - e2.to_int()?)
@@ -88,14 +431,29 @@ impl Interpreter {
         Int(e1.to_int()? * e2.to_int()?)
       },
       Bop(Div, ref e1, ref e2) if e1.is_value() && e2.is_value() => {
-        Int(e1.to_int()? / e2.to_int()?)
+        let n2 = e2.to_int()?;
+
+        if n2 == 0 {
+          return Err(RuntimeError::DivisionByZero);
+        }
+
+        Int(e1.to_int()? / n2)
       },
       Bop(Seq, ref v1, ref e2) if v1.is_value() => {
         *e2.clone()
       },
       Bop(Assign, ref v1, ref v2) if v1.is_var() && v2.is_value() => {
-        let x = v1.to_var()?;
-        self.state.assign(x, *v2.clone())?;
+        match **v1 {
+          Local(ref x, depth, index) => {
+            check_annotation(*x, v2, &self.state.annotation_local(*x, depth, index).cloned())?;
+            self.state.assign_local(*x, depth, index, *v2.clone())?
+          },
+          _ => {
+            let x = v1.to_var()?;
+            check_annotation(x, v2, &self.state.annotation(x).cloned())?;
+            self.state.assign(x, *v2.clone())?
+          },
+        }
         debug!("done assigning {:?}", self.state.mem);
         *v2.clone()
       },
@@ -105,21 +463,33 @@ impl Interpreter {
           false => *e3.clone(),
         }
       },
-      Decl(DConst, ref x, ref v1, ref e2) if v1.is_value() => {
-        self.state.alloc_const(x.to_var()?, *v1.clone())?;
+      Decl(DConst, ref x, ref t, ref v1, ref e2) if v1.is_value() => {
+        let index = top_index(x);
+        let x = x.to_var()?;
+        check_annotation(x, v1, t)?;
+        self.state.declare(x, index, Binding::Const(v1.clone()))?;
         *e2.clone()
       },
-      Decl(DVar, ref x, ref v1, ref e2) if x.is_var() && v1.is_value() => {
+      Decl(DVar, ref x, ref t, ref v1, ref e2) if x.is_var() && v1.is_value() => {
         debug!("allocing {:?}", v1);
-        self.state.alloc(x.to_var()?, *v1.clone())?;
+        let index = top_index(x);
+        let x = x.to_var()?;
+        check_annotation(x, v1, t)?;
+        self.state.declare(x, index, Binding::Bike(vec!(v1.clone()), t.clone()))?;
         *e2.clone()
       },
       // lambda lift so we can use iter() in guard
       // https://github.com/rust-lang/rfcs/issues/1006
       FnCall(ref v1, ref es) if v1.is_func() && (|| es.iter().all(|v| v.is_value()))() => {
         match **v1 {
-          Func(ref name, ref e1, ref xs) => {
-            self.state.begin_scope();
+          Func(ref name, ref e1, ref xs, ref ts, ref result) => {
+            // check the args before the scope opens, so a mismatch leaves no frame behind
+            for ((xn, en), tn) in xs.iter().zip(es.iter()).zip(ts.iter()) {
+              check_annotation(xn.to_var()?, en, tn)?;
+            }
+
+            let (names, body) = framed(e1);
+            self.state.begin_scope(names);
 
             // alloc the params
             for (xn, en) in xs.iter().zip(es.iter()) {
@@ -132,21 +502,193 @@ impl Interpreter {
               _ => {},
             };
 
-            Scope(Box::new(*e1.clone()))
+            Scope(Box::new(body.clone()), result.clone())
           },
           _ => return Err(RuntimeError::UnexpectedExpr("expected Func".to_string(), *v1.clone()))
         }
       },
-      Scope(ref v1) if v1.is_value() => {
+      FnCall(ref v1, ref es) if v1.is_ctor() && (|| es.iter().all(|v| v.is_value()))() => {
+        match **v1 {
+          Ctor(ref t, ref c, n) if n == es.len() => Variant(t.clone(), c.clone(), es.clone()),
+          Ctor(_, ref c, n) => return Err(RuntimeError::WrongNumberOfArgs(c.clone(), n, es.len())),
+          _ => return Err(RuntimeError::UnexpectedExpr("expected Ctor".to_string(), *v1.clone()))
+        }
+      },
+      TypeDecl(ref name, ref variants, ref e2) => {
+        self.state.declare_type(name.clone(), variants.clone());
+
+        for (c, fields) in variants.iter() {
+          let v = match fields.len() {
+            0 => Variant(name.clone(), c.clone(), Vec::new()),
+            n => Ctor(name.clone(), c.clone(), n),
+          };
+
+          self.state.alloc_const(self.symbols.intern(c), v)?;
+        }
+
+        *e2.clone()
+      },
+      Faint(ref v1) if v1.is_value() => {
+        return Err(RuntimeError::Raised(*v1.clone()));
+      },
+      Unwind(ref err) => {
+        return Err(*err.clone());
+      },
+      Escape(ref v1, _, ref finally) if v1.is_value() => {
+        match *finally {
+          Some(ref e3) => Bop(Seq, e3.clone(), v1.clone()),
+          None => *v1.clone(),
+        }
+      },
+      Rope(ref v1) if v1.is_value() => {
         self.state.end_scope();
         *v1.clone()
       },
-      While(ref v1, ref e1o, _, ref e2o, ref e3) if v1.is_value() => {
-        match v1.to_bool()? {
-          true => While(Box::new(*e1o.clone()), e1o.clone(), e2o.clone(), e2o.clone(), e3.clone()),
-          false => *e3.clone(),
+      Flee(ref v1) if v1.is_value() => {
+        // unwinds to the Scope of the nearest FnCall
+        return Err(RuntimeError::Flee(*v1.clone()));
+      },
+      Evolve(ref v1, ref arms) if v1.is_value() => {
+        self.evolve(v1, arms)?
+      },
+      Builtin(ref b, ref vs) if builtin_args_ready(b, vs) => {
+        // builtins are only names, so whatever binds one when it is called wins
+        match self.symbols.lookup(b.name()).filter(|x| self.state.contains(*x)) {
+          Some(x) => FnCall(Box::new(Var(x)), vs.clone()),
+          None => self.apply_builtin(b, vs)?,
         }
       },
+      PartyLit(ref vs) if (|| vs.iter().all(|v| v.is_value()))() => {
+        self.party(vs.clone())?
+      },
+      DexLit(ref entries) if (|| entries.iter().all(|entry| entry.0.is_value() && entry.1.is_value()))() => {
+        let mut dex = Vec::new();
+
+        for (k, v) in entries.iter() {
+          dex_insert(&mut dex, k.clone(), v.clone());
+        }
+
+        Dex(dex)
+      },
+      CardDecl(ref name, ref fields, ref e2) => {
+        self.state.declare_card(name.clone(), fields.clone());
+        *e2.clone()
+      },
+      CardLit(ref name, ref fields, ref base) if fields.iter().all(|field| field.1.is_value()) && base.as_ref().is_none_or(|b| b.is_value()) => {
+        self.card(name, fields, base.as_ref().map(|b| &**b))?
+      },
+      Import(ref path) => {
+        self.import(path)?
+      },
+      At(_, e1) => {
+        *e1
+      },
+      // whatever opens a frame takes its body out of this first
+      Framed(_, e1) => {
+        return Err(RuntimeError::UnexpectedExpr("a body outside its frame".to_string(), *e1));
+      },
+      Field(ref v1, ref x) if v1.is_value() && matches!(**v1, Module(_, _)) => {
+        match **v1 {
+          Module(ref path, ref exports) => match exports.iter().find(|export| export.0 == *x) {
+            Some(export) => export.1.clone(),
+            None => return Err(RuntimeError::ExportNotFound(path.clone(), x.clone())),
+          },
+          _ => return Err(RuntimeError::UnexpectedExpr("expected Module".to_string(), *v1.clone())),
+        }
+      },
+      Field(ref v1, ref x) if v1.is_value() => {
+        let (name, fields) = v1.to_card()?;
+
+        match fields.into_iter().find(|field| field.0 == *x) {
+          Some((_, v)) => v,
+          None => return Err(RuntimeError::FieldNotFound(name, x.clone())),
+        }
+      },
+      Index(ref v1, ref v2) if v1.is_value() && v2.is_value() && v1.is_dex() => {
+        dex_get(&v1.to_dex()?, v2)?
+      },
+      Index(ref v1, ref v2) if v1.is_value() && v2.is_value() => {
+        let es = v1.to_party()?;
+        let i = v2.to_int()?;
+
+        if i < 0 || i >= es.len() as isize {
+          return Err(RuntimeError::IndexOutOfBounds(i, es.len()));
+        }
+
+        es[i as usize].clone()
+      },
+      Scope(ref v1, ref t) if v1.is_value() => {
+        self.state.end_scope();
+        check_result(v1, t)?;
+        *v1.clone()
+      },
+      While(ref label, ref v1, ref e1o, ref e2, ref e2o, ref last, ref run) if v1.is_value() => {
+        let restart = |last: Option<Box<Expr>>| {
+          While(label.clone(), e1o.clone(), e1o.clone(), e2o.clone(), e2o.clone(), last, run.clone())
+        };
+
+        // once the condition holds the run clause is out of the picture
+        let ran = || last.clone().unwrap_or_else(|| Box::new(Undefined));
+
+        if !v1.to_bool()? {
+          match *last {
+            Some(ref v) => *v.clone(),
+            None => *run.clone(),
+          }
+        } else if e2.is_value() {
+          restart(Some(e2.clone()))
+        } else {
+          // a loop catches unlabelled control and control aimed at its own label
+          match self.step(*e2.clone()) {
+            Ok(e2) => While(label.clone(), v1.clone(), e1o.clone(), Box::new(e2), e2o.clone(), Some(ran()), run.clone()),
+            Err(RuntimeError::Break(ref l)) if l.is_none() || l == label => *ran(),
+            Err(RuntimeError::Continue(ref l)) if l.is_none() || l == label => restart(Some(ran())),
+            Err(err) => return Err(err),
+          }
+        }
+      },
+      For(ref label, x, ref v1, ref e2, None) if v1.is_value() => {
+        match next_item(v1)? {
+          Some((item, rest)) => {
+            // each pass gets a fresh binding in its own frame
+            let (names, body) = framed(e2);
+            self.state.begin_scope(names);
+            self.state.alloc_const(x, item)?;
+            For(label.clone(), x, Box::new(rest), e2.clone(), Some(Box::new(body.clone())))
+          },
+          None => Undefined,
+        }
+      },
+      For(ref label, x, ref v1, ref e2, Some(ref e3)) => {
+        let next = For(label.clone(), x, v1.clone(), e2.clone(), None);
+
+        if e3.is_value() {
+          self.state.end_scope();
+          next
+        } else {
+          match self.step(*e3.clone()) {
+            Ok(e3) => For(label.clone(), x, v1.clone(), e2.clone(), Some(Box::new(e3))),
+            Err(RuntimeError::Break(ref l)) if l.is_none() || l == label => {
+              self.state.end_scope();
+              Undefined
+            },
+            Err(RuntimeError::Continue(ref l)) if l.is_none() || l == label => {
+              self.state.end_scope();
+              next
+            },
+            Err(err) => {
+              self.state.end_scope();
+              return Err(err);
+            },
+          }
+        }
+      },
+      Break(ref label) => {
+        return Err(RuntimeError::Break(label.clone()));
+      },
+      Continue(ref label) => {
+        return Err(RuntimeError::Continue(label.clone()));
+      },
       Print(ref v1) if v1.is_value() => {
         println!("{}", v1);
         Expr::Undefined
@@ -154,16 +696,17 @@ impl Interpreter {
       PrintVarName(v1) => {
         let s = v1.to_var()?;
 
-        if !self.state.contains(s.clone()) {
-          return Err(RuntimeError::VariableNotFound(s));
+        if !self.state.contains(s) {
+          return Err(RuntimeError::VariableNotFound(s.to_string()));
         }
 
         println!("{}", s);
 
         Expr::Undefined
       },
-      Give(v1) => {
-        self.state.give(v1.to_var()?)?
+      Give(v1) => match *v1 {
+        Local(x, depth, index) => self.state.give_local(x, depth, index)?,
+        ref v1 => self.state.give(v1.to_var()?)?,
       },
       /**
        * Search Cases
@@ -191,34 +734,129 @@ impl Interpreter {
       Ternary(e1, e2, e3) => {
         Ternary(Box::new(self.step(*e1)?), e2, e3)
       },
-      While(ref e1, ref e1o, ref v2, ref e2o, ref e3) if v2.is_value() => {
-        While(Box::new(self.step(*e1.clone())?), e1o.clone(), v2.clone(), e2o.clone(), e3.clone())
+      Range(ref v1, ref v2, ref e3) if v1.is_value() && v2.is_value() => {
+        Range(v1.clone(), v2.clone(), Box::new(self.step(*e3.clone())?))
       },
-      While(e1, e1o, e2, e2o, e3) => {
-        While(e1, e1o, Box::new(self.step(*e2)?), e2o, e3)
+      Range(ref v1, ref e2, ref e3) if v1.is_value() => {
+        Range(v1.clone(), Box::new(self.step(*e2.clone())?), e3.clone())
       },
-      Decl(dt, addr, e1, e2) => {
-        Decl(dt, Box::new(*addr.clone()), Box::new(self.step(*e1)?), e2)
+      Range(e1, e2, e3) => {
+        Range(Box::new(self.step(*e1)?), e2, e3)
       },
-      FnCall(ref v1, ref args) if v1.is_func() => {
-        let mut found_nonvalue = false;
-
-        let stepped_args: Result<Vec<Expr>> = args.iter().map(|e| {
-          if !found_nonvalue && !e.is_value() {
-            found_nonvalue = true;
-            self.step(e.clone())
-          } else {
-            Ok(e.clone())
-          }
-        }).collect();
-
-        FnCall(v1.clone(), stepped_args?)
+      For(label, x, e1, e2, None) => {
+        For(label, x, Box::new(self.step(*e1)?), e2, None)
+      },
+      While(label, e1, e1o, e2, e2o, last, run) => {
+        While(label, Box::new(self.step(*e1)?), e1o, e2, e2o, last, run)
+      },
+      Decl(dt, addr, t, e1, e2) => {
+        Decl(dt, Box::new(*addr.clone()), t, Box::new(self.step(*e1)?), e2)
+      },
+      FnCall(ref v1, ref args) if v1.is_func() || v1.is_ctor() => {
+        FnCall(v1.clone(), self.step_first_nonvalue(args.clone())?)
       },
       FnCall(e1, args) => {
         FnCall(Box::new(self.step(*e1)?), args)
       },
-      Scope(e1) => {
-        Scope(Box::new(self.step(*e1)?))
+      Builtin(b, mut args) => {
+        if b.mutates_first_arg() && args.first().is_some_and(|e| e.is_var()) {
+          let rest = args.split_off(1);
+          args.extend(self.step_first_nonvalue(rest)?);
+          Builtin(b, args)
+        } else {
+          Builtin(b, self.step_first_nonvalue(args)?)
+        }
+      },
+      PartyLit(es) => {
+        PartyLit(self.step_first_nonvalue(es)?)
+      },
+      DexLit(entries) => {
+        let flat = entries.into_iter().flat_map(|(k, v)| vec!(k, v)).collect();
+        let mut stepped = self.step_first_nonvalue(flat)?.into_iter();
+        let mut entries = Vec::new();
+
+        while let (Some(k), Some(v)) = (stepped.next(), stepped.next()) {
+          entries.push((k, v));
+        }
+
+        DexLit(entries)
+      },
+      CardLit(name, fields, base) => {
+        if fields.iter().all(|field| field.1.is_value()) {
+          let base = match base {
+            Some(b) => Some(Box::new(self.step(*b)?)),
+            None => None,
+          };
+
+          CardLit(name, fields, base)
+        } else {
+          let (xs, es): (Vec<String>, Vec<Expr>) = fields.into_iter().unzip();
+          let es = self.step_first_nonvalue(es)?;
+
+          CardLit(name, xs.into_iter().zip(es).collect(), base)
+        }
+      },
+      Evolve(e1, arms) => {
+        Evolve(Box::new(self.step(*e1)?), arms)
+      },
+      Field(e1, x) => {
+        Field(Box::new(self.step(*e1)?), x)
+      },
+      Index(ref v1, ref e2) if v1.is_value() => {
+        Index(v1.clone(), Box::new(self.step(*e2.clone())?))
+      },
+      Index(e1, e2) => {
+        Index(Box::new(self.step(*e1)?), e2)
+      },
+      Scope(e1, t) => {
+        match self.step(*e1) {
+          Ok(e1) => Scope(Box::new(e1), t),
+          Err(RuntimeError::Flee(v)) => {
+            self.state.end_scope();
+            check_result(&v, &t)?;
+            v
+          },
+          Err(err) => {
+            self.state.end_scope();
+            return Err(err);
+          },
+        }
+      },
+      Flee(e1) => {
+        Flee(Box::new(self.step(*e1)?))
+      },
+      Faint(e1) => {
+        Faint(Box::new(self.step(*e1)?))
+      },
+      Escape(e1, rope, finally) => {
+        match self.step(*e1) {
+          Ok(e1) => Escape(Box::new(e1), rope, finally),
+          Err(err) => match (rope, caught(&err)) {
+            (Some((x, e2)), Some(v)) => {
+              // the handler gets its own frame; finally still runs after it
+              let body = {
+                let (names, body) = framed(&e2);
+                self.state.begin_scope(names);
+                body.clone()
+              };
+              self.state.alloc_const(x, v)?;
+              Escape(Box::new(Rope(Box::new(body))), None, finally)
+            },
+            (_, _) => match finally {
+              Some(e3) => Bop(Seq, e3, Box::new(Unwind(Box::new(err)))),
+              None => return Err(err),
+            },
+          },
+        }
+      },
+      Rope(e1) => {
+        match self.step(*e1) {
+          Ok(e1) => Rope(Box::new(e1)),
+          Err(err) => {
+            self.state.end_scope();
+            return Err(err);
+          },
+        }
       },
       Print(e1) => {
         Print(Box::new(self.step(*e1)?))
@@ -230,9 +868,223 @@ impl Interpreter {
     Ok(e1)
   }
 
+  fn step_first_nonvalue(&mut self, es: Vec<Expr>) -> Result<Vec<Expr>> {
+    let mut found_nonvalue = false;
+
+    es.into_iter().map(|e| {
+      if !found_nonvalue && !e.is_value() {
+        found_nonvalue = true;
+        self.step(e)
+      } else {
+        Ok(e)
+      }
+    }).collect()
+  }
+
+  // what the variable x holds, read through its slot when the resolver gave it one
+  fn read(&self, x: &Expr) -> Result<Expr> {
+    match *x {
+      Local(x, depth, index) => self.state.get_local(x, depth, index),
+      _ => self.state.get(x.to_var()?),
+    }
+  }
+
+  fn write(&mut self, x: &Expr, v: Expr) -> Result<()> {
+    match *x {
+      Local(x, depth, index) => self.state.assign_local(x, depth, index, v),
+      _ => self.state.assign(x.to_var()?, v),
+    }
+  }
+
+  fn apply_builtin(&mut self, b: &Builtin, vs: &[Expr]) -> Result<Expr> {
+    if vs.len() != b.arity() {
+      return Err(RuntimeError::WrongNumberOfArgs(b.name().to_string(), b.arity(), vs.len()));
+    }
+
+    let e = match *b {
+      Builtin::Len => {
+        match vs[0] {
+          Str(ref s) => Int(s.chars().count() as isize),
+          Party(ref es) => Int(es.len() as isize),
+          Dex(ref entries) => Int(entries.len() as isize),
+          ref v => return Err(RuntimeError::InvalidTypeConversion("str, party or dex".to_string(), v.clone())),
+        }
+      },
+      Builtin::Substr => {
+        let chars: Vec<char> = vs[0].to_str()?.chars().collect();
+        let start = vs[1].to_int()?;
+        let end = vs[2].to_int()?;
+
+        if start < 0 || start > chars.len() as isize {
+          return Err(RuntimeError::IndexOutOfBounds(start, chars.len()));
+        }
+
+        if end < start || end > chars.len() as isize {
+          return Err(RuntimeError::IndexOutOfBounds(end, chars.len()));
+        }
+
+        Str(chars[start as usize..end as usize].iter().collect())
+      },
+      Builtin::CharAt => {
+        let chars: Vec<char> = vs[0].to_str()?.chars().collect();
+        let i = vs[1].to_int()?;
+
+        if i < 0 || i >= chars.len() as isize {
+          return Err(RuntimeError::IndexOutOfBounds(i, chars.len()));
+        }
+
+        Str(chars[i as usize].to_string())
+      },
+      Builtin::Push => {
+        let mut es = self.read(&vs[0])?.to_party()?;
+
+        if es.len() >= self.party_capacity {
+          return Err(RuntimeError::PartyFull(self.party_capacity));
+        }
+
+        es.push(vs[1].clone());
+        self.write(&vs[0], Party(es))?;
+        Undefined
+      },
+      Builtin::Pop => {
+        let mut es = self.read(&vs[0])?.to_party()?;
+
+        match es.pop() {
+          Some(e) => {
+            self.write(&vs[0], Party(es))?;
+            e
+          },
+          None => return Err(RuntimeError::EmptyParty(vs[0].to_var()?.to_string())),
+        }
+      },
+      Builtin::Get => {
+        dex_get(&vs[0].to_dex()?, &vs[1])?
+      },
+      Builtin::Has => {
+        Bool(vs[0].to_dex()?.iter().any(|entry| entry.0 == vs[1]))
+      },
+      Builtin::Keys => {
+        // a dex holds any number of entries, so its keys are not a party of six
+        Party(vs[0].to_dex()?.into_iter().map(|(k, _)| k).collect())
+      },
+      Builtin::Set => {
+        let mut entries = self.read(&vs[0])?.to_dex()?;
+
+        dex_insert(&mut entries, vs[1].clone(), vs[2].clone());
+        self.write(&vs[0], Dex(entries))?;
+        Undefined
+      },
+      Builtin::Remove => {
+        let mut entries = self.read(&vs[0])?.to_dex()?;
+
+        match entries.iter().position(|entry| entry.0 == vs[1]) {
+          Some(i) => {
+            let (_, v) = entries.remove(i);
+            self.write(&vs[0], Dex(entries))?;
+            v
+          },
+          None => return Err(RuntimeError::KeyNotFound(vs[1].clone())),
+        }
+      },
+    };
+
+    Ok(e)
+  }
+
+  fn resolve(&self, path: &str) -> Result<PathBuf> {
+    self.search_paths.iter()
+      .map(|dir| dir.join(path))
+      .find(|file| file.is_file())
+      .and_then(|file| file.canonicalize().ok())
+      .ok_or_else(|| RuntimeError::ModuleNotFound(path.to_string()))
+  }
+
+  fn import(&mut self, path: &str) -> Result<Expr> {
+    let file = self.resolve(path)?;
+
+    if let Some(module) = self.modules.get(&file) {
+      return Ok(module.clone());
+    }
+
+    if let Some(i) = self.loading.iter().position(|f| *f == file) {
+      let mut cycle: Vec<String> = self.loading[i..].iter().map(|f| f.display().to_string()).collect();
+      cycle.push(file.display().to_string());
+      return Err(RuntimeError::ImportCycle(cycle));
+    }
+
+    let input = fs::read_to_string(&file).map_err(|_| RuntimeError::ModuleNotFound(path.to_string()))?;
+    let mut state = State::new();
+    let e = resolve(parse(&input, &self.symbols)?, &mut state, &self.symbols)?;
+
+    // a module runs in a state of its own
+    self.loading.push(file.clone());
+    // and in a single step as far as a tracer can tell
+    let outer = mem::replace(&mut self.state, state);
+    let tracer = self.tracer.take();
+    let result = self.run(e);
+    self.tracer = tracer;
+    let inner = mem::replace(&mut self.state, outer);
+    self.loading.pop();
+    result?;
+
+    // only pokeballs and attacks are exported
+    let mut exports: Vec<(Symbol, Expr)> = State::bindings(&inner.mem[0])
+      .filter_map(|(x, binding)| match *binding {
+        Binding::Const(ref v) => Some((x, *v.clone())),
+        Binding::Bike(_, _) => None,
+      })
+      .collect();
+    exports.sort_by_key(|export| export.0.to_string());
+
+    let exports = exports.iter().map(|export| (export.0.to_string(), with_prelude(&export.1, &exports))).collect();
+
+    // cards and types declared by a module can be built by the importer too
+    self.state.cards.extend(inner.cards);
+    self.state.types.extend(inner.types);
+
+    let module = Module(path.to_string(), exports);
+    self.modules.insert(file, module.clone());
+    Ok(module)
+  }
+
+  // parses the input and hands back an Execution to run it a few steps at a time.
+  // An input that does not parse or check hands the interpreter back with the error
+  pub fn start(mut self, input: &str) -> result::Result<Execution, (Box<Interpreter>, RuntimeError)> {
+    if self.typecheck {
+      if let Err(err) = self.check(input) {
+        return Err((Box::new(self), err));
+      }
+    }
+
+    let symbols = self.symbols.clone();
+
+    match symbols.view(|| self.compile(input)) {
+      Ok(e) => Ok(Execution::new(self, e)),
+      Err(err) => Err((Box::new(self), err)),
+    }
+  }
+
   pub fn eval(&mut self, input: &str) -> Result<Expr> {
-    let mut e = parse(input)?;
+    if self.typecheck {
+      self.check(input)?;
+    }
+
+    let symbols = self.symbols.clone();
+
+    symbols.view(|| {
+      let e = self.compile(input)?;
+      self.run(e)
+    })
+  }
+
+  // parses the input and gives its variables slots in the frames bound so far
+  fn compile(&mut self, input: &str) -> Result<Expr> {
+    let e = parse(input, &self.symbols)?;
+    Ok(resolve(e, &mut self.state, &self.symbols)?)
+  }
 
+  fn run(&mut self, e: Expr) -> Result<Expr> {
+    let mut e = e;
     let mut num_iterations = 0;
 
     loop {
@@ -1,8 +1,23 @@
 #[macro_use]
 extern crate log;
+extern crate serde;
+extern crate serde_json;
 
 pub mod parser;
 pub mod expr;
 pub mod interpreter;
+pub mod execution;
 pub mod state;
 pub mod runtime_error;
+pub mod repl;
+pub mod printer;
+pub mod formatter;
+pub mod trace;
+pub mod snapshot;
+pub mod snapshot_error;
+pub mod types;
+pub mod type_error;
+pub mod checker;
+pub mod resolver;
+pub mod resolver_error;
+pub mod symbol;
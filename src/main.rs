@@ -1,32 +1,322 @@
 extern crate meowth;
+extern crate rustyline;
 
 use meowth::interpreter::Interpreter;
+use meowth::expr::Expr;
+use meowth::formatter::format;
+use meowth::runtime_error::RuntimeError;
+use meowth::symbol::Interner;
+use meowth::repl::{Completions, Repl, Reply};
+use meowth::trace::TraceFormatter;
 
-use std::io::{Write, stdout, stdin};
+use rustyline::{Context, Editor, Helper};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+
+use std::env;
+use std::fs;
+use std::io::{IsTerminal, Read, stdin};
+use std::path::{Path, PathBuf};
+use std::process;
+
+// exit codes follow sysexits.h
+const EX_USAGE: i32 = 64;
+const EX_DATAERR: i32 = 65;
+const EX_NOINPUT: i32 = 66;
+const EX_SOFTWARE: i32 = 70;
+
+const USAGE: &str = "usage: meowth [--typecheck] [--trace[=top]] [run] <file.meow> [args...]
+       meowth [--typecheck] [--trace[=top]] -e <expr>
+       meowth fmt [--check] [<file.meow>...]  (formats stdin to stdout when no file is given)
+       meowth [--typecheck]  (reads a program from stdin when it is not a terminal)
+
+--typecheck  check types before running, and report every type error found
+--trace      print every step to stderr, with the rule that fired and where
+--trace=top  print only the program after each step";
+
+// Some(top level only) when steps should be traced
+type Trace = Option<bool>;
+
+#[derive(Clone, Copy)]
+struct Options {
+  trace: Trace,
+  typecheck: bool,
+}
 
 fn main() {
+  let mut args: Vec<String> = env::args().skip(1).collect();
+  let mut options = Options { trace: None, typecheck: false };
+
+  loop {
+    match args.first().map(|s| s.as_str()) {
+      Some("--trace") => options.trace = Some(false),
+      Some("--trace=top") => options.trace = Some(true),
+      Some("--typecheck") => options.typecheck = true,
+      _ => break,
+    }
+
+    args.remove(0);
+  }
+
+  let flagged = options.trace.is_some() || options.typecheck;
+
+  let code = match args.first().map(|s| s.as_str()) {
+    Some("-h") | Some("--help") => {
+      println!("{}", USAGE);
+      0
+    },
+    Some("-e") if args.len() == 2 => eval_and_print(&args[1], options),
+    Some("fmt") if !flagged => match args.get(1).map(|s| s.as_str()) {
+      Some("--check") => format_files(&args[2..], true),
+      _ => format_files(&args[1..], false),
+    },
+    Some("run") if args.len() >= 2 => run_file(&args[1], &args[2..], options),
+    Some(s) if !s.starts_with('-') && s != "run" => run_file(s, &args[1..], options),
+    Some(_) => usage(),
+    None if !stdin().is_terminal() => run_stdin(options),
+    None if options.trace.is_some() => usage(),
+    None => {
+      repl(options.typecheck);
+      0
+    },
+  };
+
+  process::exit(code);
+}
+
+fn usage() -> i32 {
+  eprintln!("{}", USAGE);
+  EX_USAGE
+}
+
+// parse errors are problems with the program text, anything else failed while running
+fn report(err: RuntimeError, symbols: &Interner) -> i32 {
+  eprintln!("Error: {}", symbols.display(&err));
+
+  match err {
+    RuntimeError::ParserError(_) | RuntimeError::ResolverError(_) | RuntimeError::TypeErrors(_) => EX_DATAERR,
+    _ => EX_SOFTWARE,
+  }
+}
+
+// scripts see their arguments as a party of strings named args
+fn interpreter_with_args(args: &[String], options: Options) -> Interpreter {
   let mut interpreter = Interpreter::new();
+  let args = args.iter().map(|arg| Expr::Str(arg.clone())).collect();
+  let _ = interpreter.state.alloc_const(interpreter.symbols().intern("args"), Expr::Party(args));
+  interpreter.set_typecheck(options.typecheck);
+
+  // traces go to stderr so they do not mix with what the program prints
+  if let Some(top_level_only) = options.trace {
+    let mut formatter = TraceFormatter::new(top_level_only);
+
+    interpreter.set_tracer(move |step| {
+      for line in formatter.format(step) {
+        eprintln!("{}", line);
+      }
+    });
+  }
+
+  interpreter
+}
+
+fn run_file(path: &str, args: &[String], options: Options) -> i32 {
+  let input = match fs::read_to_string(path) {
+    Ok(input) => input,
+    Err(err) => {
+      eprintln!("Error: cannot read {}: {}", path, err);
+      return EX_NOINPUT;
+    },
+  };
+
+  // modules are also looked up next to the script
+  let mut interpreter = interpreter_with_args(args, options);
+  if let Some(dir) = Path::new(path).parent() {
+    interpreter.add_search_path(dir);
+  }
+
+  match interpreter.eval(&input) {
+    Ok(_) => 0,
+    Err(err) => report(err, interpreter.symbols()),
+  }
+}
+
+fn run_stdin(options: Options) -> i32 {
+  let mut input = String::new();
+
+  if let Err(err) = stdin().read_to_string(&mut input) {
+    eprintln!("Error: cannot read stdin: {}", err);
+    return EX_NOINPUT;
+  }
+
+  let mut interpreter = interpreter_with_args(&[], options);
+
+  match interpreter.eval(&input) {
+    Ok(_) => 0,
+    Err(err) => report(err, interpreter.symbols()),
+  }
+}
+
+fn eval_and_print(input: &str, options: Options) -> i32 {
+  let mut interpreter = interpreter_with_args(&[], options);
+
+  match interpreter.eval(input) {
+    Ok(Expr::Undefined) => 0,
+    Ok(exp) => {
+      println!("{}", interpreter.symbols().display(&exp));
+      0
+    },
+    Err(err) => report(err, interpreter.symbols()),
+  }
+}
+
+// rewrites each file in place, or with check only reports the ones that would change
+fn format_files(paths: &[String], check: bool) -> i32 {
+  if paths.is_empty() {
+    return format_stdin(check);
+  }
+
+  let mut code = 0;
+
+  for path in paths.iter() {
+    let input = match fs::read_to_string(path) {
+      Ok(input) => input,
+      Err(err) => {
+        eprintln!("Error: cannot read {}: {}", path, err);
+        code = EX_NOINPUT;
+        continue;
+      },
+    };
+
+    let formatted = match format(&input) {
+      Ok(formatted) => formatted,
+      Err(err) => {
+        eprintln!("Error: {}: {}", path, err);
+        code = EX_DATAERR;
+        continue;
+      },
+    };
+
+    if formatted == input {
+      continue;
+    }
+
+    if check {
+      eprintln!("{} is not formatted", path);
+      code = code.max(1);
+    } else if let Err(err) = fs::write(path, formatted) {
+      eprintln!("Error: cannot write {}: {}", path, err);
+      code = EX_SOFTWARE;
+    }
+  }
+
+  code
+}
+
+fn format_stdin(check: bool) -> i32 {
+  let mut input = String::new();
+
+  if let Err(err) = stdin().read_to_string(&mut input) {
+    eprintln!("Error: cannot read stdin: {}", err);
+    return EX_NOINPUT;
+  }
+
+  match format(&input) {
+    Ok(ref formatted) if check && *formatted != input => {
+      eprintln!("stdin is not formatted");
+      1
+    },
+    Ok(_) if check => 0,
+    Ok(formatted) => {
+      print!("{}", formatted);
+      0
+    },
+    Err(err) => {
+      eprintln!("Error: {}", err);
+      EX_DATAERR
+    },
+  }
+}
+
+struct ReplHelper {
+  completions: Completions,
+}
+
+impl Completer for ReplHelper {
+  type Candidate = String;
+
+  fn complete(&self, line: &str, pos: usize, _: &Context) -> rustyline::Result<(usize, Vec<String>)> {
+    Ok(self.completions.complete(line, pos))
+  }
+}
+
+impl Hinter for ReplHelper {
+  type Hint = String;
+
+  fn hint(&self, line: &str, pos: usize, _: &Context) -> Option<String> {
+    self.completions.hint(line, pos)
+  }
+}
+
+impl Highlighter for ReplHelper {}
+impl Validator for ReplHelper {}
+impl Helper for ReplHelper {}
+
+fn history_path() -> Option<PathBuf> {
+  env::var_os("HOME").map(|home| Path::new(&home).join(".meowth_history"))
+}
+
+fn repl(typecheck: bool) {
+  let mut repl = Repl::new();
+  repl.interpreter.set_typecheck(typecheck);
+
+  let mut editor: Editor<ReplHelper, FileHistory> = match Editor::new() {
+    Ok(editor) => editor,
+    Err(err) => {
+      eprintln!("Error: cannot start line editor: {}", err);
+      process::exit(EX_SOFTWARE);
+    },
+  };
+
+  editor.set_helper(Some(ReplHelper { completions: repl.completions() }));
+
+  let history = history_path();
+  if let Some(ref path) = history {
+    let _ = editor.load_history(path);
+  }
 
   loop {
-    print!("meowth :: ");
-    let _ = stdout().flush();
-
-    let mut input = String::new();
-    match stdin().read_line(&mut input) {
-      Ok(_) => {
-        if input == String::from("exit\n") {
-          break;
+    match editor.readline(repl.prompt()) {
+      Ok(line) => {
+        if !line.trim().is_empty() {
+          let _ = editor.add_history_entry(line.as_str());
         }
 
-        let expr_result = interpreter.eval(&input);
-        
-        match expr_result {
-          Ok(exp) => println!(" => {}", exp), 
-          Err(err) => println!("Error: {}", err),
+        match repl.feed(&line) {
+          Reply::More => {},
+          Reply::Output(ref out) if out.is_empty() => {},
+          Reply::Output(out) => println!("{}", out),
+          Reply::Quit => break,
         }
+
+        if let Some(helper) = editor.helper_mut() {
+          helper.completions = repl.completions();
+        }
+      },
+      Err(ReadlineError::Interrupted) => repl.cancel(),
+      Err(ReadlineError::Eof) => break,
+      Err(err) => {
+        eprintln!("Error: {}", err);
+        break;
       },
-      Err(e) => print!("error: {}", e)
     }
-    let _ = stdout().flush();
+  }
+
+  if let Some(ref path) = history {
+    let _ = editor.save_history(path);
   }
 }
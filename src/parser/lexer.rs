@@ -1,15 +1,93 @@
 use parser::lexer_error::LexerError;
 use parser::token::Token;
+use symbol::{Interner, Symbol};
+use std::rc::Rc;
+
+// every keyword the lexer knows and the token it stands for
+pub const KEYWORDS: &[(&str, Token)] = &[
+  ("win", Token::Bool(true)),
+  ("lose", Token::Bool(false)),
+  ("attack", Token::FnDecl),
+  ("pokeball", Token::Let),
+  ("bike", Token::VarDecl),
+  ("battle", Token::If),
+  ("run", Token::Run),
+  ("rebattle", Token::Rebattle),
+  ("defend", Token::While),
+  ("pokedex", Token::Print),
+  ("speak", Token::PrintVarName),
+  ("beats", Token::Gt),
+  ("draws", Token::Eq),
+  ("survives", Token::Geq),
+  ("give", Token::Give),
+  ("dex", Token::Dex),
+  ("card", Token::Card),
+  ("type", Token::Type),
+  ("evolve", Token::Evolve),
+  ("flee", Token::Flee),
+  ("break", Token::Break),
+  ("continue", Token::Continue),
+  ("for", Token::For),
+  ("escape", Token::Escape),
+  ("rope", Token::Rope),
+  ("finally", Token::Finally),
+  ("faint", Token::Faint),
+  ("trade", Token::Trade),
+];
+
+// words the parser only reads as keywords where its grammar expects them, as
+// in for x in xs by 2 or trade "m" as m, and that are names anywhere else
+pub const CONTEXTUAL: &[&str] = &["in", "by", "as"];
+
+// whitespace and comments between tokens, which the parser never sees
+// but the formatter has to keep
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trivia {
+  // how many line breaks a run of whitespace held
+  Newlines(usize),
+  // the whole comment, // or #! included
+  Comment(String),
+}
 
 pub struct Lexer {
   text: String,
+  // length of the whole input, so offsets can be worked out from what is left
+  len: usize,
+  // offset of the last token returned
+  start: usize,
+  keep_trivia: bool,
+  trivia: Vec<Trivia>,
+  // where names are interned, so every use of a name shares one Symbol
+  symbols: Interner,
 }
 
 impl Lexer {
-  pub fn new(text: String) -> Lexer {
-    Lexer {
+  // a lexer that adds the names it meets to symbols
+  pub fn new(text: String, symbols: &Interner) -> Lexer {
+    Lexer::build(text, false, symbols.clone())
+  }
+
+  // a lexer that remembers the trivia before each token, see take_trivia
+  pub fn with_trivia(text: String, symbols: &Interner) -> Lexer {
+    Lexer::build(text, true, symbols.clone())
+  }
+
+  fn build(text: String, keep_trivia: bool, symbols: Interner) -> Lexer {
+    let mut lexer = Lexer {
+      len: text.len(),
       text: text,
+      start: 0,
+      keep_trivia,
+      trivia: Vec::new(),
+      symbols,
+    };
+
+    // scripts may start with a #!/usr/bin/env meowth line
+    if lexer.text.starts_with("#!") {
+      lexer.skip_inline_comment();
     }
+
+    lexer
   }
 
   fn advance(&mut self, n: usize) {
@@ -30,6 +108,44 @@ impl Lexer {
     Ok(Token::Int(n))
   }
 
+  fn lex_string(&mut self) -> Result<Token, LexerError> {
+    let mut s = String::new();
+    let mut len = None;
+    let mut chars = self.text.char_indices().skip(1);
+
+    while let Some((i, c)) = chars.next() {
+      match c {
+        '"' => {
+          len = Some(i + 1);
+          break;
+        },
+        '\\' => {
+          let escaped = match chars.next() {
+            Some((_, 'n')) => '\n',
+            Some((_, 't')) => '\t',
+            Some((_, 'r')) => '\r',
+            Some((_, '0')) => '\0',
+            Some((_, '\\')) => '\\',
+            Some((_, '"')) => '"',
+            Some((_, e)) => return Err(LexerError::InvalidEscape(format!("invalid escape sequence \\{}", e))),
+            None => break,
+          };
+
+          s.push(escaped);
+        },
+        c => s.push(c),
+      }
+    }
+
+    match len {
+      Some(n) => {
+        self.advance(n);
+        Ok(Token::Str(s))
+      },
+      None => Err(LexerError::UnterminatedString(format!("unterminated string \"{}", s))),
+    }
+  }
+
   fn lex_keyword(&mut self) -> Result<Token, LexerError> {
     let keyword: String = self.text
       .chars()
@@ -38,24 +154,10 @@ impl Lexer {
 
     self.advance(keyword.len());
 
-    let token = match keyword.as_ref() {
-      "win" => Token::Bool(true),
-      "lose" => Token::Bool(false),
-      "attack" => Token::FnDecl,
-      "pokeball" => Token::Let,
-      "bike" => Token::VarDecl,
-      "battle" => Token::If,
-      "run" => Token::Else,
-      "rebattle" => Token::Rebattle,
-      "defend" => Token::While,
-      "pokedex" => Token::Print,
-      "speak" => Token::PrintVarName,
-      "beats" => Token::Gt,
-      "draws" => Token::Eq,
-      "survives" => Token::Geq,
-      "give" => Token::Give,
-      s if s.len() > 0 => Token::Var(s.to_string()),
-      s => return Err(LexerError::InvalidKeyword(format!("invalid keyword {:?}", s)))
+    let token = match KEYWORDS.iter().find(|k| k.0 == keyword) {
+      Some(k) => k.1.clone(),
+      None if !keyword.is_empty() => Token::Var(self.symbols.intern(&keyword)),
+      None => return Err(LexerError::InvalidKeyword(format!("invalid keyword {:?}", keyword))),
     };
 
     Ok(token)
@@ -68,6 +170,11 @@ impl Lexer {
       .collect();
 
     self.advance(spaces_str.len());
+
+    let newlines = spaces_str.matches('\n').count();
+    if self.keep_trivia && newlines > 0 {
+      self.trivia.push(Trivia::Newlines(newlines));
+    }
   }
 
   fn skip_inline_comment(&mut self) {
@@ -77,12 +184,39 @@ impl Lexer {
       .collect();
 
     self.advance(comment_str.len());
+
+    if self.keep_trivia {
+      self.trivia.push(Trivia::Comment(comment_str.trim_end().to_string()));
+    }
+  }
+
+  pub fn intern(&self, name: &str) -> Symbol {
+    self.symbols.intern(name)
+  }
+
+  pub fn name(&self, x: Symbol) -> Rc<str> {
+    self.symbols.name(x).unwrap_or_else(|| Rc::from(""))
+  }
+
+  // the trivia since the previous token, which is empty unless built with_trivia
+  pub fn take_trivia(&mut self) -> Vec<Trivia> {
+    ::std::mem::take(&mut self.trivia)
   }
 
   fn skip_block_comment(&mut self) {
     // TODO: remove everything through the next '*/'
   }
 
+  // byte offset of everything lexed so far
+  pub fn offset(&self) -> usize {
+    self.len - self.text.len()
+  }
+
+  // byte offset where the last token returned starts
+  pub fn token_start(&self) -> usize {
+    self.start
+  }
+
   fn peek_next(&mut self) -> Option<char> {
     self.text.chars().next()
   }
@@ -90,10 +224,10 @@ impl Lexer {
   pub fn get_next_token(&mut self) -> Result<Token, LexerError> {
     while self.peek_next() != None {
       debug!("get_next_token: {}", self.text);
+      self.start = self.offset();
 
       let token = match self.peek_next() {
         Some('/') if self.text.starts_with("//") => {
-          self.advance(2);
           self.skip_inline_comment();
           continue;
         },
@@ -106,6 +240,10 @@ impl Lexer {
           self.advance(1);
           Token::Plus
         },
+        Some('-') if self.text.starts_with("->") => {
+          self.advance(2);
+          Token::Returns
+        },
         Some('-') => {
           self.advance(1);
           Token::Minus
@@ -138,6 +276,14 @@ impl Lexer {
           self.advance(2);
           Token::Or
         },
+        Some('|') => {
+          self.advance(1);
+          Token::Pipe
+        },
+        Some('=') if self.text.starts_with("=>") => {
+          self.advance(2);
+          Token::Arrow
+        },
         Some('=') => {
           self.advance(1);
           Token::Assign
@@ -178,11 +324,28 @@ impl Lexer {
           self.advance(1);
           Token::RBracket
         },
+        Some('[') => {
+          self.advance(1);
+          Token::LSquare
+        },
+        Some(']') => {
+          self.advance(1);
+          Token::RSquare
+        },
+        Some('.') if self.text.starts_with("..") => {
+          self.advance(2);
+          Token::DotDot
+        },
+        Some('.') => {
+          self.advance(1);
+          Token::Dot
+        },
         Some(',') => {
           self.advance(1);
           Token::Comma
         },
-        Some(c) if c.is_alphabetic() => return self.lex_keyword(),
+        Some('"') => return self.lex_string(),
+        Some(c) if c.is_alphabetic() || c == '_' => return self.lex_keyword(),
         Some(c) if c.is_digit(10) => return self.lex_integer(),
         Some(c) if c.is_whitespace() => {
           self.skip_whitespace();
@@ -195,6 +358,36 @@ impl Lexer {
       return Ok(token)
     }
 
+    self.start = self.len;
     Ok(Token::EOF)
   }
 }
+
+// every token of the input up to, but not including, the EOF
+pub fn tokenize(input: &str, symbols: &Interner) -> Result<Vec<Token>, LexerError> {
+  let mut lexer = Lexer::new(input.to_string(), symbols);
+  let mut tokens = Vec::new();
+
+  loop {
+    match lexer.get_next_token()? {
+      Token::EOF => return Ok(tokens),
+      token => tokens.push(token),
+    }
+  }
+}
+
+// every token up to and including the EOF, each with the trivia before it
+pub fn tokenize_with_trivia(input: &str, symbols: &Interner) -> Result<Vec<(Vec<Trivia>, Token)>, LexerError> {
+  let mut lexer = Lexer::with_trivia(input.to_string(), symbols);
+  let mut tokens = Vec::new();
+
+  loop {
+    let token = lexer.get_next_token()?;
+    let done = token == Token::EOF;
+    tokens.push((lexer.take_trivia(), token));
+
+    if done {
+      return Ok(tokens);
+    }
+  }
+}
@@ -2,10 +2,12 @@ use std::num;
 use std::error;
 use std::fmt;
 
-#[derive(Debug, PartialEq)] 
+#[derive(Clone, Debug, PartialEq)] 
 pub enum LexerError {
   UnexpectedCharacter(String),
   InvalidKeyword(String),
+  InvalidEscape(String),
+  UnterminatedString(String),
   ParseInt(num::ParseIntError),
 }
 
@@ -14,6 +16,8 @@ impl fmt::Display for LexerError {
     match *self {
       LexerError::UnexpectedCharacter(ref s) => write!(f, "{}", s),
       LexerError::InvalidKeyword(ref s) => write!(f, "{}", s),
+      LexerError::InvalidEscape(ref s) => write!(f, "{}", s),
+      LexerError::UnterminatedString(ref s) => write!(f, "{}", s),
       LexerError::ParseInt(ref err) => write!(f, "Parse error: {}", err),
     }
   }
@@ -24,6 +28,8 @@ impl error::Error for LexerError {
     match *self {
       LexerError::UnexpectedCharacter(ref s) => s,
       LexerError::InvalidKeyword(ref s) => s,
+      LexerError::InvalidEscape(ref s) => s,
+      LexerError::UnterminatedString(ref s) => s,
       LexerError::ParseInt(ref err) => err.description(),
     }
   }
@@ -32,6 +38,8 @@ impl error::Error for LexerError {
     match *self {
       LexerError::UnexpectedCharacter(_) => None,
       LexerError::InvalidKeyword(_) => None,
+      LexerError::InvalidEscape(_) => None,
+      LexerError::UnterminatedString(_) => None,
       LexerError::ParseInt(ref err) => Some(err),
     }
   }
@@ -1,7 +1,13 @@
-use expr::{Expr, BinOp, UnOp, Dec};
+use expr::{Expr, BinOp, UnOp, Builtin, Dec, Pattern, Span};
 use parser::lexer::Lexer;
 use parser::token::Token;
 use parser::parser_error::ParserError;
+use parser::lexer_error::LexerError;
+use symbol::{Interner, Symbol};
+use types::Type;
+use std::mem;
+use std::path::Path;
+use std::rc::Rc;
 use std::result;
 
 pub type Result<T> = result::Result<T, ParserError>;
@@ -9,16 +15,53 @@ pub type Result<T> = result::Result<T, ParserError>;
 struct Parser {
   lexer: Lexer,
   current_token: Token,
+  // number of attack bodies enclosing the current token
+  fn_depth: usize,
+  // labels of the loops enclosing the current token within its attack
+  loops: Vec<Option<String>>,
+  // whether expressions are wrapped in Expr::At with where they were written
+  spans: bool,
+  // whether Upper { starts a card literal; not right before a for loop's body,
+  // though again inside any bracket there, which card_lits_outside restores from
+  card_lits: bool,
+  card_lits_outside: Vec<bool>,
+  // where the current token starts and the one before it ended
+  start: usize,
+  end: usize,
+}
+
+// cards, types and their variants are named with a capital
+fn is_upper(name: &str) -> bool {
+  name.starts_with(char::is_uppercase)
+}
+
+fn span_start(e: &Expr) -> Option<usize> {
+  match *e {
+    Expr::At(ref span, _) => Some(span.start),
+    _ => None,
+  }
 }
 
 impl Parser {
   pub fn new(lexer: Lexer, token: Token) -> Parser {
     Parser {
-      lexer: lexer,
       current_token: token,
+      fn_depth: 0,
+      loops: Vec::new(),
+      spans: false,
+      card_lits: true,
+      card_lits_outside: Vec::new(),
+      start: lexer.token_start(),
+      end: 0,
+      lexer: lexer,
     }
   }
 
+  // the text of a name the lexer interned
+  fn name(&self, x: Symbol) -> Rc<str> {
+    self.lexer.name(x)
+  }
+
   fn current_token(&self) -> Token {
     self.current_token.clone()
   }
@@ -30,28 +73,90 @@ impl Parser {
       return Err(ParserError::UnexpectedToken(expected, actual))
     }
 
+    match expected {
+      Token::LParen | Token::LSquare | Token::LBracket => {
+        self.card_lits_outside.push(self.card_lits);
+        self.card_lits = true;
+      },
+      Token::RParen | Token::RSquare | Token::RBracket => {
+        if let Some(card_lits) = self.card_lits_outside.pop() {
+          self.card_lits = card_lits;
+        }
+      },
+      _ => {},
+    }
+
+    self.end = self.lexer.offset();
     self.current_token = self.lexer.get_next_token()?;
+    self.start = self.lexer.token_start();
     debug!("new current token: {:?}", self.current_token);
     Ok(())
   }
 
+  // marks e as written from start up to the end of the last token eaten
+  fn at(&self, start: usize, e: Expr) -> Expr {
+    if !self.spans {
+      return e;
+    }
+
+    Expr::At(Span { start, end: self.end }, Box::new(e))
+  }
+
   fn ternary(&mut self, e1: Expr, e2: Expr, e3: Expr) -> Expr {
-    Expr::Ternary(Box::new(e1), Box::new(e2), Box::new(e3))
+    let start = span_start(&e1).unwrap_or(self.end);
+    let e = Expr::Ternary(Box::new(e1), Box::new(e2), Box::new(e3));
+    self.at(start, e)
   }
 
   fn binop(&mut self, bop: BinOp, e1: Expr, e2: Expr) -> Expr {
-    Expr::Bop(bop, Box::new(e1), Box::new(e2))
+    let start = span_start(&e1).unwrap_or(self.end);
+    let e = Expr::Bop(bop, Box::new(e1), Box::new(e2));
+    self.at(start, e)
+  }
+
+  // whether the current token is the name a contextual keyword is spelled with
+  fn at_word(&self, word: &str) -> bool {
+    match self.current_token {
+      Token::Var(s) => &*self.name(s) == word,
+      _ => false,
+    }
+  }
+
+  fn eat_word(&mut self, word: &str, context: &str) -> Result<()> {
+    if !self.at_word(word) {
+      return Err(ParserError::InvalidToken(self.current_token(), String::from(context)));
+    }
+
+    let token = self.current_token();
+    self.eat(token)
+  }
+
+  // parses with card literals allowed or not, as they were again afterwards
+  fn with_card_lits<T, F: FnOnce(&mut Parser) -> Result<T>>(&mut self, allowed: bool, f: F) -> Result<T> {
+    let outer = mem::replace(&mut self.card_lits, allowed);
+    let result = f(self);
+    self.card_lits = outer;
+    result
   }
 
   fn parse_fn_params(&mut self) -> Result<Vec<Expr>> {
-    let mut params = Vec::new();
+    self.parse_items(Token::RParen)
+  }
+
+  fn parse_items(&mut self, end: Token) -> Result<Vec<Expr>> {
+    let mut items = Vec::new();
     let mut token = self.current_token();
 
-    while token != Token::RParen {
-      debug!("getting fn params");
+    while token != end {
+      debug!("getting items until {:?}", end);
+
+      if token == Token::EOF {
+        return Err(ParserError::UnexpectedToken(end, token));
+      }
+
       let term = self.binop_expr()?;
 
-      params.push(term);
+      items.push(term);
 
       if self.current_token == Token::Comma {
         self.eat(Token::Comma)?;
@@ -60,19 +165,21 @@ impl Parser {
       token = self.current_token();
     }
 
-    Ok(params)
+    Ok(items)
   }
 
-  fn parse_fn_decl_params(&mut self) -> Result<Vec<Expr>> {
+  fn parse_fn_decl_params(&mut self) -> Result<(Vec<Expr>, Vec<Option<Type>>)> {
     let mut params = Vec::new();
+    let mut annotations = Vec::new();
     let mut token = self.current_token();
 
     while token != Token::RParen {
       debug!("getting fn decl params");
       match token.clone() {
         Token::Var(s) => {
-          self.eat(Token::Var(s.clone()))?;
+          self.eat(Token::Var(s))?;
           params.push(Expr::Var(s));
+          annotations.push(self.parse_annotation()?);
         },
         Token::Comma => self.eat(Token::Comma)?,
         _ => return Err(ParserError::InvalidToken(token, String::from("parsing fn decl params")))
@@ -81,7 +188,318 @@ impl Parser {
       token = self.current_token();
     }
 
-    Ok(params)
+    Ok((params, annotations))
+  }
+
+  // an optional : type after a name
+  fn parse_annotation(&mut self) -> Result<Option<Type>> {
+    if self.current_token != Token::Else {
+      return Ok(None);
+    }
+
+    self.eat(Token::Else)?;
+    Ok(Some(self.parse_type()?))
+  }
+
+  fn parse_types(&mut self, end: Token) -> Result<Vec<Type>> {
+    let mut types = Vec::new();
+
+    while self.current_token != end {
+      types.push(self.parse_type()?);
+
+      if self.current_token == Token::Comma {
+        self.eat(Token::Comma)?;
+      } else {
+        break;
+      }
+    }
+
+    self.eat(end)?;
+    Ok(types)
+  }
+
+  // pokemon, battle, move, (), party[t], dex[k, v], attack(ts) -> t, or the
+  // name of a card or a type
+  fn parse_type(&mut self) -> Result<Type> {
+    let ty = match self.current_token() {
+      Token::Var(s) if &*self.name(s) == "pokemon" => Type::Int,
+      Token::Var(s) if &*self.name(s) == "move" => Type::Str,
+      Token::If => Type::Bool,
+      Token::LParen => {
+        self.eat(Token::LParen)?;
+        self.eat(Token::RParen)?;
+        return Ok(Type::Unit);
+      },
+      Token::Var(s) if &*self.name(s) == "party" => {
+        self.eat(Token::Var(s))?;
+        self.eat(Token::LSquare)?;
+        let item = self.parse_type()?;
+        self.eat(Token::RSquare)?;
+        return Ok(Type::Party(Box::new(item)));
+      },
+      Token::Dex => {
+        self.eat(Token::Dex)?;
+        self.eat(Token::LSquare)?;
+        let key = self.parse_type()?;
+        self.eat(Token::Comma)?;
+        let value = self.parse_type()?;
+        self.eat(Token::RSquare)?;
+        return Ok(Type::Dex(Box::new(key), Box::new(value)));
+      },
+      Token::FnDecl => {
+        self.eat(Token::FnDecl)?;
+        self.eat(Token::LParen)?;
+        let params = self.parse_types(Token::RParen)?;
+        self.eat(Token::Returns)?;
+        let result = self.parse_type()?;
+        return Ok(Type::Attack(params, Box::new(result)));
+      },
+      // cards and types are told apart once it is known which were declared
+      Token::Var(s) if is_upper(&self.name(s)) => Type::Named(self.name(s).to_string()),
+      t => return Err(ParserError::InvalidToken(t, String::from("parsing type"))),
+    };
+
+    let token = self.current_token();
+    self.eat(token)?;
+
+    Ok(ty)
+  }
+
+  fn parse_dex(&mut self) -> Result<Expr> {
+    self.eat(Token::Dex)?;
+    self.eat(Token::LBracket)?;
+
+    let mut entries = Vec::new();
+    let mut token = self.current_token();
+
+    while token != Token::RBracket {
+      debug!("getting dex entries");
+      let key = self.binop_expr()?;
+      self.eat(Token::Else)?;
+      let value = self.binop_expr()?;
+
+      entries.push((key, value));
+
+      if self.current_token == Token::Comma {
+        self.eat(Token::Comma)?;
+      }
+
+      token = self.current_token();
+    }
+
+    self.eat(Token::RBracket)?;
+
+    Ok(Expr::DexLit(entries))
+  }
+
+  fn parse_names(&mut self, end: Token, context: &str) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    let mut token = self.current_token();
+
+    while token != end {
+      debug!("getting names until {:?}", end);
+      match token.clone() {
+        Token::Var(s) => {
+          self.eat(Token::Var(s))?;
+          names.push(self.name(s).to_string());
+        },
+        Token::Comma => self.eat(Token::Comma)?,
+        _ => return Err(ParserError::InvalidToken(token, String::from(context)))
+      }
+
+      token = self.current_token();
+    }
+
+    Ok(names)
+  }
+
+  fn parse_type_decl(&mut self) -> Result<Expr> {
+    self.eat(Token::Type)?;
+
+    let name = match self.current_token() {
+      Token::Var(s) => {
+        self.eat(Token::Var(s))?;
+        self.name(s).to_string()
+      },
+      t => return Err(ParserError::InvalidToken(t, String::from("parsing type name"))),
+    };
+
+    self.eat(Token::Assign)?;
+
+    if self.current_token == Token::Pipe {
+      self.eat(Token::Pipe)?;
+    }
+
+    let mut variants = Vec::new();
+
+    loop {
+      let ctor = match self.current_token() {
+        Token::Var(s) => {
+          self.eat(Token::Var(s))?;
+          self.name(s).to_string()
+        },
+        t => return Err(ParserError::InvalidToken(t, String::from("parsing type variant"))),
+      };
+
+      let fields = if self.current_token == Token::LParen {
+        self.eat(Token::LParen)?;
+        let fields = self.parse_names(Token::RParen, "parsing type variant fields")?;
+        self.eat(Token::RParen)?;
+        fields
+      } else {
+        Vec::new()
+      };
+
+      variants.push((ctor, fields));
+
+      if self.current_token == Token::Pipe {
+        self.eat(Token::Pipe)?;
+      } else {
+        break;
+      }
+    }
+
+    self.eat(Token::Seq)?;
+    let e3 = self.block()?;
+
+    Ok(Expr::TypeDecl(name, variants, Box::new(e3)))
+  }
+
+  fn parse_pattern(&mut self) -> Result<Pattern> {
+    let pattern = match self.current_token() {
+      Token::Var(s) => {
+        self.eat(Token::Var(s))?;
+        let name = self.name(s);
+
+        if &*name == "_" {
+          Pattern::Wildcard
+        } else if is_upper(&name) {
+          let mut ps = Vec::new();
+
+          if self.current_token == Token::LParen {
+            self.eat(Token::LParen)?;
+
+            while self.current_token != Token::RParen {
+              ps.push(self.parse_pattern()?);
+
+              if self.current_token == Token::Comma {
+                self.eat(Token::Comma)?;
+              }
+            }
+
+            self.eat(Token::RParen)?;
+          }
+
+          Pattern::Ctor(name.to_string(), ps)
+        } else {
+          Pattern::Bind(s)
+        }
+      },
+      Token::Int(n) => {
+        self.eat(Token::Int(n))?;
+        Pattern::Lit(Expr::Int(n))
+      },
+      Token::Minus => {
+        self.eat(Token::Minus)?;
+
+        match self.current_token() {
+          Token::Int(n) => {
+            self.eat(Token::Int(n))?;
+            Pattern::Lit(Expr::Int(-n))
+          },
+          t => return Err(ParserError::InvalidToken(t, String::from("parsing negative pattern"))),
+        }
+      },
+      Token::Bool(b) => {
+        self.eat(Token::Bool(b))?;
+        Pattern::Lit(Expr::Bool(b))
+      },
+      Token::Str(s) => {
+        self.eat(Token::Str(s.clone()))?;
+        Pattern::Lit(Expr::Str(s))
+      },
+      t => return Err(ParserError::InvalidToken(t, String::from("parsing pattern"))),
+    };
+
+    Ok(pattern)
+  }
+
+  fn parse_evolve(&mut self) -> Result<Expr> {
+    self.eat(Token::Evolve)?;
+    self.eat(Token::LParen)?;
+    let e1 = self.statement()?;
+    self.eat(Token::RParen)?;
+    self.eat(Token::LBracket)?;
+
+    let mut arms = Vec::new();
+
+    while self.current_token != Token::RBracket {
+      debug!("getting evolve arms");
+      let pattern = self.parse_pattern()?;
+      self.eat(Token::Arrow)?;
+      let body = self.statement()?;
+
+      arms.push((pattern, body));
+
+      if self.current_token == Token::Comma {
+        self.eat(Token::Comma)?;
+      }
+    }
+
+    self.eat(Token::RBracket)?;
+
+    Ok(Expr::Evolve(Box::new(e1), arms))
+  }
+
+  fn parse_card_decl(&mut self) -> Result<Expr> {
+    self.eat(Token::Card)?;
+
+    let name = match self.current_token() {
+      Token::Var(s) => {
+        self.eat(Token::Var(s))?;
+        self.name(s).to_string()
+      },
+      t => return Err(ParserError::InvalidToken(t, String::from("parsing card name"))),
+    };
+
+    self.eat(Token::LBracket)?;
+    let fields = self.parse_names(Token::RBracket, "parsing card fields")?;
+    self.eat(Token::RBracket)?;
+    self.eat(Token::Seq)?;
+    let e3 = self.block()?;
+
+    Ok(Expr::CardDecl(name, fields, Box::new(e3)))
+  }
+
+  fn parse_card_lit(&mut self, name: String) -> Result<Expr> {
+    self.eat(Token::LBracket)?;
+
+    let mut fields = Vec::new();
+    let mut base = None;
+    let mut token = self.current_token();
+
+    while token != Token::RBracket {
+      debug!("getting card literal fields");
+      match token.clone() {
+        Token::Var(s) if base.is_none() => {
+          self.eat(Token::Var(s))?;
+          self.eat(Token::Else)?;
+          fields.push((self.name(s).to_string(), self.binop_expr()?));
+        },
+        Token::DotDot if base.is_none() => {
+          self.eat(Token::DotDot)?;
+          base = Some(Box::new(self.binop_expr()?));
+        },
+        Token::Comma => self.eat(Token::Comma)?,
+        _ => return Err(ParserError::InvalidToken(token, String::from("parsing card literal")))
+      }
+
+      token = self.current_token();
+    }
+
+    self.eat(Token::RBracket)?;
+
+    Ok(Expr::CardLit(name, fields, base))
   }
 
   fn parse_print(&mut self) -> Result<Expr> {
@@ -96,7 +514,7 @@ impl Parser {
 
     match self.current_token() {
       Token::Var(s) => {
-        self.eat(Token::Var(s.clone()))?;
+        self.eat(Token::Var(s))?;
         self.eat(Token::RParen)?;
         Ok(Expr::PrintVarName(Box::new(Expr::Var(s))))
       },
@@ -110,18 +528,31 @@ impl Parser {
 
     let var = match self.current_token() {
       Token::Var(s) => {
-        self.eat(Token::Var(s.clone()))?;
+        self.eat(Token::Var(s))?;
         Some(Expr::Var(s))
       },
       _ => None,
     };
 
     self.eat(Token::LParen)?;
-    let params = self.parse_fn_decl_params()?;
+    let (params, annotations) = self.parse_fn_decl_params()?;
     self.eat(Token::RParen)?;
 
+    let result = match self.current_token {
+      Token::Returns => {
+        self.eat(Token::Returns)?;
+        Some(Box::new(self.parse_type()?))
+      },
+      _ => None,
+    };
+
     self.eat(Token::LBracket)?;
-    let body = self.block()?;
+    self.fn_depth += 1;
+    let loops = ::std::mem::take(&mut self.loops);
+    let body = self.block();
+    self.loops = loops;
+    self.fn_depth -= 1;
+    let body = body?;
     self.eat(Token::RBracket)?;
 
     match var {
@@ -129,12 +560,12 @@ impl Parser {
         self.eat(Token::Seq)?;
         let e3 = self.block()?;
 
-        let func = Expr::Func(Some(Box::new(v.clone())), Box::new(body.clone()), params);
+        let func = Expr::Func(Some(Box::new(v.clone())), Box::new(body.clone()), params, annotations, result);
 
-        Ok(Expr::Decl(Dec::DConst, Box::new(v), Box::new(func), Box::new(e3)))
+        Ok(Expr::Decl(Dec::DConst, Box::new(v), None, Box::new(func), Box::new(e3)))
       },
       None => {
-        let func = Expr::Func(None, Box::new(body.clone()), params);
+        let func = Expr::Func(None, Box::new(body.clone()), params, annotations, result);
 
         // fn call rule
         if self.current_token == Token::LParen {
@@ -150,35 +581,213 @@ impl Parser {
     }
   }
 
+  fn parse_name(&mut self) -> Result<Option<Symbol>> {
+    match self.current_token() {
+      Token::Var(s) => {
+        self.eat(Token::Var(s))?;
+        Ok(Some(s))
+      },
+      _ => Ok(None),
+    }
+  }
+
+  fn parse_loop_label(&mut self) -> Result<Option<String>> {
+    Ok(self.parse_name()?.map(|s| self.name(s).to_string()))
+  }
+
+  fn parse_loop_control(&mut self) -> Result<Expr> {
+    let token = self.current_token();
+    self.eat(token.clone())?;
+
+    let label = self.parse_loop_label()?;
+
+    if self.loops.is_empty() {
+      return Err(ParserError::InvalidToken(token, String::from("parsing loop control outside of a loop")));
+    }
+
+    if label.is_some() && !self.loops.contains(&label) {
+      return Err(ParserError::InvalidToken(token, format!("parsing loop control with unknown label {:?}", label)));
+    }
+
+    match token {
+      Token::Break => Ok(Expr::Break(label)),
+      _ => Ok(Expr::Continue(label)),
+    }
+  }
+
+  fn parse_for(&mut self) -> Result<Expr> {
+    self.eat(Token::For)?;
+
+    // in for x in xs the second in is the keyword, in for outer x in xs the label is outer
+    let first = self.parse_name()?;
+    let second = if self.at_word("in") { None } else { self.parse_name()? };
+
+    let (label, x) = match (first, second) {
+      (Some(x), None) => (None, x),
+      (Some(label), Some(x)) => (Some(self.name(label).to_string()), x),
+      _ => return Err(ParserError::InvalidToken(self.current_token(), String::from("parsing for loop name"))),
+    };
+
+    self.eat_word("in", "parsing for loop in")?;
+    // in for mon in Team { ... } the { starts the body
+    let mut iter = self.with_card_lits(false, |p| p.binop_expr())?;
+
+    if self.current_token == Token::DotDot {
+      self.eat(Token::DotDot)?;
+      let end = self.with_card_lits(false, |p| p.binop_expr())?;
+
+      let step = if self.at_word("by") {
+        self.eat_word("by", "parsing for loop step")?;
+        self.with_card_lits(false, |p| p.binop_expr())?
+      } else {
+        Expr::Int(1)
+      };
+
+      iter = Expr::Range(Box::new(iter), Box::new(end), Box::new(step));
+    }
+
+    self.loops.push(label.clone());
+    let body = self.factor();
+    self.loops.pop();
+
+    Ok(Expr::For(label, x, Box::new(iter), Box::new(body?), None))
+  }
+
   fn parse_while(&mut self) -> Result<Expr> {
     self.eat(Token::While)?;
+    let label = self.parse_loop_label()?;
     self.eat(Token::LParen)?;
     let e1 = self.statement()?;
     self.eat(Token::RParen)?;
-    let e2 = self.factor()?;
-    self.eat(Token::Seq)?;
-    let e3 = self.block()?;
+
+    self.loops.push(label.clone());
+    let e2 = self.factor();
+    self.loops.pop();
+    let e2 = e2?;
+
+    let run = self.parse_run()?;
 
     return Ok(Expr::While(
+      label,
       Box::new(e1.clone()),
       Box::new(e1),
       Box::new(e2.clone()),
       Box::new(e2),
-      Box::new(e3)
+      None,
+      Box::new(run)
     ));
   }
 
-  fn parse_if(&mut self) -> Result<Expr> {
-    self.eat(Token::If)?;
-    let e1 = self.binop_expr()?;
-    let e2 = self.block()?;
-    let e3 = self.statement()?;
+  fn parse_trade(&mut self) -> Result<Expr> {
+    self.eat(Token::Trade)?;
+
+    let path = match self.current_token() {
+      Token::Str(s) => {
+        self.eat(Token::Str(s.clone()))?;
+        s
+      },
+      t => return Err(ParserError::InvalidToken(t, String::from("parsing trade path"))),
+    };
+
+    // the namespace defaults to the file name without its extension
+    let name = match self.current_token() {
+      Token::Var(s) if &*self.name(s) == "as" => {
+        self.eat(Token::Var(s))?;
+
+        match self.current_token() {
+          Token::Var(s) => {
+            self.eat(Token::Var(s))?;
+            s
+          },
+          t => return Err(ParserError::InvalidToken(t, String::from("parsing trade name"))),
+        }
+      },
+      t => match Path::new(&path).file_stem().and_then(|s| s.to_str()) {
+        Some(s) => self.lexer.intern(s),
+        None => return Err(ParserError::InvalidToken(t, String::from("parsing trade without a name"))),
+      },
+    };
+
+    self.eat(Token::Seq)?;
+    let e3 = self.block()?;
+
+    Ok(Expr::Decl(Dec::DConst, Box::new(Expr::Var(name)), None, Box::new(Expr::Import(path)), Box::new(e3)))
+  }
+
+  fn parse_escape(&mut self) -> Result<Expr> {
+    self.eat(Token::Escape)?;
+    let body = self.parse_braced()?;
+
+    let rope = match self.current_token() {
+      Token::Rope => {
+        self.eat(Token::Rope)?;
+        self.eat(Token::LParen)?;
+
+        let x = match self.current_token() {
+          Token::Var(s) => {
+            self.eat(Token::Var(s))?;
+            s
+          },
+          t => return Err(ParserError::InvalidToken(t, String::from("parsing rope name"))),
+        };
+
+        self.eat(Token::RParen)?;
+        Some((x, Box::new(self.parse_braced()?)))
+      },
+      _ => None,
+    };
+
+    let finally = match self.current_token() {
+      Token::Finally => {
+        self.eat(Token::Finally)?;
+        Some(Box::new(self.parse_braced()?))
+      },
+      _ => None,
+    };
+
+    if rope.is_none() && finally.is_none() {
+      return Err(ParserError::InvalidToken(self.current_token(), String::from("parsing escape without rope or finally")));
+    }
+
+    Ok(Expr::Escape(Box::new(body), rope, finally))
+  }
+
+  fn parse_if(&mut self, keyword: Token) -> Result<Expr> {
+    self.eat(keyword)?;
+    // in battle t draws Fire { ... } the { starts the branch
+    let e1 = self.with_card_lits(false, |p| p.binop_expr())?;
+    let e2 = self.parse_braced()?;
+
+    let e3 = match self.current_token() {
+      Token::Rebattle => self.parse_if(Token::Rebattle)?,
+      _ => self.parse_run()?,
+    };
 
     return Ok(self.ternary(e1, e2, e3));
   }
 
+  // a missing run branch evaluates to ()
+  fn parse_run(&mut self) -> Result<Expr> {
+    if self.current_token != Token::Run {
+      return Ok(Expr::Undefined);
+    }
+
+    self.eat(Token::Run)?;
+    self.parse_braced()
+  }
+
+  fn parse_braced(&mut self) -> Result<Expr> {
+    self.eat(Token::LBracket)?;
+    let e = self.block()?;
+    self.eat(Token::RBracket)?;
+
+    Ok(e)
+  }
+
   fn factor(&mut self) -> Result<Expr> {
-    let e = match self.current_token() {
+    let start = self.start;
+
+    let mut e = match self.current_token() {
       Token::Int(n) => {
         self.eat(Token::Int(n.clone()))?;
         Expr::Int(n)
@@ -187,8 +796,12 @@ impl Parser {
         self.eat(Token::Bool(b.clone()))?;
         Expr::Bool(b)
       },
+      Token::Str(s) => {
+        self.eat(Token::Str(s.clone()))?;
+        Expr::Str(s)
+      },
       Token::Var(s) => {
-        self.eat(Token::Var(s.clone()))?;
+        self.eat(Token::Var(s))?;
 
         // fn call rule
         if self.current_token == Token::LParen {
@@ -196,7 +809,15 @@ impl Parser {
           let params = self.parse_fn_params()?;
           self.eat(Token::RParen)?;
 
-          Expr::FnCall(Box::new(Expr::Var(s)), params)
+          // a builtin's name called is the builtin, unless it is bound when the call runs
+          match Builtin::from_name(&self.name(s)) {
+            Some(b) => Expr::Builtin(b, params),
+            None => Expr::FnCall(Box::new(Expr::Var(s)), params),
+          }
+        } else if self.current_token == Token::LBracket && self.card_lits && is_upper(&self.name(s)) {
+          // card literal rule
+          let name = self.name(s).to_string();
+          self.parse_card_lit(name)?
         } else {
           Expr::Var(s)
         }
@@ -206,7 +827,7 @@ impl Parser {
         self.eat(Token::LParen)?;
         match self.current_token() {
           Token::Var(s) => {
-            self.eat(Token::Var(s.clone()))?;
+            self.eat(Token::Var(s))?;
             self.eat(Token::RParen)?;
             Ok(Expr::Give(Box::new(Expr::Var(s))))
           },
@@ -225,53 +846,78 @@ impl Parser {
       Token::VarDecl => {
         self.eat(Token::VarDecl)?;
         let var = self.term()?;
+        let annotation = self.parse_annotation()?;
         self.eat(Token::Assign)?;
         let e2 = self.statement()?;
         self.eat(Token::Seq)?;
         let e3 = self.block()?;
 
-        Expr::Decl(Dec::DVar, Box::new(var), Box::new(e2), Box::new(e3))
+        Expr::Decl(Dec::DVar, Box::new(var), annotation, Box::new(e2), Box::new(e3))
       },
       Token::Let => {
         self.eat(Token::Let)?;
         let var = self.term()?;
+        let annotation = self.parse_annotation()?;
         self.eat(Token::Assign)?;
         let e2 = self.statement()?;
         self.eat(Token::Seq)?;
         let e3 = self.block()?;
 
-        Expr::Decl(Dec::DConst, Box::new(var), Box::new(e2), Box::new(e3))
+        Expr::Decl(Dec::DConst, Box::new(var), annotation, Box::new(e2), Box::new(e3))
       },
       Token::If => {
-        self.parse_if()?
-      },
-      Token::Rebattle => {
-        self.eat(Token::Rebattle)?;
-
-        let e1 = self.binop_expr()?;
-        let e2 = self.block()?;
-        let e3 = self.statement()?;
-
-        self.ternary(e1, e2, e3)
-      },
-      Token::Else => {
-        self.eat(Token::Else)?;
-        self.statement()?
+        self.parse_if(Token::If)?
       },
       Token::While => {
         self.parse_while()?
       },
+      Token::For => {
+        self.parse_for()?
+      },
       Token::LParen => {
         self.eat(Token::LParen)?;
         let node = self.statement()?;
         self.eat(Token::RParen)?;
         node
       },
-      Token::LBracket => {
-        self.eat(Token::LBracket)?;
-        let node = self.block()?;
-        self.eat(Token::RBracket)?;
-        node
+      Token::LBracket => self.parse_braced()?,
+      Token::LSquare => {
+        self.eat(Token::LSquare)?;
+        let items = self.parse_items(Token::RSquare)?;
+        self.eat(Token::RSquare)?;
+        Expr::PartyLit(items)
+      },
+      Token::Dex => {
+        self.parse_dex()?
+      },
+      Token::Card => {
+        self.parse_card_decl()?
+      },
+      Token::Type => {
+        self.parse_type_decl()?
+      },
+      Token::Evolve => {
+        self.parse_evolve()?
+      },
+      Token::Flee if self.fn_depth == 0 => {
+        return Err(ParserError::InvalidToken(Token::Flee, String::from("parsing flee outside of an attack")))
+      },
+      Token::Flee => {
+        self.eat(Token::Flee)?;
+        Expr::Flee(Box::new(self.statement()?))
+      },
+      Token::Faint => {
+        self.eat(Token::Faint)?;
+        Expr::Faint(Box::new(self.statement()?))
+      },
+      Token::Escape => {
+        self.parse_escape()?
+      },
+      Token::Trade => {
+        self.parse_trade()?
+      },
+      Token::Break | Token::Continue => {
+        self.parse_loop_control()?
       },
       Token::Not => {
         self.eat(Token::Not)?;
@@ -290,7 +936,40 @@ impl Parser {
       }
     };
 
-    Ok(e)
+    // index and field access rules
+    loop {
+      match self.current_token() {
+        Token::LSquare => {
+          self.eat(Token::LSquare)?;
+          let index = self.statement()?;
+          self.eat(Token::RSquare)?;
+
+          e = Expr::Index(Box::new(e), Box::new(index));
+        },
+        Token::Dot => {
+          self.eat(Token::Dot)?;
+
+          match self.current_token() {
+            Token::Var(s) => {
+              self.eat(Token::Var(s))?;
+              e = Expr::Field(Box::new(e), self.name(s).to_string());
+            },
+            t => return Err(ParserError::InvalidToken(t, String::from("parsing field name"))),
+          }
+        },
+        // calls through a module namespace, e.g. kanto.gnaw(3)
+        Token::LParen if matches!(e, Expr::Field(_, _)) => {
+          self.eat(Token::LParen)?;
+          let params = self.parse_fn_params()?;
+          self.eat(Token::RParen)?;
+
+          e = Expr::FnCall(Box::new(e), params);
+        },
+        _ => break,
+      }
+    }
+
+    Ok(self.at(start, e))
   }
 
   pub fn term(&mut self) -> Result<Expr> {
@@ -403,8 +1082,10 @@ impl Parser {
   }
 }
 
-pub fn parse(input: &str) -> Result<Expr> {
-  let mut lexer = Lexer::new(input.to_string());
+// parses with names interned in symbols, so they share one Symbol with
+// every earlier input that used them
+pub fn parse(input: &str, symbols: &Interner) -> Result<Expr> {
+  let mut lexer = Lexer::new(input.to_string(), symbols);
 
   let token = lexer.get_next_token()?;
   
@@ -416,3 +1097,34 @@ pub fn parse(input: &str) -> Result<Expr> {
 
   expr
 }
+
+// like parse, with every factor and operator wrapped in an Expr::At saying
+// where in the input it was written; only for checking, never for running
+pub fn parse_with_spans(input: &str, symbols: &Interner) -> Result<Expr> {
+  let mut lexer = Lexer::new(input.to_string(), symbols);
+
+  let token = lexer.get_next_token()?;
+
+  let mut parser = Parser::new(lexer, token);
+  parser.spans = true;
+
+  parser.program()
+}
+
+// unclosed brackets or strings mean there is more input to come, as when
+// an attack is typed over several lines in the REPL
+pub fn is_incomplete(input: &str, symbols: &Interner) -> bool {
+  let mut lexer = Lexer::new(input.to_string(), symbols);
+  let mut depth = 0;
+
+  loop {
+    match lexer.get_next_token() {
+      Ok(Token::EOF) => return depth > 0,
+      Ok(Token::LParen) | Ok(Token::LBracket) | Ok(Token::LSquare) => depth += 1,
+      Ok(Token::RParen) | Ok(Token::RBracket) | Ok(Token::RSquare) => depth -= 1,
+      Ok(_) => {},
+      Err(LexerError::UnterminatedString(_)) => return true,
+      Err(_) => return false,
+    }
+  }
+}
@@ -1,3 +1,5 @@
+use symbol::Symbol;
+
 #[derive(Clone, Debug, PartialEq)] 
 pub enum Token {
   Plus,
@@ -19,15 +21,18 @@ pub enum Token {
   Seq,
   Ternary,
   Else,
-  Var(String),
+  Var(Symbol),
   Int(isize),
   Bool(bool),
+  Str(String),
   Let,
   VarDecl,
   Assign,
   FnDecl,
   LBracket,
   RBracket,
+  LSquare,
+  RSquare,
   Comma,
   If,
   While,
@@ -36,6 +41,26 @@ pub enum Token {
   PrintVarName,
   Give,
   Rebattle,
+  Run,
+  Dex,
+  Card,
+  Dot,
+  DotDot,
+  Type,
+  Pipe,
+  Evolve,
+  Arrow,
+  Flee,
+  Break,
+  Continue,
+  For,
+  Escape,
+  Rope,
+  Finally,
+  Faint,
+  Trade,
+  // -> before a return type
+  Returns,
 }
 
 impl Token {
@@ -0,0 +1,409 @@
+use expr::{Expr, BinOp, UnOp, Dec, Pattern};
+use expr::Expr::*;
+use symbol::Interner;
+use types::Type;
+
+// how loosely an expression binds, in the order the parser's levels nest
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+enum Level {
+  Block,
+  Statement,
+  Binop,
+  Term,
+  Prefix,
+  Atom,
+}
+
+// printed text, the level it parses back at, and the loosest operator
+// that would be swallowed by its last part if printed right after it
+struct Doc {
+  text: String,
+  level: Level,
+  reach: Option<Level>,
+}
+
+fn doc(text: String, level: Level, reach: Option<Level>) -> Doc {
+  Doc { text, level, reach }
+}
+
+fn atom(text: String) -> Doc {
+  doc(text, Level::Atom, None)
+}
+
+fn tighter(a: Level, b: Option<Level>) -> Option<Level> {
+  match b {
+    Some(b) if b < a => Some(b),
+    _ => Some(a),
+  }
+}
+
+// prints e where the parser expects at least min, followed by an
+// operator of level follower, wrapping it when it would parse differently
+fn fit(e: &Expr, min: Level, follower: Option<Level>) -> Doc {
+  let d = layout(e);
+
+  let swallows = match (d.reach, follower) {
+    (Some(reach), Some(follower)) => reach <= follower,
+    _ => false,
+  };
+
+  if d.level >= min && !swallows {
+    return d;
+  }
+
+  match d.level {
+    // a sequence does not fit in parens, but braces do not build a node either
+    Level::Block => atom(format!("{{ {} }}", d.text)),
+    _ => atom(format!("({})", d.text)),
+  }
+}
+
+fn braced(e: &Expr) -> String {
+  format!("{{ {} }}", fit(e, Level::Block, None).text)
+}
+
+fn items(es: &[Expr]) -> String {
+  let es: Vec<String> = es.iter().map(|e| fit(e, Level::Binop, None).text).collect();
+  es.join(", ")
+}
+
+fn names(xs: &[String]) -> String {
+  xs.join(", ")
+}
+
+fn annotation(t: &Option<Type>) -> String {
+  match *t {
+    Some(ref t) => format!(": {}", t),
+    None => String::new(),
+  }
+}
+
+// the params of an attack in their parens, and what it returns if that was written down
+fn signature(ps: &[Expr], ts: &[Option<Type>], result: &Option<Box<Type>>) -> String {
+  let ps: Vec<String> = ps.iter().enumerate().map(|(i, p)| {
+    let t = ts.get(i).map(annotation).unwrap_or_default();
+
+    match *p {
+      Var(ref x) => format!("{}{}", x, t),
+      ref p => format!("{}{}", source(p), t),
+    }
+  }).collect();
+
+  match *result {
+    Some(ref t) => format!("({}) -> {}", ps.join(", "), t),
+    None => format!("({})", ps.join(", ")),
+  }
+}
+
+fn entries(entries: &[(Expr, Expr)]) -> String {
+  let entries: Vec<String> = entries.iter()
+    .map(|(k, v)| format!("{}: {}", fit(k, Level::Binop, None).text, fit(v, Level::Binop, None).text))
+    .collect();
+  entries.join(", ")
+}
+
+fn fields(fields: &[(String, Expr)]) -> Vec<String> {
+  fields.iter().map(|(x, v)| format!("{}: {}", x, fit(v, Level::Binop, None).text)).collect()
+}
+
+fn card(name: &str, mut fields: Vec<String>, base: Option<&Expr>) -> String {
+  if let Some(base) = base {
+    fields.push(format!("..{}", fit(base, Level::Binop, None).text));
+  }
+
+  if fields.is_empty() {
+    format!("{} {{}}", name)
+  } else {
+    format!("{} {{ {} }}", name, fields.join(", "))
+  }
+}
+
+fn label(label: &Option<String>) -> String {
+  match *label {
+    Some(ref label) => format!("{} ", label),
+    None => String::new(),
+  }
+}
+
+// the rest of a block after a declaration; nothing at all when it is ()
+fn rest(e: &Expr) -> String {
+  match *e {
+    Undefined => String::new(),
+    ref e => format!(" {}", fit(e, Level::Block, None).text),
+  }
+}
+
+pub fn quote(s: &str) -> String {
+  let mut quoted = String::from("\"");
+
+  for c in s.chars() {
+    match c {
+      '\n' => quoted.push_str("\\n"),
+      '\t' => quoted.push_str("\\t"),
+      '\r' => quoted.push_str("\\r"),
+      '\0' => quoted.push_str("\\0"),
+      '\\' => quoted.push_str("\\\\"),
+      '"' => quoted.push_str("\\\""),
+      c => quoted.push(c),
+    }
+  }
+
+  quoted.push('"');
+  quoted
+}
+
+fn op(op: &BinOp) -> &'static str {
+  match *op {
+    BinOp::Plus => "+",
+    BinOp::Minus => "-",
+    BinOp::Times => "*",
+    BinOp::Div => "/",
+    BinOp::Eq => "draws",
+    BinOp::Ne => "!=",
+    BinOp::Leq => "<=",
+    BinOp::Geq => "survives",
+    BinOp::Lt => "<",
+    BinOp::Gt => "beats",
+    BinOp::And => "&&",
+    BinOp::Or => "||",
+    BinOp::Mod => "%",
+    BinOp::Seq => ";",
+    BinOp::Assign => "=",
+  }
+}
+
+fn pattern(p: &Pattern) -> String {
+  match *p {
+    Pattern::Wildcard => String::from("_"),
+    Pattern::Bind(ref x) => x.to_string(),
+    Pattern::Lit(ref e) => source(e),
+    Pattern::Ctor(ref name, ref ps) if ps.is_empty() => name.clone(),
+    Pattern::Ctor(ref name, ref ps) => {
+      let ps: Vec<String> = ps.iter().map(pattern).collect();
+      format!("{}({})", name, ps.join(", "))
+    },
+  }
+}
+
+fn battle(c: &Expr, e2: &Expr, e3: &Expr) -> String {
+  let mut text = format!("({}) {}", fit(c, Level::Statement, None).text, braced(e2));
+
+  match *e3 {
+    Undefined => {},
+    Ternary(ref c, ref e2, ref e3) => text.push_str(&format!(" rebattle {}", battle(c, e2, e3))),
+    ref e3 => text.push_str(&format!(" run {}", braced(e3))),
+  }
+
+  text
+}
+
+// whether e has a card literal outside any brackets, which would take the
+// { of a for loop body as its own
+fn loose_card_lit(e: &Expr) -> bool {
+  match *e {
+    CardLit(_, _, _) => true,
+    Bop(_, ref e1, ref e2) => loose_card_lit(e1) || loose_card_lit(e2),
+    Range(ref e1, ref e2, ref e3) => loose_card_lit(e1) || loose_card_lit(e2) || loose_card_lit(e3),
+    Decl(_, _, _, ref e2, ref e3) => loose_card_lit(e2) || loose_card_lit(e3),
+    Uop(_, ref e1) | Field(ref e1, _) | Index(ref e1, _) | FnCall(ref e1, _) | At(_, ref e1) | Framed(_, ref e1) |
+      Flee(ref e1) | Faint(ref e1) | CardDecl(_, _, ref e1) | TypeDecl(_, _, ref e1) => loose_card_lit(e1),
+    _ => false,
+  }
+}
+
+// a part of a for loop before its body
+fn before_body(e: &Expr) -> String {
+  let text = fit(e, Level::Binop, None).text;
+
+  if loose_card_lit(e) {
+    format!("({})", text)
+  } else {
+    text
+  }
+}
+
+fn range(e1: &Expr, e2: &Expr, e3: &Expr) -> String {
+  if *e3 == Int(1) {
+    return format!("{}..{}", before_body(e1), before_body(e2));
+  }
+
+  format!("{}..{} by {}", before_body(e1), before_body(e2), before_body(e3))
+}
+
+fn layout(e: &Expr) -> Doc {
+  match *e {
+    Int(n) if n < 0 => doc(n.to_string(), Level::Prefix, None),
+    Int(n) => atom(n.to_string()),
+    Bool(true) => atom(String::from("win")),
+    Bool(false) => atom(String::from("lose")),
+    Str(ref s) => atom(quote(s)),
+    Var(ref x) | Local(ref x, _, _) => atom(x.to_string()),
+    Undefined => atom(String::from("()")),
+    Uop(ref uop, ref e1) => {
+      let d = fit(e1, Level::Prefix, None);
+      let sign = match *uop {
+        UnOp::Not => "!",
+        UnOp::Neg => "-",
+      };
+
+      doc(format!("{}{}", sign, d.text), Level::Prefix, d.reach)
+    },
+    Bop(BinOp::Seq, ref e1, ref e2) => {
+      let left = fit(e1, Level::Block, Some(Level::Block)).text;
+
+      match **e2 {
+        // only the end of a block can leave out what follows a ;
+        Undefined => doc(format!("{};", left), Level::Block, Some(Level::Block)),
+        ref e2 => {
+          let right = fit(e2, Level::Statement, None);
+          doc(format!("{}; {}", left, right.text), Level::Block, right.reach)
+        },
+      }
+    },
+    Bop(BinOp::Assign, ref e1, ref e2) => {
+      let left = fit(e1, Level::Binop, Some(Level::Statement)).text;
+      let right = fit(e2, Level::Statement, None);
+      doc(format!("{} = {}", left, right.text), Level::Statement, tighter(Level::Statement, right.reach))
+    },
+    Bop(ref bop @ BinOp::Times, ref e1, ref e2) | Bop(ref bop @ BinOp::Div, ref e1, ref e2) => {
+      let left = fit(e1, Level::Prefix, Some(Level::Term)).text;
+      let right = fit(e2, Level::Term, None);
+      doc(format!("{} {} {}", left, op(bop), right.text), Level::Term, tighter(Level::Term, right.reach))
+    },
+    Bop(ref bop, ref e1, ref e2) => {
+      let left = fit(e1, Level::Binop, Some(Level::Binop)).text;
+      let right = fit(e2, Level::Term, None);
+      doc(format!("{} {} {}", left, op(bop), right.text), Level::Binop, right.reach)
+    },
+    Ternary(ref c, ref e2, ref e3) => atom(format!("battle {}", battle(c, e2, e3))),
+    While(ref l, ref c, _, ref body, _, _, ref run) => {
+      let text = format!("defend {}({}) {}", label(l), fit(c, Level::Statement, None).text, braced(body));
+
+      // without a run the body is the last thing parsed, so [ and . would reach into it
+      if **run == Undefined {
+        return doc(text, Level::Prefix, None);
+      }
+
+      atom(format!("{} run {}", text, braced(run)))
+    },
+    Decl(Dec::DConst, ref x, None, ref e2, ref e3) if matches!(**e2, Func(Some(ref y), _, _, _, _) if y.to_var().ok() == x.to_var().ok()) => match **e2 {
+      Func(_, ref body, ref ps, ref ts, ref result) => {
+        doc(format!("attack {}{} {};{}", source(x), signature(ps, ts, result), braced(body), rest(e3)), Level::Prefix, Some(Level::Block))
+      },
+      _ => unreachable!(),
+    },
+    Decl(Dec::DConst, ref x, None, ref e2, ref e3) if matches!(**e2, Import(_)) => match **e2 {
+      Import(ref path) => doc(format!("trade {} as {};{}", quote(path), source(x), rest(e3)), Level::Prefix, Some(Level::Block)),
+      _ => unreachable!(),
+    },
+    Decl(ref dec, ref x, ref t, ref e2, ref e3) => {
+      let keyword = match *dec {
+        Dec::DConst => "pokeball",
+        Dec::DVar => "bike",
+      };
+      let value = fit(e2, Level::Statement, Some(Level::Block)).text;
+
+      doc(format!("{} {}{} = {};{}", keyword, source(x), annotation(t), value, rest(e3)), Level::Prefix, Some(Level::Block))
+    },
+    Func(ref name, ref body, ref ps, ref ts, ref result) => {
+      let name = match *name {
+        Some(ref x) => source(x),
+        None => String::new(),
+      };
+
+      atom(format!("attack {}{} {}", name, signature(ps, ts, result), braced(body)))
+    },
+    FnCall(ref f, ref args) => {
+      let callee = match **f {
+        Var(_) | Local(_, _, _) | Field(_, _) | Func(None, _, _, _, _) => layout(f).text,
+        ref f => format!("({})", source(f)),
+      };
+
+      atom(format!("{}({})", callee, items(args)))
+    },
+    Scope(ref e1, _) | Rope(ref e1) => atom(braced(e1)),
+    Print(ref e1) => doc(format!("pokedex({})", fit(e1, Level::Statement, None).text), Level::Prefix, Some(Level::Binop)),
+    PrintVarName(ref e1) => atom(format!("speak({})", source(e1))),
+    Give(ref e1) => atom(format!("give({})", source(e1))),
+    Builtin(ref b, ref args) => atom(format!("{}({})", b.name(), items(args))),
+    PartyLit(ref es) | Party(ref es) => atom(format!("[{}]", items(es))),
+    Index(ref e1, ref e2) => {
+      atom(format!("{}[{}]", fit(e1, Level::Atom, None).text, fit(e2, Level::Statement, None).text))
+    },
+    DexLit(ref es) | Dex(ref es) if es.is_empty() => atom(String::from("dex {}")),
+    DexLit(ref es) | Dex(ref es) => atom(format!("dex {{ {} }}", entries(es))),
+    CardDecl(ref name, ref xs, ref e3) => {
+      doc(format!("card {} {{ {} }};{}", name, names(xs), rest(e3)), Level::Prefix, Some(Level::Block))
+    },
+    CardLit(ref name, ref fs, ref base) => atom(card(name, fields(fs), base.as_ref().map(|b| &**b))),
+    Card(ref name, ref fs) => atom(card(name, fields(fs), None)),
+    Field(ref e1, ref x) => atom(format!("{}.{}", fit(e1, Level::Atom, None).text, x)),
+    TypeDecl(ref name, ref variants, ref e3) => {
+      let variants: Vec<String> = variants.iter().map(|(ctor, xs)| match xs.len() {
+        0 => ctor.clone(),
+        _ => format!("{}({})", ctor, names(xs)),
+      }).collect();
+
+      doc(format!("type {} = {};{}", name, variants.join(" | "), rest(e3)), Level::Prefix, Some(Level::Block))
+    },
+    Ctor(_, ref name, _) => atom(name.clone()),
+    Variant(_, ref name, ref vs) if vs.is_empty() => atom(name.clone()),
+    Variant(_, ref name, ref vs) => atom(format!("{}({})", name, items(vs))),
+    Evolve(ref e1, ref arms) => {
+      let arms: Vec<String> = arms.iter()
+        .map(|(p, body)| format!("{} => {}", pattern(p), fit(body, Level::Statement, None).text))
+        .collect();
+
+      atom(format!("evolve ({}) {{ {} }}", fit(e1, Level::Statement, None).text, arms.join(", ")))
+    },
+    Flee(ref e1) | Faint(ref e1) => {
+      let keyword = if matches!(*e, Flee(_)) { "flee" } else { "faint" };
+      let d = fit(e1, Level::Statement, None);
+      doc(format!("{} {}", keyword, d.text), Level::Prefix, tighter(Level::Statement, d.reach))
+    },
+    Break(ref l) => atom(format!("break{}", l.as_ref().map_or(String::new(), |l| format!(" {}", l)))),
+    Continue(ref l) => atom(format!("continue{}", l.as_ref().map_or(String::new(), |l| format!(" {}", l)))),
+    Range(ref e1, ref e2, ref e3) => doc(range(e1, e2, e3), Level::Statement, None),
+    For(ref l, ref x, ref iter, ref body, ref running) => {
+      let iter = match **iter {
+        Range(ref e1, ref e2, ref e3) => range(e1, e2, e3),
+        ref iter => before_body(iter),
+      };
+
+      let text = format!("for {}{} in {} {}", label(l), x, iter, braced(body));
+
+      match *running {
+        // the pass in progress runs before the rest of the loop
+        Some(ref e3) => doc(format!("{}; {}", braced(e3), text), Level::Block, None),
+        None => doc(text, Level::Prefix, None),
+      }
+    },
+    Escape(ref body, ref rope, ref finally) => {
+      let mut text = format!("escape {}", braced(body));
+
+      if let Some((ref x, ref e2)) = *rope {
+        text.push_str(&format!(" rope ({}) {}", x, braced(e2)));
+      }
+
+      if let Some(ref e3) = *finally {
+        text.push_str(&format!(" finally {}", braced(e3)));
+      }
+
+      atom(text)
+    },
+    Unwind(ref err) => doc(format!("faint {}", quote(&err.to_string())), Level::Prefix, Some(Level::Statement)),
+    Import(ref path) => doc(format!("trade {}", quote(path)), Level::Prefix, None),
+    Module(ref path, _) => atom(format!("module {}", quote(path))),
+    At(_, ref e1) | Framed(_, ref e1) => layout(e1),
+  }
+}
+
+// an expression as meowth source, with its names from the interner in view
+pub(crate) fn source(e: &Expr) -> String {
+  fit(e, Level::Block, None).text
+}
+
+// prints an expression as meowth source, with the names symbols gave it
+pub fn print(e: &Expr, symbols: &Interner) -> String {
+  symbols.view(|| source(e))
+}
@@ -0,0 +1,333 @@
+use expr::{Expr, Builtin};
+use interpreter::Interpreter;
+use parser::lexer::{tokenize, KEYWORDS, CONTEXTUAL};
+use parser::parser::{parse, is_incomplete};
+use state::{State, Binding};
+use symbol::Symbol;
+use std::collections::HashMap;
+use std::fs;
+
+pub const PROMPT: &str = "meowth :: ";
+pub const CONTINUATION_PROMPT: &str = "       .. ";
+
+const COMMANDS: &[&str] = &[
+  ":bindings", ":reset", ":load", ":save", ":snapshot", ":restore", ":ast", ":tokens", ":type", ":typecheck", ":help",
+];
+
+const HELP: &str = ":bindings        list everything bound, with its kind and value
+:reset           forget every binding
+:load <file>     run a file in this session
+:save <file>     write the inputs that worked as a script
+:snapshot <file> write every binding, with the history of each bike, as JSON
+:restore <file>  replace every binding with a snapshot
+:ast <expr>      show the parsed expression
+:tokens <expr>   show the lexer output
+:type <expr>     show the type the checker infers
+:typecheck on|off check the types of every input before it runs
+:help            show this list
+exit             leave the REPL";
+
+pub enum Reply {
+  // the input so far is unfinished, ask for another line
+  More,
+  Output(String),
+  Quit,
+}
+
+pub struct Repl {
+  pub interpreter: Interpreter,
+  buffer: String,
+  // inputs that evaluated without an error, for :save
+  inputs: Vec<String>,
+}
+
+// what tab completion knows about the session, refreshed after every input
+#[derive(Clone, Debug, Default)]
+pub struct Completions {
+  names: Vec<String>,
+  attacks: HashMap<String, Vec<String>>,
+}
+
+fn is_name_char(c: char) -> bool {
+  c.is_alphabetic() || c == '_'
+}
+
+// where the name that text ends with starts, past a delimiter of any width
+fn name_start(text: &str) -> usize {
+  text.char_indices().rev().find(|&(_, c)| !is_name_char(c)).map_or(0, |(i, c)| i + c.len_utf8())
+}
+
+impl Completions {
+  // where the word under the cursor starts, and what it could become
+  pub fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+    let before = &line[..pos];
+    let start = name_start(before);
+    let prefix = &before[start..];
+
+    if start == 1 && before.starts_with(':') {
+      let commands = COMMANDS.iter().filter(|c| c[1..].starts_with(prefix)).map(|c| c.to_string()).collect();
+      return (0, commands);
+    }
+
+    if prefix.is_empty() {
+      return (pos, Vec::new());
+    }
+
+    let mut candidates: Vec<String> = KEYWORDS.iter()
+      .map(|k| k.0)
+      .chain(CONTEXTUAL.iter().cloned())
+      .chain(Builtin::ALL.iter().map(|b| b.name()))
+      .chain(self.names.iter().map(|x| x.as_str()))
+      .filter(|x| x.starts_with(prefix))
+      .map(|x| x.to_string())
+      .collect();
+    candidates.sort();
+    candidates.dedup();
+
+    (start, candidates)
+  }
+
+  // the rest of an attack's parameter list, right after its name and (
+  pub fn hint(&self, line: &str, pos: usize) -> Option<String> {
+    if pos < line.len() || !line.ends_with('(') {
+      return None;
+    }
+
+    let before = &line[..pos - 1];
+    let start = name_start(before);
+
+    self.attacks.get(&before[start..]).map(|params| format!("{})", params.join(", ")))
+  }
+}
+
+fn param_names(params: &[Expr]) -> Vec<String> {
+  params.iter().map(|p| p.to_var().map_or_else(|_| p.to_string(), |x| x.to_string())).collect()
+}
+
+fn describe_params(params: &[Expr]) -> String {
+  param_names(params).join(", ")
+}
+
+fn describe_value(v: &Expr) -> String {
+  match *v {
+    Expr::Str(ref s) => format!("{:?}", s),
+    Expr::Func(_, _, ref params, _, _) => format!("attack({})", describe_params(params)),
+    _ => v.to_string(),
+  }
+}
+
+fn describe_binding(x: &str, binding: &Binding) -> String {
+  match *binding {
+    Binding::Const(ref v) => match **v {
+      Expr::Func(_, _, ref params, _, _) => format!("attack {}({})", x, describe_params(params)),
+      ref v => format!("pokeball {} = {}", x, describe_value(v)),
+    },
+    Binding::Bike(ref vs, _) => match vs.last() {
+      Some(v) => format!("bike {} = {}", x, describe_value(v)),
+      None => format!("bike {} (empty)", x),
+    },
+  }
+}
+
+impl Default for Repl {
+  fn default() -> Repl {
+    Repl::new()
+  }
+}
+
+impl Repl {
+  pub fn new() -> Repl {
+    Repl {
+      interpreter: Interpreter::new(),
+      buffer: String::new(),
+      inputs: Vec::new(),
+    }
+  }
+
+  pub fn prompt(&self) -> &'static str {
+    if self.buffer.is_empty() {
+      PROMPT
+    } else {
+      CONTINUATION_PROMPT
+    }
+  }
+
+  // drops a half typed input, e.g. on ctrl-c
+  pub fn cancel(&mut self) {
+    self.buffer.clear();
+  }
+
+  // names show as their text while a line is handled
+  pub fn feed(&mut self, line: &str) -> Reply {
+    let symbols = self.interpreter.symbols().clone();
+    symbols.view(|| self.handle(line))
+  }
+
+  fn handle(&mut self, line: &str) -> Reply {
+    if self.buffer.is_empty() && line.trim() == "exit" {
+      return Reply::Quit;
+    }
+
+    if self.buffer.is_empty() && line.trim_start().starts_with(':') {
+      return Reply::Output(self.command(line.trim()));
+    }
+
+    self.buffer.push_str(line);
+    self.buffer.push('\n');
+
+    if is_incomplete(&self.buffer, self.interpreter.symbols()) {
+      return Reply::More;
+    }
+
+    let input = ::std::mem::take(&mut self.buffer);
+
+    if input.trim().is_empty() {
+      return Reply::Output(String::new());
+    }
+
+    self.eval(&input)
+  }
+
+  fn eval(&mut self, input: &str) -> Reply {
+    match self.interpreter.eval(input) {
+      Ok(exp) => {
+        self.inputs.push(input.trim().to_string());
+        Reply::Output(format!(" => {}", exp))
+      },
+      Err(err) => Reply::Output(format!("Error: {}", err)),
+    }
+  }
+
+  // each input is its own program, so they are chained with ;
+  pub fn script(&self) -> String {
+    let mut script = String::new();
+
+    for input in self.inputs.iter() {
+      script.push_str(input);
+
+      if !input.ends_with(';') {
+        // a ; after a trailing // comment would be commented out
+        let last_line = input.lines().last().unwrap_or("");
+        script.push_str(if last_line.contains("//") { "\n;" } else { ";" });
+      }
+
+      script.push('\n');
+    }
+
+    script
+  }
+
+  fn command(&mut self, line: &str) -> String {
+    let (command, arg) = match line.find(char::is_whitespace) {
+      Some(i) => (&line[..i], line[i..].trim()),
+      None => (line, ""),
+    };
+
+    match command {
+      ":bindings" => self.bindings(),
+      ":reset" => {
+        self.interpreter.state = State::new();
+        self.inputs.clear();
+        String::new()
+      },
+      ":save" if !arg.is_empty() => match fs::write(arg, self.script()) {
+        Ok(_) => String::new(),
+        Err(err) => format!("Error: cannot write {}: {}", arg, err),
+      },
+      ":snapshot" if !arg.is_empty() => {
+        match self.interpreter.state.snapshot(self.interpreter.symbols()).map(|json| fs::write(arg, json)) {
+          Ok(Ok(_)) => String::new(),
+          Ok(Err(err)) => format!("Error: cannot write {}: {}", arg, err),
+          Err(err) => format!("Error: cannot snapshot: {}", err),
+        }
+      },
+      ":restore" if !arg.is_empty() => match fs::read_to_string(arg) {
+        Ok(json) => match State::restore(&json, self.interpreter.symbols()) {
+          Ok(state) => {
+            self.interpreter.state = state;
+            String::new()
+          },
+          Err(err) => format!("Error: cannot restore {}: {}", arg, err),
+        },
+        Err(err) => format!("Error: cannot read {}: {}", arg, err),
+      },
+      ":load" if !arg.is_empty() => match fs::read_to_string(arg) {
+        Ok(input) => match self.eval(&input) {
+          Reply::Output(out) => out,
+          _ => String::new(),
+        },
+        Err(err) => format!("Error: cannot read {}: {}", arg, err),
+      },
+      ":ast" if !arg.is_empty() => match parse(arg, self.interpreter.symbols()) {
+        Ok(e) => format!("{:#?}", e),
+        Err(err) => format!("Error: {}", err),
+      },
+      ":tokens" if !arg.is_empty() => match tokenize(arg, self.interpreter.symbols()) {
+        Ok(tokens) => {
+          let tokens: Vec<String> = tokens.iter().map(|t| format!("{:?}", t)).collect();
+          tokens.join(" ")
+        },
+        Err(err) => format!("Error: {}", err),
+      },
+      ":type" if !arg.is_empty() => match self.interpreter.check(arg) {
+        Ok(ty) => ty.to_string(),
+        Err(err) => format!("Error: {}", err),
+      },
+      ":typecheck" => match arg {
+        "" | "on" => {
+          self.interpreter.set_typecheck(true);
+          String::from("typecheck is on")
+        },
+        "off" => {
+          self.interpreter.set_typecheck(false);
+          String::from("typecheck is off")
+        },
+        _ => format!("Error: {} takes on or off", command),
+      },
+      ":load" | ":save" | ":snapshot" | ":restore" | ":ast" | ":tokens" | ":type" => format!("Error: {} needs an argument", command),
+      ":help" => HELP.to_string(),
+      _ => format!("Error: unknown command {}, try :help", command),
+    }
+  }
+
+  pub fn completions(&self) -> Completions {
+    self.interpreter.symbols().view(|| self.collect_completions())
+  }
+
+  fn collect_completions(&self) -> Completions {
+    let mut completions = Completions::default();
+
+    for frame in self.interpreter.state.mem.iter() {
+      for (x, binding) in State::bindings(frame) {
+        let v = match *binding {
+          Binding::Const(ref v) => Some(v),
+          Binding::Bike(ref vs, _) => vs.last(),
+        };
+
+        if let Some(Expr::Func(_, _, params, _, _)) = v.map(|v| &**v) {
+          completions.attacks.insert(x.to_string(), param_names(params));
+        }
+
+        completions.names.push(x.to_string());
+      }
+    }
+
+    completions
+  }
+
+  // every binding, outermost frame first, sorted by name within a frame
+  fn bindings(&self) -> String {
+    let mut lines = Vec::new();
+
+    for frame in self.interpreter.state.mem.iter() {
+      let mut bindings: Vec<(Symbol, &Binding)> = State::bindings(frame).collect();
+      bindings.sort_by_key(|b| b.0.to_string());
+
+      for b in bindings {
+        lines.push(describe_binding(&b.0.to_string(), b.1));
+      }
+    }
+
+    lines.join("\n")
+  }
+}
@@ -0,0 +1,295 @@
+use expr::{Expr, Pattern};
+use expr::Expr::*;
+use resolver_error::ResolverError;
+use state::State;
+use symbol::{Interner, Symbol};
+use std::mem;
+use std::result;
+
+pub type Result<T> = result::Result<T, ResolverError>;
+
+struct Frame {
+  names: Vec<Symbol>,
+  // an attack's own frame, past which names are bound by whoever calls it
+  attack: bool,
+}
+
+struct Resolver<'a> {
+  frames: Vec<Frame>,
+  symbols: &'a Interner,
+}
+
+// runs between parsing and evaluation. A variable some enclosing frame binds
+// becomes a Local holding its slot, one bound outside an attack stays a Var
+// for its caller to bind, and one no enclosing frame binds, even past an
+// attack, is an error before anything runs. Once it
+// resolves, the top frame of state gets slots for the names it declares
+pub fn resolve(e: Expr, state: &mut State, symbols: &Interner) -> Result<Expr> {
+  let mut frames: Vec<Frame> = state.mem.iter().map(|frame| Frame {
+    names: frame.iter().map(|b| b.0).collect(),
+    attack: false,
+  }).collect();
+
+  if frames.is_empty() {
+    frames.push(Frame { names: Vec::new(), attack: false });
+  }
+
+  if let Some(top) = frames.last_mut() {
+    declarations(&e, &mut top.names, symbols);
+  }
+
+  let mut resolver = Resolver { frames, symbols };
+  let e = resolver.expr(e)?;
+
+  if let Some(top) = resolver.frames.pop() {
+    state.extend_top(&top.names);
+  }
+
+  Ok(e)
+}
+
+fn declare(names: &mut Vec<Symbol>, x: Symbol) {
+  if !names.contains(&x) {
+    names.push(x);
+  }
+}
+
+fn pattern_names(p: &Pattern, names: &mut Vec<Symbol>) {
+  match *p {
+    Pattern::Bind(ref x) => declare(names, *x),
+    Pattern::Ctor(_, ref ps) => {
+      for p in ps.iter() {
+        pattern_names(p, names);
+      }
+    },
+    Pattern::Wildcard | Pattern::Lit(_) => {},
+  }
+}
+
+// the names e binds in the frame it runs in, in the order they are written.
+// A name bound anywhere in a frame gets its slot for the whole frame, since a
+// defend loop can come back around to uses written before it
+fn declarations(e: &Expr, names: &mut Vec<Symbol>, symbols: &Interner) {
+  match *e {
+    Decl(_, ref x, _, ref e2, ref e3) => {
+      if let Ok(x) = x.to_var() {
+        declare(names, x);
+      }
+
+      declarations(e2, names, symbols);
+      declarations(e3, names, symbols);
+    },
+    TypeDecl(_, ref variants, ref e2) => {
+      for variant in variants.iter() {
+        declare(names, symbols.intern(&variant.0));
+      }
+
+      declarations(e2, names, symbols);
+    },
+    // attacks, evolve arms, for loop bodies and ropes run in frames of their own
+    Evolve(ref e1, _) => declarations(e1, names, symbols),
+    Func(_, _, _, _, _) | Framed(_, _) => {},
+    For(_, _, ref iter, _, _) => declarations(iter, names, symbols),
+    Escape(ref body, _, ref finally) => {
+      declarations(body, names, symbols);
+
+      if let Some(ref e3) = *finally {
+        declarations(e3, names, symbols);
+      }
+    },
+    _ => {
+      for child in children(e) {
+        declarations(child, names, symbols);
+      }
+    },
+  }
+}
+
+fn children(e: &Expr) -> Vec<&Expr> {
+  match *e {
+    Bop(_, ref e1, ref e2) | Index(ref e1, ref e2) => vec!(e1, e2),
+    Uop(_, ref e1) | Scope(ref e1, _) | Print(ref e1) | PrintVarName(ref e1) | Give(ref e1) |
+      CardDecl(_, _, ref e1) | Field(ref e1, _) | Flee(ref e1) | Rope(ref e1) | Faint(ref e1) | At(_, ref e1) |
+      Framed(_, ref e1) => vec!(e1),
+    Ternary(ref e1, ref e2, ref e3) | Range(ref e1, ref e2, ref e3) => vec!(e1, e2, e3),
+    While(_, ref e1, ref e1o, ref e2, ref e2o, ref last, ref run) => {
+      let mut es: Vec<&Expr> = vec!(e1, e1o, e2, e2o, run);
+      es.extend(last.iter().map(|e| &**e));
+      es
+    },
+    FnCall(ref f, ref args) => {
+      let mut es: Vec<&Expr> = vec!(f);
+      es.extend(args.iter());
+      es
+    },
+    Builtin(_, ref es) | PartyLit(ref es) => es.iter().collect(),
+    DexLit(ref entries) => entries.iter().flat_map(|entry| vec!(&entry.0, &entry.1)).collect(),
+    CardLit(_, ref fields, ref base) => fields.iter().map(|field| &field.1).chain(base.iter().map(|e| &**e)).collect(),
+    _ => Vec::new(),
+  }
+}
+
+impl<'a> Resolver<'a> {
+  fn var(&self, x: Symbol) -> Result<Expr> {
+    let mut past_attack = false;
+
+    for (depth, frame) in self.frames.iter().rev().enumerate() {
+      if let Some(index) = frame.names.iter().position(|name| *name == x) {
+        // past an attack the binding is whoever calls it's, found when it runs
+        return Ok(if past_attack { Var(x) } else { Local(x, depth, index) });
+      }
+
+      past_attack = past_attack || frame.attack;
+    }
+
+    let name = self.symbols.name(x).map_or_else(|| x.to_string(), |name| name.to_string());
+    Err(ResolverError::UndefinedVariable(name))
+  }
+
+  fn scoped(&mut self, names: Vec<Symbol>, attack: bool, e: Expr) -> Result<Expr> {
+    self.frames.push(Frame { names, attack });
+    let e = self.expr(e);
+    self.frames.pop();
+    e
+  }
+
+  // a body that runs in a frame of its own, holding the names its frame is laid out with
+  fn framed(&mut self, names: Vec<Symbol>, attack: bool, e: Expr) -> Result<Expr> {
+    let e = self.scoped(names.clone(), attack, e)?;
+    Ok(Framed(names, Box::new(e)))
+  }
+
+  // resolves in place, keeping the allocation
+  fn boxed(&mut self, mut e: Box<Expr>) -> Result<Box<Expr>> {
+    let inner = mem::replace(&mut *e, Undefined);
+    *e = self.expr(inner)?;
+    Ok(e)
+  }
+
+  fn exprs(&mut self, es: Vec<Expr>) -> Result<Vec<Expr>> {
+    es.into_iter().map(|e| self.expr(e)).collect()
+  }
+
+  fn expr(&mut self, e: Expr) -> Result<Expr> {
+    let e = match e {
+      Var(x) => return self.var(x),
+      Decl(dec, x, t, e2, e3) => {
+        // declarations put every name of a frame in it before its uses were resolved
+        let x = match *x {
+          Var(x) => self.var(x)?,
+          x => x,
+        };
+
+        Decl(dec, Box::new(x), t, self.boxed(e2)?, self.boxed(e3)?)
+      },
+      Func(name, body, params, ts, result) => {
+        // params are bound first, then the attack's own name
+        let mut names = Vec::new();
+
+        for p in params.iter().chain(name.iter().map(|x| &**x)) {
+          if let Ok(x) = p.to_var() {
+            declare(&mut names, x);
+          }
+        }
+
+        declarations(&body, &mut names, self.symbols);
+        let body = self.framed(names, true, *body)?;
+
+        Func(name, Box::new(body), params, ts, result)
+      },
+      For(label, x, iter, body, running) => {
+        let iter = self.boxed(iter)?;
+        let mut names = vec!(x);
+        declarations(&body, &mut names, self.symbols);
+
+        let body = self.framed(names.clone(), false, *body)?;
+        let running = match running {
+          Some(e3) => Some(Box::new(self.scoped(names, false, *e3)?)),
+          None => None,
+        };
+
+        For(label, x, iter, Box::new(body), running)
+      },
+      Escape(body, rope, finally) => {
+        let body = self.boxed(body)?;
+        let rope = match rope {
+          Some((x, e2)) => {
+            let mut names = vec!(x);
+            declarations(&e2, &mut names, self.symbols);
+            Some((x, Box::new(self.framed(names, false, *e2)?)))
+          },
+          None => None,
+        };
+        let finally = match finally {
+          Some(e3) => Some(self.boxed(e3)?),
+          None => None,
+        };
+
+        Escape(body, rope, finally)
+      },
+      Bop(op, e1, e2) => Bop(op, self.boxed(e1)?, self.boxed(e2)?),
+      Uop(op, e1) => Uop(op, self.boxed(e1)?),
+      Ternary(e1, e2, e3) => Ternary(self.boxed(e1)?, self.boxed(e2)?, self.boxed(e3)?),
+      Range(e1, e2, e3) => Range(self.boxed(e1)?, self.boxed(e2)?, self.boxed(e3)?),
+      While(label, e1, e1o, e2, e2o, last, run) => {
+        let last = match last {
+          Some(v) => Some(self.boxed(v)?),
+          None => None,
+        };
+
+        While(label, self.boxed(e1)?, self.boxed(e1o)?, self.boxed(e2)?, self.boxed(e2o)?, last, self.boxed(run)?)
+      },
+      FnCall(f, args) => FnCall(self.boxed(f)?, self.exprs(args)?),
+      Builtin(b, args) => Builtin(b, self.exprs(args)?),
+      PartyLit(es) => PartyLit(self.exprs(es)?),
+      DexLit(entries) => {
+        let entries: Result<Vec<(Expr, Expr)>> = entries.into_iter()
+          .map(|(k, v)| Ok((self.expr(k)?, self.expr(v)?)))
+          .collect();
+
+        DexLit(entries?)
+      },
+      CardLit(name, fields, base) => {
+        let fields: Result<Vec<(String, Expr)>> = fields.into_iter()
+          .map(|(x, e)| Ok((x, self.expr(e)?)))
+          .collect();
+        let base = match base {
+          Some(b) => Some(self.boxed(b)?),
+          None => None,
+        };
+
+        CardLit(name, fields?, base)
+      },
+      Evolve(e1, arms) => {
+        // an arm's frame holds what its pattern binds, then what its body declares
+        let arms: Result<Vec<(Pattern, Expr)>> = arms.into_iter()
+          .map(|(p, body)| {
+            let mut names = Vec::new();
+            pattern_names(&p, &mut names);
+            declarations(&body, &mut names, self.symbols);
+            Ok((p, self.framed(names, false, body)?))
+          })
+          .collect();
+
+        Evolve(self.boxed(e1)?, arms?)
+      },
+      CardDecl(name, fields, e2) => CardDecl(name, fields, self.boxed(e2)?),
+      TypeDecl(name, variants, e2) => TypeDecl(name, variants, self.boxed(e2)?),
+      Index(e1, e2) => Index(self.boxed(e1)?, self.boxed(e2)?),
+      Field(e1, x) => Field(self.boxed(e1)?, x),
+      Scope(e1, t) => Scope(self.boxed(e1)?, t),
+      Print(e1) => Print(self.boxed(e1)?),
+      PrintVarName(e1) => PrintVarName(self.boxed(e1)?),
+      Give(e1) => Give(self.boxed(e1)?),
+      Flee(e1) => Flee(self.boxed(e1)?),
+      Faint(e1) => Faint(self.boxed(e1)?),
+      Rope(e1) => Rope(self.boxed(e1)?),
+      At(span, e1) => At(span, self.boxed(e1)?),
+      Framed(names, e1) => Framed(names, self.boxed(e1)?),
+      // values, and what never holds a variable
+      e => e,
+    };
+
+    Ok(e)
+  }
+}
@@ -0,0 +1,27 @@
+use std::error;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResolverError {
+  UndefinedVariable(String),
+}
+
+impl fmt::Display for ResolverError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      ResolverError::UndefinedVariable(ref x) => write!(f, "Variable {:?} is never bound", x),
+    }
+  }
+}
+
+impl error::Error for ResolverError {
+  fn description(&self) -> &str {
+    match *self {
+      ResolverError::UndefinedVariable(_) => "Undefined variable",
+    }
+  }
+
+  fn cause(&self) -> Option<&dyn error::Error> {
+    None
+  }
+}
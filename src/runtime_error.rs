@@ -2,8 +2,12 @@ use std::error;
 use std::fmt;
 use expr::Expr;
 use parser::parser_error::ParserError;
+use resolver_error::ResolverError;
+use printer::source;
+use type_error::Diagnostic;
+use types::Type;
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum RuntimeError {
   SteppingOnValue(Expr),
   UnexpectedExpr(String, Expr),
@@ -12,24 +16,111 @@ pub enum RuntimeError {
   InvalidTypeConversion(String, Expr),
   InvalidMemoryState(String),
   ParserError(ParserError),
+  ResolverError(ResolverError),
   TooManyIterations(usize),
   EmptyBike(String),
   GiveFromConst(String),
+  WrongNumberOfArgs(String, usize, usize),
+  IndexOutOfBounds(isize, usize),
+  PartyFull(usize),
+  EmptyParty(String),
+  KeyNotFound(Expr),
+  CardNotFound(String),
+  FieldNotFound(String, String),
+  MissingField(String, String),
+  NonExhaustiveMatch(Vec<String>),
+  Flee(Expr),
+  Break(Option<String>),
+  Continue(Option<String>),
+  NotIterable(Expr),
+  ZeroStep,
+  Raised(Expr),
+  DivisionByZero,
+  ModuleNotFound(String),
+  ImportCycle(Vec<String>),
+  ExportNotFound(String, String),
+  TypeErrors(Vec<Diagnostic>),
+  AnnotationMismatch(String, Box<Type>, Expr),
 }
 
 impl fmt::Display for RuntimeError {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match *self {
-      RuntimeError::SteppingOnValue(ref e) => write!(f, "Stepping on a value {:?}", e),
-      RuntimeError::UnexpectedExpr(ref s, ref e) => write!(f, "Unexpected expression. Expected {} and found {:?}", s, e),
+      RuntimeError::SteppingOnValue(ref e) => write!(f, "Stepping on a value {}", source(e)),
+      RuntimeError::UnexpectedExpr(ref s, ref e) => write!(f, "Unexpected expression. Expected {} and found {}", s, source(e)),
       RuntimeError::VariableNotFound(ref e) => write!(f, "Variable {:?} does not exist in memory", e),
-      RuntimeError::InvalidConstAssignment(ref e, ref s) => write!(f, "Cannot assign {:?} to const {}", e, s),
-      RuntimeError::InvalidTypeConversion(ref s, ref e) => write!(f, "Invalid type conversion. Expected {} and found {:?}", s, e),
+      RuntimeError::InvalidConstAssignment(ref e, ref s) => write!(f, "Cannot assign {} to const {}", source(e), s),
+      RuntimeError::InvalidTypeConversion(ref s, ref e) => write!(f, "Invalid type conversion. Expected {} and found {}", s, source(e)),
       RuntimeError::InvalidMemoryState(ref s) => write!(f, "Unexpected internal memory state: {}", s),
       RuntimeError::TooManyIterations(ref n) => write!(f, "Too many iterations while evaluating expression: {}", n),
       RuntimeError::EmptyBike(ref s) => write!(f, "No value in empty bike {}", s),
       RuntimeError::GiveFromConst(ref s) => write!(f, "Cannot give from const {}", s),
+      RuntimeError::WrongNumberOfArgs(ref s, ref expected, ref actual) => write!(f, "{} expects {} arguments but was given {}", s, expected, actual),
+      RuntimeError::IndexOutOfBounds(ref i, ref len) => write!(f, "Index {} is out of bounds for length {}", i, len),
+      RuntimeError::PartyFull(ref n) => write!(f, "Party is full, it can only hold {}", n),
+      RuntimeError::EmptyParty(ref s) => write!(f, "No value in empty party {}", s),
+      RuntimeError::KeyNotFound(ref e) => write!(f, "Key {} does not exist in dex", source(e)),
+      RuntimeError::CardNotFound(ref s) => write!(f, "Card {} has not been declared", s),
+      RuntimeError::FieldNotFound(ref s, ref x) => write!(f, "Card {} has no field {}", s, x),
+      RuntimeError::MissingField(ref s, ref x) => write!(f, "Card {} is missing field {}", s, x),
+      RuntimeError::NonExhaustiveMatch(ref ps) => write!(f, "evolve does not cover {}", ps.join(", ")),
+      RuntimeError::Flee(ref e) => write!(f, "Cannot flee with {} outside of an attack", source(e)),
+      RuntimeError::Break(ref l) => write!(f, "Cannot break to {:?} outside of a loop", l),
+      RuntimeError::Continue(ref l) => write!(f, "Cannot continue to {:?} outside of a loop", l),
+      RuntimeError::NotIterable(ref e) => write!(f, "for loop cannot iterate over {}", source(e)),
+      RuntimeError::ZeroStep => write!(f, "for loop cannot count by 0"),
+      RuntimeError::Raised(ref e) => write!(f, "Uncaught faint with {}", source(e)),
+      RuntimeError::DivisionByZero => write!(f, "Cannot divide by zero"),
+      RuntimeError::ModuleNotFound(ref s) => write!(f, "Module {:?} was not found on the search path", s),
+      RuntimeError::ImportCycle(ref ps) => write!(f, "Import cycle: {}", ps.join(" -> ")),
+      RuntimeError::ExportNotFound(ref s, ref x) => write!(f, "Module {:?} does not export {}", s, x),
+      RuntimeError::TypeErrors(ref ds) => {
+        let ds: Vec<String> = ds.iter().map(|d| d.to_string()).collect();
+        write!(f, "{}", ds.join("\n"))
+      },
+      RuntimeError::AnnotationMismatch(ref x, ref t, ref e) => write!(f, "{} is annotated {} but was given {}", x, t, source(e)),
       RuntimeError::ParserError(ref err) => write!(f, "Parser error: {}", err),
+      RuntimeError::ResolverError(ref err) => write!(f, "{}", err),
+    }
+  }
+}
+
+impl RuntimeError {
+  // the name of this kind of error, for a rope to branch on
+  pub fn kind(&self) -> &'static str {
+    match *self {
+      RuntimeError::SteppingOnValue(_) => "SteppingOnValue",
+      RuntimeError::UnexpectedExpr(_, _) => "UnexpectedExpr",
+      RuntimeError::VariableNotFound(_) => "VariableNotFound",
+      RuntimeError::InvalidConstAssignment(_, _) => "InvalidConstAssignment",
+      RuntimeError::InvalidTypeConversion(_, _) => "InvalidTypeConversion",
+      RuntimeError::InvalidMemoryState(_) => "InvalidMemoryState",
+      RuntimeError::ParserError(_) => "ParserError",
+      RuntimeError::ResolverError(_) => "ResolverError",
+      RuntimeError::TooManyIterations(_) => "TooManyIterations",
+      RuntimeError::EmptyBike(_) => "EmptyBike",
+      RuntimeError::GiveFromConst(_) => "GiveFromConst",
+      RuntimeError::WrongNumberOfArgs(_, _, _) => "WrongNumberOfArgs",
+      RuntimeError::IndexOutOfBounds(_, _) => "IndexOutOfBounds",
+      RuntimeError::PartyFull(_) => "PartyFull",
+      RuntimeError::EmptyParty(_) => "EmptyParty",
+      RuntimeError::KeyNotFound(_) => "KeyNotFound",
+      RuntimeError::CardNotFound(_) => "CardNotFound",
+      RuntimeError::FieldNotFound(_, _) => "FieldNotFound",
+      RuntimeError::MissingField(_, _) => "MissingField",
+      RuntimeError::NonExhaustiveMatch(_) => "NonExhaustiveMatch",
+      RuntimeError::Flee(_) => "Flee",
+      RuntimeError::Break(_) => "Break",
+      RuntimeError::Continue(_) => "Continue",
+      RuntimeError::NotIterable(_) => "NotIterable",
+      RuntimeError::ZeroStep => "ZeroStep",
+      RuntimeError::Raised(_) => "Raised",
+      RuntimeError::DivisionByZero => "DivisionByZero",
+      RuntimeError::ModuleNotFound(_) => "ModuleNotFound",
+      RuntimeError::ImportCycle(_) => "ImportCycle",
+      RuntimeError::ExportNotFound(_, _) => "ExportNotFound",
+      RuntimeError::TypeErrors(_) => "TypeErrors",
+      RuntimeError::AnnotationMismatch(_, _, _) => "AnnotationMismatch",
     }
   }
 }
@@ -46,7 +137,29 @@ impl error::Error for RuntimeError {
       RuntimeError::TooManyIterations(_) => "Too many iterations",
       RuntimeError::EmptyBike(_) => "No value in empty bike",
       RuntimeError::GiveFromConst(_) => "Cannot give from const",
+      RuntimeError::WrongNumberOfArgs(_, _, _) => "Wrong number of arguments",
+      RuntimeError::IndexOutOfBounds(_, _) => "Index out of bounds",
+      RuntimeError::PartyFull(_) => "Party is full",
+      RuntimeError::EmptyParty(_) => "No value in empty party",
+      RuntimeError::KeyNotFound(_) => "Key does not exist in dex",
+      RuntimeError::CardNotFound(_) => "Card has not been declared",
+      RuntimeError::FieldNotFound(_, _) => "Card has no such field",
+      RuntimeError::MissingField(_, _) => "Card is missing a field",
+      RuntimeError::NonExhaustiveMatch(_) => "evolve does not cover every value",
+      RuntimeError::Flee(_) => "Cannot flee outside of an attack",
+      RuntimeError::Break(_) => "Cannot break outside of a loop",
+      RuntimeError::Continue(_) => "Cannot continue outside of a loop",
+      RuntimeError::NotIterable(_) => "for loop cannot iterate over value",
+      RuntimeError::ZeroStep => "for loop cannot count by 0",
+      RuntimeError::Raised(_) => "Uncaught faint",
+      RuntimeError::DivisionByZero => "Cannot divide by zero",
+      RuntimeError::ModuleNotFound(_) => "Module was not found",
+      RuntimeError::ImportCycle(_) => "Import cycle",
+      RuntimeError::ExportNotFound(_, _) => "Module does not export name",
+      RuntimeError::TypeErrors(_) => "Type errors",
+      RuntimeError::AnnotationMismatch(_, _, _) => "Value does not match its annotation",
       RuntimeError::ParserError(ref err) => err.description(),
+      RuntimeError::ResolverError(_) => "Variable is never bound",
     }
   }
 
@@ -61,7 +174,29 @@ impl error::Error for RuntimeError {
       RuntimeError::TooManyIterations(_) => None,
       RuntimeError::EmptyBike(_) => None,
       RuntimeError::GiveFromConst(_) => None,
+      RuntimeError::WrongNumberOfArgs(_, _, _) => None,
+      RuntimeError::IndexOutOfBounds(_, _) => None,
+      RuntimeError::PartyFull(_) => None,
+      RuntimeError::EmptyParty(_) => None,
+      RuntimeError::KeyNotFound(_) => None,
+      RuntimeError::CardNotFound(_) => None,
+      RuntimeError::FieldNotFound(_, _) => None,
+      RuntimeError::MissingField(_, _) => None,
+      RuntimeError::NonExhaustiveMatch(_) => None,
+      RuntimeError::Flee(_) => None,
+      RuntimeError::Break(_) => None,
+      RuntimeError::Continue(_) => None,
+      RuntimeError::NotIterable(_) => None,
+      RuntimeError::ZeroStep => None,
+      RuntimeError::Raised(_) => None,
+      RuntimeError::DivisionByZero => None,
+      RuntimeError::ModuleNotFound(_) => None,
+      RuntimeError::ImportCycle(_) => None,
+      RuntimeError::ExportNotFound(_, _) => None,
+      RuntimeError::TypeErrors(_) => None,
+      RuntimeError::AnnotationMismatch(_, _, _) => None,
       RuntimeError::ParserError(ref err) => Some(err),
+      RuntimeError::ResolverError(ref err) => Some(err),
     }
   }
 }
@@ -71,3 +206,9 @@ impl From<ParserError> for RuntimeError {
     RuntimeError::ParserError(err)
   }
 }
+
+impl From<ResolverError> for RuntimeError {
+  fn from(err: ResolverError) -> RuntimeError {
+    RuntimeError::ResolverError(err)
+  }
+}
@@ -0,0 +1,85 @@
+use expr::Expr;
+use state::State;
+use symbol::Interner;
+use snapshot_error::SnapshotError;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json;
+use serde_json::Value;
+use std::result;
+
+pub type Result<T> = result::Result<T, SnapshotError>;
+
+// bump whenever a change to State or Expr changes their serialized form
+pub const SNAPSHOT_VERSION: u64 = 5;
+
+const STATE_KIND: &str = "state";
+const VALUE_KIND: &str = "value";
+
+#[derive(Serialize)]
+struct Envelope<'a, T: 'a> {
+  version: u64,
+  kind: &'a str,
+  data: &'a T,
+}
+
+fn save<T: Serialize>(kind: &str, data: &T) -> Result<String> {
+  let envelope = Envelope {
+    version: SNAPSHOT_VERSION,
+    kind,
+    data,
+  };
+
+  Ok(serde_json::to_string(&envelope)?)
+}
+
+// the envelope is checked before the data, so an old or foreign snapshot
+// is reported as such instead of as whatever part of it fails to parse
+fn load<T: DeserializeOwned>(kind: &str, json: &str) -> Result<T> {
+  let mut envelope: Value = serde_json::from_str(json)?;
+
+  let version = match envelope.get("version") {
+    Some(v) => v.as_u64().ok_or(SnapshotError::MissingVersion)?,
+    None => return Err(SnapshotError::MissingVersion),
+  };
+
+  if version != SNAPSHOT_VERSION {
+    return Err(SnapshotError::IncompatibleVersion(version, SNAPSHOT_VERSION));
+  }
+
+  match envelope.get("kind").and_then(|k| k.as_str()) {
+    Some(k) if k == kind => {},
+    Some(k) => return Err(SnapshotError::WrongKind(kind.to_string(), k.to_string())),
+    None => return Err(SnapshotError::WrongKind(kind.to_string(), "unknown".to_string())),
+  }
+
+  let data = envelope.get_mut("data").map(|data| data.take()).unwrap_or(Value::Null);
+  Ok(serde_json::from_value(data)?)
+}
+
+// names are saved as text from symbols, and loaded back by interning them there
+pub fn save_state(state: &State, symbols: &Interner) -> Result<String> {
+  symbols.view(|| save(STATE_KIND, state))
+}
+
+pub fn load_state(json: &str, symbols: &Interner) -> Result<State> {
+  symbols.view(|| load(STATE_KIND, json))
+}
+
+pub fn save_value(e: &Expr, symbols: &Interner) -> Result<String> {
+  if !e.is_value() {
+    return Err(SnapshotError::NotAValue(e.clone()));
+  }
+
+  symbols.view(|| save(VALUE_KIND, e))
+}
+
+pub fn load_value(json: &str, symbols: &Interner) -> Result<Expr> {
+  let e: Expr = symbols.view(|| load(VALUE_KIND, json))?;
+
+  if !e.is_value() {
+    return Err(SnapshotError::NotAValue(e));
+  }
+
+  Ok(e)
+}
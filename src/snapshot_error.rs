@@ -0,0 +1,47 @@
+use std::error;
+use std::fmt;
+use expr::Expr;
+use serde_json;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SnapshotError {
+  Json(String),
+  MissingVersion,
+  IncompatibleVersion(u64, u64), // found, supported
+  WrongKind(String, String), // expected, actual
+  NotAValue(Expr),
+}
+
+impl fmt::Display for SnapshotError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      SnapshotError::Json(ref s) => write!(f, "Malformed snapshot: {}", s),
+      SnapshotError::MissingVersion => write!(f, "Snapshot has no version"),
+      SnapshotError::IncompatibleVersion(ref found, ref supported) => write!(f, "Snapshot version {} cannot be loaded, this meowth reads version {}", found, supported),
+      SnapshotError::WrongKind(ref expected, ref actual) => write!(f, "Expected a {} snapshot and found a {} snapshot", expected, actual),
+      SnapshotError::NotAValue(ref e) => write!(f, "Only values can be saved, found {:?}", e),
+    }
+  }
+}
+
+impl error::Error for SnapshotError {
+  fn description(&self) -> &str {
+    match *self {
+      SnapshotError::Json(_) => "Malformed snapshot",
+      SnapshotError::MissingVersion => "Snapshot has no version",
+      SnapshotError::IncompatibleVersion(_, _) => "Snapshot version cannot be loaded",
+      SnapshotError::WrongKind(_, _) => "Snapshot holds the wrong kind of thing",
+      SnapshotError::NotAValue(_) => "Only values can be saved",
+    }
+  }
+
+  fn cause(&self) -> Option<&dyn error::Error> {
+    None
+  }
+}
+
+impl From<serde_json::Error> for SnapshotError {
+  fn from(err: serde_json::Error) -> SnapshotError {
+    SnapshotError::Json(err.to_string())
+  }
+}
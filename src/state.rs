@@ -1,114 +1,234 @@
 use expr::Expr;
 use std::collections::HashMap;
 use runtime_error::RuntimeError;
+use serde::{Serialize, Deserialize};
+use snapshot;
+use snapshot_error::SnapshotError;
+use symbol::{Interner, Symbol};
+use types::Type;
 
-#[derive(Clone, Debug)] 
+// the card a rope is given for an error meowth itself raised, naming its
+// kind, like DivisionByZero, and holding its message
+pub const ERROR_CARD: &str = "Error";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)] 
 pub struct State {
-  pub mem: Vec<HashMap<String, Binding>>,
+  // each frame has a slot for every name the resolver found it binds, laid
+  // out in the order it numbered them. A slot stays empty until its
+  // declaration runs
+  pub mem: Vec<Vec<(Symbol, Option<Binding>)>>,
+  pub cards: HashMap<String, Vec<String>>,
+  pub types: HashMap<String, Vec<(String, Vec<String>)>>,
 }
 
-#[derive(Clone, Debug)] 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)] 
 pub enum Binding {
-  Bike(Vec<Box<Expr>>),
+  // every value the bike has held, and the annotation each must meet
+  Bike(Vec<Box<Expr>>, Option<Type>),
   Const(Box<Expr>),
 }
 
 impl State {
   pub fn new() -> State {
+    let mut cards = HashMap::new();
+    cards.insert(ERROR_CARD.to_string(), vec!("kind".to_string(), "message".to_string()));
+
     return State {
-      mem: vec!(HashMap::new()),
+      mem: vec!(Vec::new()),
+      cards,
+      types: HashMap::new(),
     }
   }
 
-  fn first_map_for(&mut self, x: String) -> Option<&mut HashMap<String, Binding>> {
-    self.mem.iter_mut().rev().find(|map| map.contains_key(&x))
+  // versioned JSON holding every frame and the whole history of every bike
+  pub fn snapshot(&self, symbols: &Interner) -> Result<String, SnapshotError> {
+    snapshot::save_state(self, symbols)
   }
 
-  pub fn alloc(&mut self, x: String, v1: Expr) -> Result<(), RuntimeError> {
-    let binding = Binding::Bike(vec!(Box::new(v1)));
+  pub fn restore(json: &str, symbols: &Interner) -> Result<State, SnapshotError> {
+    snapshot::load_state(json, symbols)
+  }
 
-    match self.mem.last_mut() {
-      Some(map) => {
-        map.insert(x, binding);
-        Ok(())
-      },
-      None => Err(RuntimeError::InvalidMemoryState("no memory frame for var allocation".to_string())),
+  // the bindings made so far in a frame, leaving out the empty slots
+  pub fn bindings(frame: &[(Symbol, Option<Binding>)]) -> impl Iterator<Item = (Symbol, &Binding)> {
+    frame.iter().filter_map(|slot| slot.1.as_ref().map(|b| (slot.0, b)))
+  }
+
+  // the slot the resolver gave a variable, depth frames down from the top
+  fn slot(&self, depth: usize, index: usize) -> Option<&Binding> {
+    match self.mem.len().checked_sub(depth + 1) {
+      Some(f) => self.mem[f].get(index).and_then(|slot| slot.1.as_ref()),
+      None => None,
     }
   }
 
-  pub fn alloc_const(&mut self, x: String, v1: Expr) ->Result<(), RuntimeError> {
-    let binding = Binding::Const(Box::new(v1));
+  fn slot_mut(&mut self, depth: usize, index: usize) -> Option<&mut Binding> {
+    match self.mem.len().checked_sub(depth + 1) {
+      Some(f) => self.mem[f].get_mut(index).and_then(|slot| slot.1.as_mut()),
+      None => None,
+    }
+  }
 
-    match self.mem.last_mut() {
-      Some(map) => {
-        map.insert(x, binding);
-        Ok(())
+  // a name the resolver left to whoever calls an attack is looked up by
+  // name, from the top frame down
+  fn position(&self, x: Symbol) -> Option<(usize, usize)> {
+    self.mem.iter().enumerate().rev()
+      .find_map(|(f, frame)| frame.iter().position(|b| b.1.is_some() && b.0 == x).map(|i| (f, i)))
+  }
+
+  fn binding(&self, x: Symbol, slot: Option<(usize, usize)>) -> Option<&Binding> {
+    match slot {
+      Some((depth, index)) => self.slot(depth, index),
+      None => self.position(x).and_then(|(f, i)| self.mem[f][i].1.as_ref()),
+    }
+  }
+
+  fn binding_mut(&mut self, x: Symbol, slot: Option<(usize, usize)>) -> Option<&mut Binding> {
+    match slot {
+      Some((depth, index)) => self.slot_mut(depth, index),
+      None => match self.position(x) {
+        Some((f, i)) => self.mem[f][i].1.as_mut(),
+        None => None,
       },
-      None => return Err(RuntimeError::InvalidMemoryState("no memory frame for const allocation".to_string())),
     }
   }
 
-  pub fn assign(&mut self, x: String, v1: Expr) -> Result<(), RuntimeError> {
-    let mut map_option = self.first_map_for(x.clone());
+  // binds x in the top frame, at the index the resolver gave it. One it
+  // gave no index to, like a param, goes in the slot laid out for its name
+  pub fn declare(&mut self, x: Symbol, index: Option<usize>, binding: Binding) -> Result<(), RuntimeError> {
+    let frame = match self.mem.last_mut() {
+      Some(frame) => frame,
+      None => return Err(RuntimeError::InvalidMemoryState("no memory frame for allocation".to_string())),
+    };
 
-    let map = match map_option.as_mut() {
-      Some(m) => m,
-      None => return Err(RuntimeError::InvalidConstAssignment(v1, x)),
+    let i = match index {
+      Some(i) if i < frame.len() => Some(i),
+      _ => frame.iter().position(|b| b.0 == x),
     };
 
-    match map.get_mut(&x) {
-      Some(&mut Binding::Bike(ref mut v)) => {
+    match i {
+      Some(i) => frame[i] = (x, Some(binding)),
+      None => frame.push((x, Some(binding))),
+    }
+
+    Ok(())
+  }
+
+  pub fn alloc(&mut self, x: Symbol, v1: Expr) -> Result<(), RuntimeError> {
+    self.declare(x, None, Binding::Bike(vec!(Box::new(v1)), None))
+  }
+
+  pub fn alloc_const(&mut self, x: Symbol, v1: Expr) -> Result<(), RuntimeError> {
+    self.declare(x, None, Binding::Const(Box::new(v1)))
+  }
+
+  pub fn assign(&mut self, x: Symbol, v1: Expr) -> Result<(), RuntimeError> {
+    self.assign_at(x, None, v1)
+  }
+
+  pub fn assign_local(&mut self, x: Symbol, depth: usize, index: usize, v1: Expr) -> Result<(), RuntimeError> {
+    self.assign_at(x, Some((depth, index)), v1)
+  }
+
+  fn assign_at(&mut self, x: Symbol, slot: Option<(usize, usize)>, v1: Expr) -> Result<(), RuntimeError> {
+    match self.binding_mut(x, slot) {
+      Some(&mut Binding::Bike(ref mut v, _)) => {
         v.push(Box::new(v1));
+        Ok(())
       },
-      _ => return Err(RuntimeError::InvalidConstAssignment(v1, x))
-    };
+      _ => Err(RuntimeError::InvalidConstAssignment(v1, x.to_string())),
+    }
+  }
 
-    Ok(())
+  // what a bike was annotated with, for checking what is assigned to it
+  pub fn annotation(&self, x: Symbol) -> Option<&Type> {
+    self.annotation_at(x, None)
+  }
+
+  pub fn annotation_local(&self, x: Symbol, depth: usize, index: usize) -> Option<&Type> {
+    self.annotation_at(x, Some((depth, index)))
   }
 
-  pub fn get(&mut self, x: String) -> Result<Expr, RuntimeError> {
-    match self.first_map_for(x.clone()) {
-      Some(map) => match map.get(&x).clone() {
-        Some(&Binding::Bike(ref b)) => {
-          match b.last() {
-            Some(e) => Ok(*e.clone()),
-            _ => Err(RuntimeError::EmptyBike(x)),
-          }
+  fn annotation_at(&self, x: Symbol, slot: Option<(usize, usize)>) -> Option<&Type> {
+    self.binding(x, slot).and_then(|b| match *b {
+      Binding::Bike(_, ref t) => t.as_ref(),
+      Binding::Const(_) => None,
+    })
+  }
+
+  pub fn get(&self, x: Symbol) -> Result<Expr, RuntimeError> {
+    self.get_at(x, None)
+  }
+
+  pub fn get_local(&self, x: Symbol, depth: usize, index: usize) -> Result<Expr, RuntimeError> {
+    self.get_at(x, Some((depth, index)))
+  }
+
+  fn get_at(&self, x: Symbol, slot: Option<(usize, usize)>) -> Result<Expr, RuntimeError> {
+    match self.binding(x, slot) {
+      Some(&Binding::Bike(ref b, _)) => {
+        match b.last() {
+          Some(e) => Ok(*e.clone()),
+          _ => Err(RuntimeError::EmptyBike(x.to_string())),
         }
-        Some(&Binding::Const(ref e)) => Ok(*e.clone()),
-        _ => Err(RuntimeError::VariableNotFound(x)),
-      },
-      None => Err(RuntimeError::VariableNotFound(x)),
+      }
+      Some(&Binding::Const(ref e)) => Ok(*e.clone()),
+      None => Err(RuntimeError::VariableNotFound(x.to_string())),
     }
   }
 
-  pub fn contains(&mut self, x: String) -> bool {
-    match self.get(x) {
-      Ok(_) => true,
-      _ => false,
+  pub fn contains(&self, x: Symbol) -> bool {
+    self.get(x).is_ok()
+  }
+
+  pub fn give(&mut self, x: Symbol) -> Result<Expr, RuntimeError> {
+    self.give_at(x, None)
+  }
+
+  pub fn give_local(&mut self, x: Symbol, depth: usize, index: usize) -> Result<Expr, RuntimeError> {
+    self.give_at(x, Some((depth, index)))
+  }
+
+  fn give_at(&mut self, x: Symbol, slot: Option<(usize, usize)>) -> Result<Expr, RuntimeError> {
+    match self.binding_mut(x, slot) {
+      Some(&mut Binding::Bike(ref mut v, _)) => {
+        match v.pop() {
+          Some(e) => Ok(*e),
+          None => Err(RuntimeError::EmptyBike(x.to_string())),
+        }
+      }
+      Some(_) => Err(RuntimeError::GiveFromConst(x.to_string())),
+      None => Err(RuntimeError::VariableNotFound(x.to_string())),
     }
   }
 
-  pub fn give(&mut self, x: String) -> Result<Expr, RuntimeError> {
-    match self.first_map_for(x.clone()) {
-      Some(map) => match map.get_mut(&x) {
-        Some(binding) => match binding {
-          &mut Binding::Bike(ref mut v) => {
-            match v.pop() {
-              Some(e) => Ok(*e),
-              None => Err(RuntimeError::EmptyBike(x)),
-            }
-          }
-          _ => Err(RuntimeError::GiveFromConst(x)),
-        },
-        _ => Err(RuntimeError::VariableNotFound(x)),
-      },
-      None => Err(RuntimeError::VariableNotFound(x)),
+  pub fn declare_card(&mut self, name: String, fields: Vec<String>) {
+    self.cards.insert(name, fields);
+  }
+
+  pub fn card_fields(&self, name: String) -> Result<Vec<String>, RuntimeError> {
+    match self.cards.get(&name) {
+      Some(fields) => Ok(fields.clone()),
+      None => Err(RuntimeError::CardNotFound(name)),
     }
   }
 
-  pub fn begin_scope(&mut self) {
-    self.mem.push(HashMap::new());
+  pub fn declare_type(&mut self, name: String, variants: Vec<(String, Vec<String>)>) {
+    self.types.insert(name, variants);
+  }
+
+  // a frame with an empty slot for each of the names the resolver laid out for it
+  pub fn begin_scope(&mut self, names: &[Symbol]) {
+    self.mem.push(names.iter().map(|x| (*x, None)).collect());
+  }
+
+  // makes room in the top frame for names the resolver added to it
+  pub fn extend_top(&mut self, names: &[Symbol]) {
+    if let Some(frame) = self.mem.last_mut() {
+      for x in names.iter().skip(frame.len()) {
+        frame.push((*x, None));
+      }
+    }
   }
 
   pub fn end_scope(&mut self) {
@@ -0,0 +1,152 @@
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error as DeError;
+use serde::ser::Error as SerError;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+// a variable name, as its number in the Interner that handed it out. Two
+// from the same Interner are the same name exactly when they are equal
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+  // the name this stands for in the interner in view, if one is
+  fn with_name<T, F: FnOnce(Option<&str>) -> T>(self, f: F) -> T {
+    VIEW.with(|view| match *view.borrow() {
+      Some(ref symbols) => {
+        let names = symbols.0.borrow();
+        f(names.names.get(self.0 as usize).map(|name| &**name))
+      },
+      None => f(None),
+    })
+  }
+}
+
+// outside an interner's view a symbol can only show its number, so values
+// are shown through Interner::display
+impl fmt::Debug for Symbol {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    self.with_name(|name| match name {
+      Some(name) => write!(f, "{:?}", name),
+      None => write!(f, "Symbol({})", self.0),
+    })
+  }
+}
+
+impl fmt::Display for Symbol {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    self.with_name(|name| match name {
+      Some(name) => write!(f, "{}", name),
+      None => write!(f, "#{}", self.0),
+    })
+  }
+}
+
+// written as the plain name, so a snapshot can be loaded into any interner
+impl Serialize for Symbol {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    self.with_name(|name| match name {
+      Some(name) => serializer.serialize_str(name),
+      None => Err(S::Error::custom("a symbol is saved with the interner that made it in view")),
+    })
+  }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Symbol, D::Error> {
+    let name = String::deserialize(deserializer)?;
+
+    VIEW.with(|view| match *view.borrow() {
+      Some(ref symbols) => Ok(symbols.intern(&name)),
+      None => Err(D::Error::custom("a symbol is loaded with an interner in view")),
+    })
+  }
+}
+
+#[derive(Default)]
+struct Names {
+  names: Vec<Rc<str>>,
+  ids: HashMap<Rc<str>, Symbol>,
+}
+
+// hands out one Symbol per name. An Interpreter owns one, and its clones
+// share its names, so the lexer can intern into it while it runs
+#[derive(Clone, Default)]
+pub struct Interner(Rc<RefCell<Names>>);
+
+thread_local! {
+  // the interner symbols are shown and saved by, while one is in view
+  static VIEW: RefCell<Option<Interner>> = const { RefCell::new(None) };
+}
+
+impl Interner {
+  pub fn new() -> Interner {
+    Interner::default()
+  }
+
+  pub fn intern(&self, name: &str) -> Symbol {
+    if let Some(x) = self.lookup(name) {
+      return x;
+    }
+
+    let mut names = self.0.borrow_mut();
+    let x = Symbol(names.names.len() as u32);
+    let name: Rc<str> = Rc::from(name);
+    names.names.push(name.clone());
+    names.ids.insert(name, x);
+    x
+  }
+
+  // the symbol for a name, when it has been interned
+  pub fn lookup(&self, name: &str) -> Option<Symbol> {
+    self.0.borrow().ids.get(name).cloned()
+  }
+
+  pub fn name(&self, x: Symbol) -> Option<Rc<str>> {
+    self.0.borrow().names.get(x.0 as usize).cloned()
+  }
+
+  pub fn len(&self) -> usize {
+    self.0.borrow().names.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.0.borrow().names.is_empty()
+  }
+
+  // x, shown with the names this interner gave its symbols
+  pub fn display<'a, T: fmt::Display + ?Sized>(&'a self, x: &'a T) -> Shown<'a, T> {
+    Shown { symbols: self, x }
+  }
+
+  // runs f with this interner in view, so the symbols it made show and
+  // save as their names
+  pub(crate) fn view<T, F: FnOnce() -> T>(&self, f: F) -> T {
+    let _outer = Outer(VIEW.with(|view| view.replace(Some(self.clone()))));
+    f()
+  }
+}
+
+// puts back the interner that was in view, even when f panics
+struct Outer(Option<Interner>);
+
+impl Drop for Outer {
+  fn drop(&mut self) {
+    let outer = self.0.take();
+    let _ = VIEW.try_with(|view| *view.borrow_mut() = outer);
+  }
+}
+
+// a value with the interner to show its names from, see Interner::display
+pub struct Shown<'a, T: 'a + ?Sized> {
+  symbols: &'a Interner,
+  x: &'a T,
+}
+
+impl<'a, T: fmt::Display + ?Sized> fmt::Display for Shown<'a, T> {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    self.symbols.view(|| self.x.fmt(f))
+  }
+}
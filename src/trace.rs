@@ -0,0 +1,96 @@
+use expr::{Expr, UnOp};
+use expr::Expr::*;
+use printer::source;
+
+// one rule firing, as seen by a tracer
+pub struct TraceStep<'a> {
+  // how deep inside the program the rule fired; 0 is the whole program
+  pub depth: usize,
+  pub rule: &'a str,
+  pub before: &'a Expr,
+  pub after: &'a Expr,
+}
+
+pub type Tracer = Box<dyn FnMut(&TraceStep)>;
+
+// the name of the rule that steps e when e itself is the redex
+pub fn rule(e: &Expr) -> String {
+  let rule = match *e {
+    Var(_) | Local(_, _, _) => "Var",
+    Uop(UnOp::Not, _) => "Not",
+    Uop(UnOp::Neg, _) => "Neg",
+    Bop(ref op, _, _) => return format!("{:?}", op),
+    Ternary(_, _, _) => "Battle",
+    While(_, _, _, _, _, _, _) => "Defend",
+    Decl(_, _, _, _, _) => "Decl",
+    Func(_, _, _, _, _) => "Func",
+    FnCall(_, _) => "FnCall",
+    Scope(_, _) => "Scope exit",
+    Print(_) => "Print",
+    PrintVarName(_) => "PrintVarName",
+    Give(_) => "Give",
+    Builtin(ref b, _) => b.name(),
+    PartyLit(_) => "PartyLit",
+    Index(_, _) => "Index",
+    DexLit(_) => "DexLit",
+    CardDecl(_, _, _) => "CardDecl",
+    CardLit(_, _, _) => "CardLit",
+    Field(_, _) => "Field",
+    TypeDecl(_, _, _) => "TypeDecl",
+    Evolve(_, _) => "Evolve",
+    Flee(_) => "Flee",
+    Break(_) => "Break",
+    Continue(_) => "Continue",
+    For(_, _, _, _, None) => "For",
+    For(_, _, _, _, Some(_)) => "For pass",
+    Escape(_, _, _) => "Escape",
+    Rope(_) => "Rope exit",
+    Faint(_) => "Faint",
+    Unwind(_) => "Unwind",
+    Import(_) => "Import",
+    At(_, _) => "At",
+    _ => "Value",
+  };
+
+  rule.to_string()
+}
+
+// turns trace steps into numbered lines: the program after each step, and
+// under it the part that changed when the rule fired deeper inside
+pub struct TraceFormatter {
+  top_level_only: bool,
+  steps: usize,
+  redex: Option<String>,
+}
+
+impl TraceFormatter {
+  pub fn new(top_level_only: bool) -> TraceFormatter {
+    TraceFormatter {
+      top_level_only,
+      steps: 0,
+      redex: None,
+    }
+  }
+
+  pub fn format(&mut self, step: &TraceStep) -> Vec<String> {
+    if step.depth > 0 {
+      if !self.top_level_only {
+        self.redex = Some(format!("      {} ~> {}", source(step.before), source(step.after)));
+      }
+
+      return Vec::new();
+    }
+
+    let mut lines = Vec::new();
+
+    if self.steps == 0 {
+      lines.push(format!("{:>4}. {}", 0, source(step.before)));
+    }
+
+    self.steps += 1;
+    lines.push(format!("{:>4}. [{}] {}", self.steps, step.rule, source(step.after)));
+    lines.extend(self.redex.take());
+
+    lines
+  }
+}
@@ -0,0 +1,87 @@
+use std::error;
+use std::fmt;
+use types::Type;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeError {
+  Mismatch(Type, Type), // expected, found
+  InfiniteType(Type, Type),
+  WrongNumberOfArgs(usize, usize), // expected, given
+  UnboundVariable(String),
+  AssignToConst(String),
+  GiveFromConst(String),
+  CardNotFound(String),
+  FieldNotFound(String, String),
+  MissingField(String, String),
+  NotACard(Type),
+  NotIndexable(Type),
+  NotIterable(Type),
+  NoLength(Type),
+  UnknownType(String),
+  NonExhaustiveMatch(Vec<String>),
+}
+
+impl fmt::Display for TypeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    // both types of a pair name their variables together
+    let mut names = Vec::new();
+
+    match *self {
+      TypeError::Mismatch(ref t1, ref t2) => write!(f, "Expected {} and found {}", t1.show(&mut names), t2.show(&mut names)),
+      TypeError::InfiniteType(ref t1, ref t2) => write!(f, "{} would have to contain itself to be {}", t1.show(&mut names), t2.show(&mut names)),
+      TypeError::WrongNumberOfArgs(ref expected, ref given) => write!(f, "attack expects {} arguments but was given {}", expected, given),
+      TypeError::UnboundVariable(ref x) => write!(f, "Variable {:?} is not bound", x),
+      TypeError::AssignToConst(ref x) => write!(f, "Cannot assign to const {}", x),
+      TypeError::GiveFromConst(ref x) => write!(f, "Cannot give from const {}", x),
+      TypeError::CardNotFound(ref s) => write!(f, "Card {} has not been declared", s),
+      TypeError::FieldNotFound(ref s, ref x) => write!(f, "Card {} has no field {}", s, x),
+      TypeError::MissingField(ref s, ref x) => write!(f, "Card {} is missing field {}", s, x),
+      TypeError::NotACard(ref t) => write!(f, "Expected a card and found {}", t),
+      TypeError::NotIndexable(ref t) => write!(f, "Cannot index into {}", t),
+      TypeError::NotIterable(ref t) => write!(f, "for loop cannot iterate over {}", t),
+      TypeError::NoLength(ref t) => write!(f, "Expected move, party or dex and found {}", t),
+      TypeError::UnknownType(ref s) => write!(f, "No card or type is named {}", s),
+      TypeError::NonExhaustiveMatch(ref ps) => write!(f, "evolve does not cover {}", ps.join(", ")),
+    }
+  }
+}
+
+impl error::Error for TypeError {
+  fn description(&self) -> &str {
+    match *self {
+      TypeError::Mismatch(_, _) => "Mismatched types",
+      TypeError::InfiniteType(_, _) => "Infinite type",
+      TypeError::WrongNumberOfArgs(_, _) => "Wrong number of arguments",
+      TypeError::UnboundVariable(_) => "Unbound variable",
+      TypeError::AssignToConst(_) => "Assignment to const",
+      TypeError::GiveFromConst(_) => "Give from const",
+      TypeError::CardNotFound(_) => "Card not found",
+      TypeError::FieldNotFound(_, _) => "Field not found",
+      TypeError::MissingField(_, _) => "Missing field",
+      TypeError::NotACard(_) => "Not a card",
+      TypeError::NotIndexable(_) => "Not indexable",
+      TypeError::NotIterable(_) => "Not iterable",
+      TypeError::NoLength(_) => "No length",
+      TypeError::UnknownType(_) => "Unknown type",
+      TypeError::NonExhaustiveMatch(_) => "Non-exhaustive evolve",
+    }
+  }
+
+  fn cause(&self) -> Option<&dyn error::Error> {
+    None
+  }
+}
+
+// a type error and where in the source it was found
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+  pub line: usize,
+  pub column: usize,
+  pub error: TypeError,
+}
+
+impl fmt::Display for Diagnostic {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}:{}: {}", self.line, self.column, self.error)
+  }
+}
@@ -0,0 +1,98 @@
+use serde::{Serialize, Deserialize};
+use std::fmt;
+
+// what the checker knows about a value before the program runs, and what
+// an annotation says about one
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Type {
+  Int,
+  Bool,
+  Str,
+  Unit,
+  Party(Box<Type>),
+  Dex(Box<Type>, Box<Type>),
+  Attack(Vec<Type>, Box<Type>),
+  Card(String),
+  // a type declared with type, e.g. Element for Fire | Water
+  Named(String),
+  Module(String),
+  // not known yet, or free to be anything in a polymorphic attack
+  Var(usize),
+}
+
+impl Type {
+  pub fn occurs(&self, v: usize) -> bool {
+    match *self {
+      Type::Var(u) => u == v,
+      Type::Party(ref t) => t.occurs(v),
+      Type::Dex(ref k, ref t) => k.occurs(v) || t.occurs(v),
+      Type::Attack(ref ps, ref r) => ps.iter().any(|p| p.occurs(v)) || r.occurs(v),
+      _ => false,
+    }
+  }
+
+  // every type variable, in the order they are written
+  pub fn vars(&self, vars: &mut Vec<usize>) {
+    match *self {
+      Type::Var(v) if !vars.contains(&v) => vars.push(v),
+      Type::Party(ref t) => t.vars(vars),
+      Type::Dex(ref k, ref t) => {
+        k.vars(vars);
+        t.vars(vars);
+      },
+      Type::Attack(ref ps, ref r) => {
+        for p in ps.iter() {
+          p.vars(vars);
+        }
+
+        r.vars(vars);
+      },
+      _ => {},
+    }
+  }
+
+  // type variables are named 'a, 'b, ... by their place in names, which
+  // several types can share so the same variable reads the same in each
+  pub fn show(&self, names: &mut Vec<usize>) -> String {
+    match *self {
+      Type::Int => String::from("pokemon"),
+      Type::Bool => String::from("battle"),
+      Type::Str => String::from("move"),
+      Type::Unit => String::from("()"),
+      Type::Party(ref t) => format!("party[{}]", t.show(names)),
+      Type::Dex(ref k, ref t) => format!("dex[{}, {}]", k.show(names), t.show(names)),
+      Type::Attack(ref ps, ref r) => {
+        let ps: Vec<String> = ps.iter().map(|p| p.show(names)).collect();
+        format!("attack({}) -> {}", ps.join(", "), r.show(names))
+      },
+      Type::Card(ref name) | Type::Named(ref name) => name.clone(),
+      Type::Module(ref path) => format!("module {:?}", path),
+      Type::Var(v) => {
+        let i = match names.iter().position(|n| *n == v) {
+          Some(i) => i,
+          None => {
+            names.push(v);
+            names.len() - 1
+          },
+        };
+
+        var_name(i)
+      },
+    }
+  }
+}
+
+fn var_name(i: usize) -> String {
+  let letter = (b'a' + (i % 26) as u8) as char;
+
+  match i / 26 {
+    0 => format!("'{}", letter),
+    n => format!("'{}{}", letter, n),
+  }
+}
+
+impl fmt::Display for Type {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", self.show(&mut Vec::new()))
+  }
+}
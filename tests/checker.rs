@@ -0,0 +1,191 @@
+extern crate meowth;
+
+#[cfg(test)]
+mod test {
+  use meowth::expr::Expr;
+  use meowth::interpreter::Interpreter;
+  use meowth::runtime_error::RuntimeError;
+  use meowth::type_error::TypeError;
+  use meowth::types::Type;
+
+  fn type_of(input: &str) -> Result<String, Vec<String>> {
+    match Interpreter::new().check(input) {
+      Ok(ty) => Ok(ty.to_string()),
+      Err(RuntimeError::TypeErrors(ds)) => Err(ds.iter().map(|d| d.to_string()).collect()),
+      Err(err) => panic!("unexpected error {}", err),
+    }
+  }
+
+  fn errors(input: &str) -> Vec<TypeError> {
+    match Interpreter::new().check(input) {
+      Err(RuntimeError::TypeErrors(ds)) => ds.into_iter().map(|d| d.error).collect(),
+      result => panic!("expected type errors, got {:?}", result),
+    }
+  }
+
+  #[test]
+  fn test_infers_types() {
+    assert_eq!(Ok("pokemon".to_string()), type_of("1 + 2 * 3"));
+    assert_eq!(Ok("battle".to_string()), type_of("1 beats 2 && !lose"));
+    assert_eq!(Ok("move".to_string()), type_of("\"level \" + 25"));
+    assert_eq!(Ok("()".to_string()), type_of("pokedex(1)"));
+    assert_eq!(Ok("party[pokemon]".to_string()), type_of("[1, 2, 3]"));
+    assert_eq!(Ok("dex[move, battle]".to_string()), type_of("dex { \"a\": win }"));
+    assert_eq!(Ok("pokemon".to_string()), type_of("bike i = 0; defend (i < 3) { i = i + 1 }; i"));
+    assert_eq!(Ok("pokemon".to_string()), type_of("battle (win) { 1 } run { 2 }"));
+    assert_eq!(Ok("()".to_string()), type_of("battle (win) { 1 }"));
+    assert_eq!(Ok("attack(pokemon) -> pokemon".to_string()), type_of("attack (n) { n + 1 }"));
+    assert_eq!(
+      Ok("pokemon".to_string()),
+      type_of("attack fib(n) { battle (n < 2) { flee n }; fib(n - 1) + fib(n - 2) }; fib(10)")
+    );
+    assert_eq!(Ok("Trainer".to_string()), type_of("card Trainer { name, badges }; Trainer { name: \"ash\", badges: 8 }"));
+    assert_eq!(
+      Ok("move".to_string()),
+      type_of("type Element = Fire | Water | Grass(level); evolve (Grass(3)) { Fire => \"hot\", Grass(n) => \"leafy\", _ => \"wet\" }")
+    );
+  }
+
+  #[test]
+  fn test_polymorphic_attacks() {
+    assert_eq!(Ok("attack('a) -> 'a".to_string()), type_of("attack (x) { x }"));
+    assert_eq!(Ok("attack(party['a], pokemon) -> 'a".to_string()), type_of("attack (xs, n) { xs[n] }"));
+    assert_eq!(
+      Ok("battle".to_string()),
+      type_of("attack id(x) { x }; id(1); id(\"one\"); id(win)")
+    );
+    assert_eq!(
+      Ok("attack(attack('a) -> 'b, attack('b) -> 'c) -> attack('a) -> 'c".to_string()),
+      type_of("attack (f, g) { attack (x) { g(f(x)) } }")
+    );
+
+    // bikes can change, so they are not
+    assert!(type_of("attack id(x) { x }; bike f = id; f(1); f(win)").is_err());
+  }
+
+  #[test]
+  fn test_builtin_names() {
+    assert_eq!(Ok("pokemon".to_string()), type_of("pokeball n = len(\"abc\"); bike len = 3; len + n"));
+    assert_eq!(Ok("battle".to_string()), type_of("attack len(s) { win }; len(\"abc\")"));
+    assert!(type_of("attack len(s) { win }; len(\"abc\") + 1").is_err());
+  }
+
+  #[test]
+  fn test_reports_every_error_with_its_place() {
+    assert_eq!(Err(vec!("1:1: Expected pokemon and found battle".to_string())), type_of("win + 3"));
+    assert_eq!(Err(vec!("1:8: Expected battle and found pokemon".to_string())), type_of("battle (5) { pokedex(1) }"));
+
+    let program = "pokeball a = win + 3;\nbattle (5) { a };\nlen(7)";
+    assert_eq!(
+      Err(vec!(
+        "1:14: Expected pokemon and found battle".to_string(),
+        "2:8: Expected battle and found pokemon".to_string(),
+        "3:1: Expected move, party or dex and found pokemon".to_string(),
+      )),
+      type_of(program)
+    );
+  }
+
+  #[test]
+  fn test_errors() {
+    assert_eq!(vec!(TypeError::UnboundVariable("nope".to_string())), errors("nope + 1"));
+    assert_eq!(vec!(TypeError::AssignToConst("x".to_string())), errors("pokeball x = 1; x = 2"));
+    assert_eq!(vec!(TypeError::GiveFromConst("x".to_string())), errors("pokeball x = 1; give(x)"));
+    assert_eq!(vec!(TypeError::WrongNumberOfArgs(1, 2)), errors("attack f(n) { n }; f(1, 2)"));
+    assert_eq!(vec!(TypeError::Mismatch(Type::Int, Type::Str)), errors("[1, \"two\"]"));
+    assert_eq!(vec!(TypeError::NotIterable(Type::Int)), errors("for x in 5 { pokedex(x) }"));
+    assert_eq!(
+      vec!(TypeError::Mismatch(Type::Bool, Type::Int)),
+      errors("attack f(n) { battle (n beats 0) { flee win }; n }; f(1)")
+    );
+
+    // attacks see their caller's bindings, so names bound later are fine inside one
+    assert!(type_of("attack f() { later + 1 }; pokeball later = 2; f()").is_ok());
+  }
+
+  #[test]
+  fn test_evolve_exhaustiveness() {
+    // missing arms are reported before anything runs, even when the value would match
+    assert_eq!(
+      vec!(TypeError::NonExhaustiveMatch(vec!("Water".to_string(), "Grass(_)".to_string()))),
+      errors("type Element = | Fire | Water | Grass(level); evolve (Fire) { Fire => 1 }")
+    );
+    assert_eq!(
+      vec!(TypeError::NonExhaustiveMatch(vec!("Grass(_)".to_string()))),
+      errors("type Element = | Fire | Grass(level); evolve (Fire) { Fire => 1, Grass(1) => 2 }")
+    );
+    assert_eq!(vec!(TypeError::NonExhaustiveMatch(vec!("lose".to_string()))), errors("evolve (win) { win => 1 }"));
+    assert_eq!(vec!(TypeError::NonExhaustiveMatch(vec!("_".to_string()))), errors("evolve (1) { 1 => 1, 2 => 2 }"));
+
+    // a wildcard, a binding or every constructor covers them all
+    assert_eq!(Ok("pokemon".to_string()), type_of("evolve (1) { 1 => 1, _ => 2 }"));
+    assert_eq!(Ok("pokemon".to_string()), type_of("evolve (\"mew\") { n => 1 }"));
+    assert_eq!(Ok("pokemon".to_string()), type_of("type Element = | Fire | Grass(level); evolve (Fire) { Fire => 1, Grass(n) => n }"));
+    assert_eq!(Ok("pokemon".to_string()), type_of("evolve (win) { win => 1, lose => 2 }"));
+
+    // with typecheck on, nothing runs
+    let mut interpreter = Interpreter::new();
+    interpreter.set_typecheck(true);
+    assert!(matches!(interpreter.eval("bike x = 1; evolve (x) { 1 => 1 }"), Err(RuntimeError::TypeErrors(_))));
+  }
+
+  #[test]
+  fn test_cards() {
+    let card = "card Trainer { name, badges };";
+
+    assert_eq!(
+      vec!(TypeError::FieldNotFound("Trainer".to_string(), "age".to_string())),
+      errors(&format!("{} pokeball t = Trainer {{ name: \"ash\", badges: 8 }}; t.age", card))
+    );
+    assert_eq!(
+      vec!(
+        TypeError::FieldNotFound("Trainer".to_string(), "age".to_string()),
+        TypeError::MissingField("Trainer".to_string(), "badges".to_string()),
+      ),
+      errors(&format!("{} Trainer {{ name: \"ash\", age: 10 }}", card))
+    );
+    assert_eq!(vec!(TypeError::CardNotFound("Gym".to_string())), errors("Gym { leader: \"brock\" }"));
+    assert_eq!(
+      vec!(TypeError::Mismatch(Type::Int, Type::Str)),
+      errors(&format!("{} pokeball t = Trainer {{ name: \"ash\", badges: 8 }}; t.badges + t.name - 1", card))
+    );
+
+    // a field only one card has gives away the card
+    assert_eq!(
+      Ok("attack(Trainer) -> pokemon".to_string()),
+      type_of(&format!("{} attack (t) {{ t.badges + 1 }}", card))
+    );
+  }
+
+  #[test]
+  fn test_checks_before_running() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_typecheck(true);
+
+    // nothing is bound, because nothing ran
+    assert!(interpreter.eval("bike x = 1; x = x + win").is_err());
+    let x = interpreter.symbols().intern("x");
+    assert!(interpreter.state.get(x).is_err());
+
+    // what a session has bound is known to later inputs
+    assert_eq!(Ok(Expr::Int(1)), interpreter.eval("pokeball n = 1; attack twice(f, x) { f(f(x)) }; n"));
+    assert_eq!(Ok(Type::Int), interpreter.check("twice(attack (m) { m * 2 }, n)"));
+    assert!(interpreter.eval("twice(n, n)").is_err());
+    assert_eq!(Ok(Expr::Int(4)), interpreter.eval("twice(attack (m) { m * 2 }, n)"));
+  }
+
+  #[test]
+  fn test_annotations() {
+    assert_eq!(Ok("attack(pokemon) -> pokemon".to_string()), type_of("attack (n: pokemon) -> pokemon { n }"));
+    assert_eq!(Ok("party[move]".to_string()), type_of("pokeball xs: party[move] = []; xs"));
+    assert_eq!(
+      Ok("Trainer".to_string()),
+      type_of("card Trainer { name }; attack (t: Trainer) { t }(Trainer { name: \"ash\" })")
+    );
+
+    assert_eq!(Err(vec!("1:25: Expected pokemon and found move".to_string())), type_of("pokeball mew: pokemon = \"mew\"; mew"));
+    assert_eq!(vec!(TypeError::Mismatch(Type::Str, Type::Int)), errors("attack (n) -> move { n + 1 }(1)"));
+    assert_eq!(vec!(TypeError::Mismatch(Type::Int, Type::Bool)), errors("attack gnaw(n: pokemon) { n }; gnaw(win)"));
+    assert_eq!(vec!(TypeError::UnknownType("Gym".to_string())), errors("pokeball g: Gym = 1; g"));
+  }
+}
@@ -0,0 +1,131 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::process;
+use std::process::{Command, Output, Stdio};
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  fn meowth(args: &[&str], stdin: Option<&str>) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_meowth"))
+      .args(args)
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .spawn()
+      .unwrap();
+
+    {
+      let mut pipe = child.stdin.take().unwrap();
+      if let Some(input) = stdin {
+        pipe.write_all(input.as_bytes()).unwrap();
+      }
+    }
+
+    child.wait_with_output().unwrap()
+  }
+
+  fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+  }
+
+  #[test]
+  fn test_eval_flag() {
+    let output = meowth(&["-e", "1 + 2"], None);
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("3\n", stdout(&output));
+  }
+
+  #[test]
+  fn test_run_file() {
+    let output = meowth(&["run", "tests/scripts/args.meow", "pikachu", "mew"], None);
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("2\npikachu\n", stdout(&output));
+
+    let output = meowth(&["tests/scripts/args.meow", "eevee"], None);
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("1\neevee\n", stdout(&output));
+  }
+
+  #[test]
+  fn test_stdin() {
+    let output = meowth(&[], Some("pokedex(151)"));
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("151\n", stdout(&output));
+  }
+
+  #[test]
+  fn test_exit_codes() {
+    let output = meowth(&["-e", "1 +* 2"], None);
+    assert_eq!(Some(65), output.status.code());
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("Error: Parser error"));
+    assert_eq!("", stdout(&output));
+
+    assert_eq!(Some(65), meowth(&["-e", "missingno"], None).status.code());
+    assert_eq!(Some(65), meowth(&["-e", "attack lost() { missingno }; lost()"], None).status.code());
+    assert_eq!(Some(70), meowth(&["-e", "attack lost() { missingno }; lost(); pokeball missingno = 1; missingno"], None).status.code());
+    assert_eq!(Some(70), meowth(&[], Some("faint 1")).status.code());
+    assert_eq!(Some(66), meowth(&["run", "tests/scripts/missing.meow"], None).status.code());
+    assert_eq!(Some(64), meowth(&["run"], None).status.code());
+    assert_eq!(Some(64), meowth(&["--bogus"], None).status.code());
+  }
+
+  #[test]
+  fn test_trace_flag() {
+    let output = meowth(&["--trace", "-e", "attack f(n) { n * 2 }; f(1 + 2)"], None);
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("6\n", stdout(&output));
+
+    let trace = String::from_utf8_lossy(&output.stderr).to_string();
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!("   0. attack f(n) { n * 2 }; f(1 + 2)", lines[0]);
+    assert_eq!("   1. [Decl] f(1 + 2)", lines[1]);
+    assert!(lines.contains(&"      1 + 2 ~> 3"));
+    assert!(lines.contains(&"   7. [Scope exit] 6"));
+
+    let output = meowth(&["--trace=top", "-e", "attack f(n) { n * 2 }; f(1 + 2)"], None);
+    let trace = String::from_utf8_lossy(&output.stderr).to_string();
+    assert!(trace.lines().all(|line| !line.contains("~>")));
+    assert_eq!(8, trace.lines().count());
+  }
+
+  #[test]
+  fn test_typecheck_flag() {
+    // every error is reported and nothing runs
+    let output = meowth(&["--typecheck", "-e", "pokedex(1); win + 3; battle (5) { 1 }"], None);
+    assert_eq!(Some(65), output.status.code());
+    assert_eq!("", stdout(&output));
+    assert_eq!(
+      "Error: 1:13: Expected pokemon and found battle\n1:29: Expected battle and found pokemon\n",
+      String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = meowth(&["--typecheck", "--trace=top", "tests/scripts/args.meow", "eevee"], None);
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("1\neevee\n", stdout(&output));
+  }
+
+  #[test]
+  fn test_fmt() {
+    let output = meowth(&["fmt", "--check", "tests/fmt/formatted.meow"], None);
+    assert_eq!(Some(0), output.status.code());
+
+    let output = meowth(&["fmt", "--check", "tests/fmt/messy.meow"], None);
+    assert_eq!(Some(1), output.status.code());
+    assert_eq!("tests/fmt/messy.meow is not formatted\n", String::from_utf8_lossy(&output.stderr));
+
+    let output = meowth(&["fmt"], Some("pokeball  x=1;x"));
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("pokeball x = 1;\nx\n", stdout(&output));
+    assert_eq!(Some(65), meowth(&["fmt"], Some("1 +* 2")).status.code());
+
+    let file = env::temp_dir().join(format!("meowth-fmt-{}.meow", process::id()));
+    fs::copy("tests/fmt/messy.meow", &file).unwrap();
+    let output = meowth(&["fmt", file.to_str().unwrap()], None);
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!(fs::read_to_string("tests/fmt/formatted.meow").unwrap(), fs::read_to_string(&file).unwrap());
+    fs::remove_file(&file).unwrap();
+  }
+}
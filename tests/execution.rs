@@ -0,0 +1,120 @@
+extern crate meowth;
+
+#[cfg(test)]
+mod test {
+  use meowth::execution::{start, Progress};
+  use meowth::expr::Expr;
+  use meowth::interpreter::Interpreter;
+  use meowth::parser::parser_error::ParserError;
+  use meowth::runtime_error::RuntimeError;
+
+  const COUNT: &str = "bike i = 0; defend (i < 10) { i = i + 1 }; i";
+
+  fn run_to_end(input: &str, budget: usize) -> (Result<Expr, RuntimeError>, usize) {
+    let mut execution = start(input).unwrap();
+    let mut calls = 0;
+
+    loop {
+      calls += 1;
+
+      match execution.run_for(budget) {
+        Ok(Progress::Pending) => continue,
+        Ok(Progress::Done(v)) => return (Ok(v), calls),
+        Err(err) => return (Err(err), calls),
+      }
+    }
+  }
+
+  #[test]
+  fn test_run_for() {
+    let mut execution = start(COUNT).unwrap();
+
+    assert_eq!(Ok(Progress::Pending), execution.run_for(0));
+    assert_eq!(0, execution.steps());
+    assert_eq!(Ok(Progress::Pending), execution.run_for(5));
+    assert_eq!(5, execution.steps());
+
+    let (result, calls) = run_to_end(COUNT, 1000000);
+    assert_eq!(Ok(Expr::Int(10)), result);
+    assert_eq!(1, calls);
+
+    // any budget gets the same answer, just over more calls
+    let (result, calls) = run_to_end(COUNT, 3);
+    assert_eq!(Ok(Expr::Int(10)), result);
+    assert!(calls > 10);
+
+    // a finished execution stays finished
+    let mut execution = start("1 + 2").unwrap();
+    assert_eq!(Ok(Progress::Done(Expr::Int(3))), execution.run_for(10));
+    assert_eq!(1, execution.steps());
+    assert_eq!(Ok(Progress::Done(Expr::Int(3))), execution.run_for(10));
+    assert_eq!(1, execution.steps());
+  }
+
+  #[test]
+  fn test_interleaved_executions() {
+    let mut slow = start("bike i = 0; defend (i < 100) { i = i + 1 }; i").unwrap();
+    let mut fast = start("bike i = 5; i * 2").unwrap();
+
+    let mut slow_done = None;
+    let mut fast_done = None;
+
+    while slow_done.is_none() || fast_done.is_none() {
+      if let Ok(Progress::Done(v)) = slow.run_for(4) {
+        slow_done = Some(v);
+      }
+      if let Ok(Progress::Done(v)) = fast.run_for(4) {
+        fast_done = fast_done.or(Some(v));
+      }
+    }
+
+    // each execution has its own state
+    assert_eq!(Some(Expr::Int(100)), slow_done);
+    assert_eq!(Some(Expr::Int(10)), fast_done);
+  }
+
+  #[test]
+  fn test_execution_errors() {
+    assert!(matches!(start("1 +* 2"), Err(RuntimeError::ParserError(ParserError::InvalidToken(_, _)))));
+
+    let mut execution = start("bike i = 0; i = i + 1; attack lost() { missingno }; lost(); pokeball missingno = 0; missingno").unwrap();
+    assert_eq!(Ok(Progress::Pending), execution.run_for(1));
+    assert_eq!(Err(RuntimeError::VariableNotFound("missingno".to_string())), execution.run_for(100));
+    assert_eq!(Err(RuntimeError::VariableNotFound("missingno".to_string())), execution.run_for(100));
+
+    // what ran before the error is still there to inspect
+    let mut interpreter = execution.into_interpreter();
+    assert_eq!(Ok(Expr::Int(1)), interpreter.eval("i"));
+  }
+
+  #[test]
+  fn test_start_keeps_interpreter_state() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval("attack double(n) { n * 2 };").unwrap();
+
+    let mut execution = interpreter.start("double(21)").ok().unwrap();
+    assert_eq!(Ok(Progress::Done(Expr::Int(42))), execution.run_for(100));
+    assert_eq!(1, execution.interpreter().state.mem.len());
+  }
+
+  #[test]
+  fn test_start_hands_back_interpreter_on_error() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval("pokeball n = 21;").unwrap();
+
+    // a typo does not lose the session
+    let mut interpreter = match interpreter.start("pokeball m =") {
+      Err((interpreter, RuntimeError::ParserError(_))) => *interpreter,
+      _ => panic!("expected a parser error"),
+    };
+    assert_eq!(Ok(Expr::Int(42)), interpreter.eval("n * 2"));
+
+    interpreter.set_typecheck(true);
+    let interpreter = match interpreter.start("n + win") {
+      Err((interpreter, RuntimeError::TypeErrors(_))) => *interpreter,
+      _ => panic!("expected type errors"),
+    };
+    let mut execution = interpreter.start("n * 2").ok().unwrap();
+    assert_eq!(Ok(Progress::Done(Expr::Int(42))), execution.run_for(100));
+  }
+}
@@ -0,0 +1,71 @@
+extern crate meowth;
+
+#[cfg(test)]
+mod test {
+  use meowth::formatter::format;
+  use meowth::parser::parser::parse;
+  use meowth::symbol::Interner;
+  use std::fs;
+
+  fn check(input: &str, expected: &str) {
+    let formatted = format(input).unwrap();
+    assert_eq!(expected, formatted);
+
+    let symbols = Interner::new();
+    assert_eq!(parse(input, &symbols).unwrap(), parse(&formatted, &symbols).unwrap());
+    assert_eq!(formatted, format(&formatted).unwrap(), "formatting twice changed it");
+  }
+
+  #[test]
+  fn test_spacing() {
+    check("pokeball   mew=151;mew+1", "pokeball mew = 151;\nmew + 1\n");
+    check("f( 1,2 )[0].x", "f(1, 2)[0].x\n");
+    check("1- -2*(3)", "1 - -2 * (3)\n");
+    check("win?1:2", "win ? 1 : 2\n");
+    check("dex {1:2}", "dex { 1: 2 }\n");
+    check("dex {}", "dex {}\n");
+    check("for i in 0..10 by 2 {i}", "for i in 0..10 by 2 { i }\n");
+    check("for i in -3..3 by -1 {i}", "for i in -3..3 by -1 { i }\n");
+    check("attack(x){x}(1)", "attack (x) { x }(1)\n");
+    check("type E = Fire|Grass(level);E", "type E = Fire | Grass(level);\nE\n");
+  }
+
+  #[test]
+  fn test_blocks_keep_their_lines() {
+    check("battle (win) { 1 } run { 2 }", "battle (win) { 1 } run { 2 }\n");
+    check("battle (win) {\n1;2 } run { 2 }", "battle (win) {\n  1;\n  2\n} run { 2 }\n");
+    check("attack f(n) {\n    battle (n < 1) {\n  0\n} run {\n f(n - 1) }\n};\nf(3)",
+      "attack f(n) {\n  battle (n < 1) {\n    0\n  } run {\n    f(n - 1)\n  }\n};\nf(3)\n");
+  }
+
+  #[test]
+  fn test_comments_and_blank_lines() {
+    check("// top\n\n\n\npokeball a = 1; // one\n// two\nbike b = 2;\n\nb // end\n",
+      "// top\n\npokeball a = 1; // one\n// two\nbike b = 2;\n\nb // end\n");
+    check("defend (lose) { // never\n  1\n}", "defend (lose) { // never\n  1\n}\n");
+    check("#!/usr/bin/env meowth\n1", "#!/usr/bin/env meowth\n1\n");
+  }
+
+  #[test]
+  fn test_files() {
+    let messy = fs::read_to_string("tests/fmt/messy.meow").unwrap();
+    let formatted = fs::read_to_string("tests/fmt/formatted.meow").unwrap();
+    check(&messy, &formatted);
+
+    for dir in &["tests/modules", "tests/scripts"] {
+      for entry in fs::read_dir(dir).unwrap() {
+        let input = fs::read_to_string(entry.unwrap().path()).unwrap();
+        let formatted = format(&input).unwrap();
+        let symbols = Interner::new();
+        assert_eq!(parse(&input, &symbols).unwrap(), parse(&formatted, &symbols).unwrap());
+        assert_eq!(formatted, format(&formatted).unwrap());
+      }
+    }
+  }
+
+  #[test]
+  fn test_invalid_programs_are_not_formatted() {
+    assert!(format("1 +* 2").is_err());
+    assert!(format("\"open").is_err());
+  }
+}
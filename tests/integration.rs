@@ -6,6 +6,13 @@ mod tests {
   use meowth::interpreter::Interpreter;
   use meowth::expr::Expr;
   use meowth::runtime_error::RuntimeError;
+  use meowth::resolver_error::ResolverError;
+  use meowth::parser::parser_error::ParserError;
+  use meowth::parser::lexer_error::LexerError;
+  use meowth::parser::token::Token;
+  use std::env;
+  use std::fs;
+  use std::process;
 
   extern crate env_logger;
 
@@ -78,7 +85,7 @@ mod tests {
     );
 
     assert_eq!(
-      Err(RuntimeError::VariableNotFound(String::from("foo"))),
+      Err(RuntimeError::ResolverError(ResolverError::UndefinedVariable(String::from("foo")))),
       meowth("
         speak(foo);
       ")
@@ -575,4 +582,1136 @@ mod tests {
     assert_eq!(Ok(Expr::Int(2)), meowth("(1-1)+(2-2)+(3-3)+((1+2)-((3-2)+1)+1)"));
     assert_eq!(Ok(Expr::Int(0)), meowth("((((((((((1-1)))+1))))-1)))"));
   }
+
+  #[test]
+  pub fn test_strings() {
+    let _ = env_logger::init();
+
+    assert_eq!(Ok(Expr::Str("pikachu".to_string())), meowth("\"pikachu\""));
+    assert_eq!(Ok(Expr::Str("".to_string())), meowth("\"\""));
+    assert_eq!(Ok(Expr::Str("thunder shock".to_string())), meowth("\"thunder\" + \" \" + \"shock\""));
+    assert_eq!(Ok(Expr::Str("level 25".to_string())), meowth("pokeball lvl = 25; \"level \" + lvl"));
+    assert_eq!(Ok(Expr::Str("win!".to_string())), meowth("(1 draws 1) + \"!\""));
+
+    assert_eq!(Ok(Expr::Bool(true)), meowth("\"mew\" draws \"mew\""));
+    assert_eq!(Ok(Expr::Bool(false)), meowth("\"mew\" draws \"mewtwo\""));
+    assert_eq!(Ok(Expr::Bool(true)), meowth("\"mew\" != \"mewtwo\""));
+    assert_eq!(Ok(Expr::Bool(false)), meowth("\"151\" draws 151"));
+
+    assert_eq!(Ok(Expr::Str("a\"b\\c\nd\te".to_string())), meowth("\"a\\\"b\\\\c\\nd\\te\""));
+
+    assert_eq!(Ok(Expr::Undefined), meowth("pokeball move = \"tackle\"; pokedex(move);"));
+  }
+
+  #[test]
+  pub fn test_string_builtins() {
+    let _ = env_logger::init();
+
+    assert_eq!(Ok(Expr::Int(7)), meowth("len(\"pikachu\")"));
+    assert_eq!(Ok(Expr::Int(0)), meowth("len(\"\")"));
+    assert_eq!(Ok(Expr::Int(7)), meowth("len(\"poké\" + \"mon\")"));
+    assert_eq!(Ok(Expr::Str("pika".to_string())), meowth("substr(\"pikachu\", 0, 4)"));
+    assert_eq!(Ok(Expr::Str("chu".to_string())), meowth("pokeball s = \"pikachu\"; substr(s, 4, len(s))"));
+    assert_eq!(Ok(Expr::Str("".to_string())), meowth("substr(\"mew\", 1, 1)"));
+    assert_eq!(Ok(Expr::Str("k".to_string())), meowth("char_at(\"pikachu\", 2)"));
+    assert_eq!(Ok(Expr::Str("é".to_string())), meowth("char_at(\"poké\", 3)"));
+
+    assert_eq!(Err(RuntimeError::IndexOutOfBounds(3, 3)), meowth("char_at(\"mew\", 3)"));
+    assert_eq!(Err(RuntimeError::IndexOutOfBounds(-1, 3)), meowth("char_at(\"mew\", -1)"));
+    assert_eq!(Err(RuntimeError::IndexOutOfBounds(4, 3)), meowth("substr(\"mew\", 0, 4)"));
+    assert_eq!(Err(RuntimeError::IndexOutOfBounds(1, 3)), meowth("substr(\"mew\", 2, 1)"));
+    assert_eq!(
+      Err(RuntimeError::WrongNumberOfArgs("len".to_string(), 1, 2)),
+      meowth("len(\"mew\", \"two\")")
+    );
+    assert_eq!(
+      Err(RuntimeError::InvalidTypeConversion("str, party or dex".to_string(), Expr::Int(151))),
+      meowth("len(151)")
+    );
+  }
+
+  #[test]
+  pub fn test_builtin_names() {
+    let _ = env_logger::init();
+
+    assert_eq!(Ok(Expr::Int(4)), meowth("bike len = 3; len + 1"));
+    assert_eq!(Ok(Expr::Int(6)), meowth("pokeball in = 1; pokeball by = 2; pokeball as = 3; in + by + as"));
+    assert_eq!(Ok(Expr::Int(3)), meowth("pokeball ks = keys(dex { 1: 2, 3: 4 }); pokeball keys = len(ks); keys + 1"));
+
+    // whatever binds a builtin's name when it is called is called instead
+    assert_eq!(Ok(Expr::Int(42)), meowth("attack len(s) { 42 }; len(\"abc\")"));
+    assert_eq!(Ok(Expr::Int(7)), meowth("attack push(p, x) { x }; bike p = [1]; push(p, 7)"));
+    assert_eq!(
+      Ok(Expr::Party(vec!(Expr::Int(1), Expr::Int(2)))),
+      meowth("bike p = [1]; push(p, 2); pokeball push = 5; p")
+    );
+    assert_eq!(
+      Ok(Expr::Int(3)),
+      meowth("attack f(s) { len(s) }; attack g() { attack len(s) { 0 }; f(\"ab\") }; f(\"abc\") + g()")
+    );
+    assert_eq!(
+      Err(RuntimeError::SteppingOnValue(Expr::Int(1))),
+      meowth("pokeball get = 1; get(dex {}, 1)")
+    );
+  }
+
+  #[test]
+  pub fn test_string_lexer_errors() {
+    let _ = env_logger::init();
+
+    assert_eq!(
+      Err(RuntimeError::ParserError(ParserError::LexerError(
+        LexerError::UnterminatedString("unterminated string \"pika".to_string())
+      ))),
+      meowth("\"pika")
+    );
+
+    assert_eq!(
+      Err(RuntimeError::ParserError(ParserError::LexerError(
+        LexerError::UnterminatedString("unterminated string \"pika".to_string())
+      ))),
+      meowth("\"pika\\")
+    );
+
+    assert_eq!(
+      Err(RuntimeError::ParserError(ParserError::LexerError(
+        LexerError::InvalidEscape("invalid escape sequence \\q".to_string())
+      ))),
+      meowth("\"pika\\qchu\"")
+    );
+  }
+
+  #[test]
+  pub fn test_party() {
+    let _ = env_logger::init();
+
+    assert_eq!(Ok(Expr::Party(vec!())), meowth("[]"));
+    assert_eq!(
+      Ok(Expr::Party(vec!(Expr::Int(25), Expr::Int(151), Expr::Str("mew".to_string())))),
+      meowth("pokeball mew = 151; [25, mew, \"mew\"]")
+    );
+    assert_eq!(Ok(Expr::Int(151)), meowth("pokeball party = [25, 150 + 1]; party[1]"));
+    assert_eq!(Ok(Expr::Int(2)), meowth("[[1, 2], [3]][0][1]"));
+    assert_eq!(Ok(Expr::Int(3)), meowth("len([1, 2, 3])"));
+    assert_eq!(Ok(Expr::Int(0)), meowth("len([])"));
+
+    assert_eq!(Ok(Expr::Bool(true)), meowth("[1, [2, win]] draws [1, [2, win]]"));
+    assert_eq!(Ok(Expr::Bool(false)), meowth("[1, 2] draws [2, 1]"));
+    assert_eq!(Ok(Expr::Bool(true)), meowth("[1, 2] != [1, 2, 3]"));
+
+    assert_eq!(Err(RuntimeError::IndexOutOfBounds(2, 2)), meowth("[1, 2][2]"));
+    assert_eq!(Err(RuntimeError::IndexOutOfBounds(-1, 2)), meowth("[1, 2][-1]"));
+    assert_eq!(
+      Err(RuntimeError::InvalidTypeConversion("party".to_string(), Expr::Int(1))),
+      meowth("1[0]")
+    );
+  }
+
+  #[test]
+  pub fn test_party_push_pop() {
+    let _ = env_logger::init();
+
+    assert_eq!(
+      Ok(Expr::Party(vec!(Expr::Int(1), Expr::Int(2)))),
+      meowth("bike p = [1]; push(p, 1 + 1); p")
+    );
+    assert_eq!(Ok(Expr::Int(3)), meowth("bike p = [1, 2, 3]; pop(p)"));
+    assert_eq!(Ok(Expr::Int(2)), meowth("bike p = [1, 2, 3]; pop(p); len(p)"));
+
+    // each push and pop is an assignment, so give() rolls it back
+    assert_eq!(Ok(Expr::Int(1)), meowth("bike p = [1]; push(p, 2); give(p); len(p)"));
+
+    assert_eq!(Err(RuntimeError::EmptyParty("p".to_string())), meowth("bike p = []; pop(p)"));
+    assert_eq!(
+      Err(RuntimeError::InvalidConstAssignment(
+        Expr::Party(vec!(Expr::Int(1), Expr::Int(2))),
+        "p".to_string()
+      )),
+      meowth("pokeball p = [1]; push(p, 2)")
+    );
+    assert_eq!(
+      Err(RuntimeError::InvalidTypeConversion("var".to_string(), Expr::Party(vec!()))),
+      meowth("push([], 2)")
+    );
+  }
+
+  #[test]
+  pub fn test_party_capacity() {
+    let _ = env_logger::init();
+
+    assert_eq!(Ok(Expr::Int(6)), meowth("len([1, 2, 3, 4, 5, 6])"));
+    assert_eq!(Err(RuntimeError::PartyFull(6)), meowth("[1, 2, 3, 4, 5, 6, 7]"));
+    assert_eq!(
+      Err(RuntimeError::PartyFull(6)),
+      meowth("bike p = [1, 2, 3, 4, 5, 6]; push(p, 7)")
+    );
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_party_capacity(2);
+
+    assert_eq!(Ok(Expr::Undefined), interpreter.eval("bike p = [1];"));
+    assert_eq!(Ok(Expr::Undefined), interpreter.eval("push(p, 2)"));
+    assert_eq!(Err(RuntimeError::PartyFull(2)), interpreter.eval("push(p, 3)"));
+    assert_eq!(Err(RuntimeError::PartyFull(2)), interpreter.eval("[1, 2, 3]"));
+  }
+
+  #[test]
+  pub fn test_party_defend_loop() {
+    let _ = env_logger::init();
+
+    assert_eq!(
+      Ok(Expr::Int(176)),
+      meowth("
+        pokeball party = [25, 151];
+        bike total = 0;
+        bike i = 0;
+        defend (i < len(party)) {
+          total = total + party[i];
+          i = i + 1
+        };
+        total
+      ")
+    );
+
+    assert_eq!(
+      Ok(Expr::Party(vec!(Expr::Int(3), Expr::Int(2), Expr::Int(1)))),
+      meowth("
+        bike party = [1, 2, 3];
+        bike reversed = [];
+        defend (len(party) beats 0) {
+          push(reversed, pop(party))
+        };
+        reversed
+      ")
+    );
+  }
+
+  #[test]
+  pub fn test_party_display() {
+    assert_eq!("[]", format!("{}", meowth("[]").unwrap()));
+    assert_eq!("[1, [win, ()], \"mew\"]", format!("{}", meowth("[1, [win, pokedex(1)], \"mew\"]").unwrap()));
+  }
+
+  #[test]
+  pub fn test_dex() {
+    let _ = env_logger::init();
+
+    assert_eq!(Ok(Expr::Dex(vec!())), meowth("dex {}"));
+    assert_eq!(
+      Ok(Expr::Dex(vec!(
+        (Expr::Int(25), Expr::Str("pikachu".to_string())),
+        (Expr::Int(151), Expr::Str("mew".to_string())),
+      ))),
+      meowth("dex { 25: \"pikachu\", 150 + 1: \"mew\" }")
+    );
+
+    // a repeated key keeps its first position and its last value
+    assert_eq!(
+      Ok(Expr::Dex(vec!((Expr::Int(1), Expr::Int(3)), (Expr::Int(2), Expr::Int(2))))),
+      meowth("dex { 1: 1, 2: 2, 1: 3 }")
+    );
+
+    assert_eq!(Ok(Expr::Int(4)), meowth("pokeball caught = dex { 25: 4, 151: 1 }; caught[25]"));
+    assert_eq!(Ok(Expr::Int(1)), meowth("get(dex { \"mew\": 1, win: 2 }, \"mew\")"));
+    assert_eq!(Ok(Expr::Int(2)), meowth("get(dex { \"mew\": 1, win: 2 }, win)"));
+    assert_eq!(Ok(Expr::Bool(true)), meowth("has(dex { 25: 4 }, 25)"));
+    assert_eq!(Ok(Expr::Bool(false)), meowth("has(dex { 25: 4 }, 26)"));
+    assert_eq!(Ok(Expr::Int(2)), meowth("len(dex { 25: 4, 151: 1 })"));
+    assert_eq!(
+      Ok(Expr::Party(vec!(Expr::Int(151), Expr::Int(25)))),
+      meowth("keys(dex { 151: 1, 25: 4 })")
+    );
+    assert_eq!(
+      Ok(Expr::Party((1..8).map(Expr::Int).collect())),
+      meowth("keys(dex { 1: 1, 2: 2, 3: 3, 4: 4, 5: 5, 6: 6, 7: 7 })")
+    );
+
+    assert_eq!(Ok(Expr::Bool(true)), meowth("dex { 1: [2] } draws dex { 1: [2] }"));
+    assert_eq!(Ok(Expr::Bool(false)), meowth("dex { 1: 2 } draws dex { 1: 3 }"));
+
+    assert_eq!(Err(RuntimeError::KeyNotFound(Expr::Int(26))), meowth("dex { 25: 4 }[26]"));
+    assert_eq!(
+      Err(RuntimeError::KeyNotFound(Expr::Str("missingno".to_string()))),
+      meowth("get(dex {}, \"missingno\")")
+    );
+  }
+
+  #[test]
+  pub fn test_dex_set_remove() {
+    let _ = env_logger::init();
+
+    assert_eq!(
+      Ok(Expr::Dex(vec!((Expr::Int(25), Expr::Int(5)), (Expr::Int(151), Expr::Int(1))))),
+      meowth("bike caught = dex { 25: 4 }; set(caught, 151, 1); set(caught, 25, caught[25] + 1); caught")
+    );
+    assert_eq!(Ok(Expr::Int(4)), meowth("bike caught = dex { 25: 4 }; remove(caught, 25)"));
+    assert_eq!(Ok(Expr::Int(0)), meowth("bike caught = dex { 25: 4 }; remove(caught, 25); len(caught)"));
+    assert_eq!(Ok(Expr::Int(4)), meowth("bike caught = dex { 25: 4 }; remove(caught, 25); give(caught); caught[25]"));
+
+    assert_eq!(
+      Err(RuntimeError::KeyNotFound(Expr::Int(1))),
+      meowth("bike caught = dex {}; remove(caught, 1)")
+    );
+    assert_eq!(
+      Err(RuntimeError::InvalidConstAssignment(
+        Expr::Dex(vec!((Expr::Int(1), Expr::Int(1)))),
+        "caught".to_string()
+      )),
+      meowth("pokeball caught = dex {}; set(caught, 1, 1)")
+    );
+  }
+
+  #[test]
+  pub fn test_dex_display() {
+    assert_eq!("dex {}", format!("{}", meowth("dex {}").unwrap()));
+    assert_eq!(
+      "dex { 25: \"pikachu\", \"mew\": [151] }",
+      format!("{}", meowth("dex { 25: \"pikachu\", \"mew\": [151] }").unwrap())
+    );
+  }
+
+  fn trainer(name: &str, badges: isize) -> Expr {
+    Expr::Card("Trainer".to_string(), vec!(
+      ("name".to_string(), Expr::Str(name.to_string())),
+      ("badges".to_string(), Expr::Int(badges)),
+    ))
+  }
+
+  #[test]
+  pub fn test_cards() {
+    let _ = env_logger::init();
+
+    assert_eq!(
+      Ok(trainer("ash", 8)),
+      meowth("card Trainer { name, badges }; Trainer { name: \"ash\", badges: 4 + 4 }")
+    );
+
+    // fields are stored in declaration order
+    assert_eq!(
+      Ok(trainer("misty", 2)),
+      meowth("card Trainer { name, badges }; Trainer { badges: 2, name: \"misty\" }")
+    );
+
+    assert_eq!(
+      Ok(Expr::Int(8)),
+      meowth("card Trainer { name, badges }; pokeball ash = Trainer { name: \"ash\", badges: 8 }; ash.badges")
+    );
+
+    assert_eq!(
+      Ok(Expr::Int(25)),
+      meowth("
+        card Trainer { name, lead };
+        card Mon { species, level };
+        pokeball ash = Trainer { name: \"ash\", lead: Mon { species: 25, level: 5 } };
+        ash.lead.species
+      ")
+    );
+
+    assert_eq!(
+      Ok(Expr::Int(9)),
+      meowth("
+        card Trainer { name, badges };
+        attack earn(t) { Trainer { badges: t.badges + 1, ..t } };
+        earn(Trainer { name: \"ash\", badges: 8 }).badges
+      ")
+    );
+
+    assert_eq!(
+      Ok(trainer("ash", 8)),
+      meowth("
+        card Trainer { name, badges };
+        pokeball ash = Trainer { name: \"ash\", badges: 0 };
+        pokeball champ = Trainer { badges: 8, ..ash };
+        ash.badges draws 0 ? champ : ash
+      ")
+    );
+
+    assert_eq!(
+      Ok(Expr::Bool(true)),
+      meowth("card T { a, b }; T { a: 1, b: [2] } draws T { b: [2], a: 1 }")
+    );
+    assert_eq!(
+      Ok(Expr::Bool(false)),
+      meowth("card T { a, b }; T { a: 1, b: 2 } draws T { a: 1, b: 3 }")
+    );
+    assert_eq!(
+      Ok(Expr::Bool(false)),
+      meowth("card T { a }; card U { a }; T { a: 1 } draws U { a: 1 }")
+    );
+  }
+
+  #[test]
+  pub fn test_card_errors() {
+    let _ = env_logger::init();
+
+    assert_eq!(
+      Err(RuntimeError::FieldNotFound("Trainer".to_string(), "age".to_string())),
+      meowth("card Trainer { name }; Trainer { name: 1 }.age")
+    );
+    assert_eq!(
+      Err(RuntimeError::FieldNotFound("Trainer".to_string(), "age".to_string())),
+      meowth("card Trainer { name }; Trainer { name: 1, age: 10 }")
+    );
+    assert_eq!(
+      Err(RuntimeError::MissingField("Trainer".to_string(), "badges".to_string())),
+      meowth("card Trainer { name, badges }; Trainer { name: 1 }")
+    );
+    assert_eq!(
+      Err(RuntimeError::CardNotFound("Trainer".to_string())),
+      meowth("Trainer { name: 1 }")
+    );
+    assert_eq!(
+      Err(RuntimeError::InvalidTypeConversion("card".to_string(), Expr::Int(1))),
+      meowth("pokeball x = 1; x.name")
+    );
+    assert_eq!(
+      Err(RuntimeError::InvalidTypeConversion(
+        "Trainer".to_string(),
+        Expr::Card("Mon".to_string(), vec!(("name".to_string(), Expr::Int(1))))
+      )),
+      meowth("card Trainer { name }; card Mon { name }; Trainer { ..Mon { name: 1 } }")
+    );
+  }
+
+  #[test]
+  pub fn test_card_display() {
+    assert_eq!(
+      "Trainer { name: \"ash\", party: [25] }",
+      format!("{}", meowth("card Trainer { name, party }; Trainer { name: \"ash\", party: [25] }").unwrap())
+    );
+  }
+
+  #[test]
+  pub fn test_types() {
+    let _ = env_logger::init();
+
+    assert_eq!(
+      Ok(Expr::Variant("Element".to_string(), "Fire".to_string(), vec!())),
+      meowth("type Element = Fire | Water | Grass(level); Fire")
+    );
+    assert_eq!(
+      Ok(Expr::Variant("Element".to_string(), "Grass".to_string(), vec!(Expr::Int(5)))),
+      meowth("type Element = Fire | Water | Grass(level); Grass(2 + 3)")
+    );
+    assert_eq!(
+      Ok(Expr::Bool(true)),
+      meowth("type Element = Fire | Water | Grass(level); (Grass(5) draws Grass(5)) && (Fire != Water)")
+    );
+    assert_eq!(
+      Ok(Expr::Bool(false)),
+      meowth("type Element = Fire | Water | Grass(level); Grass(5) draws Grass(6)")
+    );
+    assert_eq!(
+      Err(RuntimeError::WrongNumberOfArgs("Grass".to_string(), 1, 2)),
+      meowth("type Element = Fire | Water | Grass(level); Grass(1, 2)")
+    );
+    assert_eq!(
+      "Grass(5, \"leaf\")",
+      format!("{}", meowth("type Element = | Fire | Grass(level, move); Grass(5, \"leaf\")").unwrap())
+    );
+  }
+
+  #[test]
+  pub fn test_evolve() {
+    let _ = env_logger::init();
+
+    let element = "
+      type Element = Fire | Water | Grass(level);
+      attack power(e) {
+        evolve (e) {
+          Fire => 10,
+          Water => 20,
+          Grass(n) => n * 2,
+        }
+      };
+    ";
+
+    assert_eq!(Ok(Expr::Int(10)), meowth(&format!("{} power(Fire)", element)));
+    assert_eq!(Ok(Expr::Int(20)), meowth(&format!("{} power(Water)", element)));
+    assert_eq!(Ok(Expr::Int(14)), meowth(&format!("{} power(Grass(7))", element)));
+
+    assert_eq!(
+      Ok(Expr::Int(3)),
+      meowth("
+        type Element = Fire | Water | Grass(level);
+        evolve (Grass(1)) {
+          Grass(0) => 1,
+          Grass(1) => 3,
+          _ => 2
+        }
+      ")
+    );
+
+    assert_eq!(Ok(Expr::Str("two".to_string())), meowth("evolve (1 + 1) { 1 => \"one\", 2 => \"two\", _ => \"many\" }"));
+    assert_eq!(Ok(Expr::Str("many".to_string())), meowth("evolve (7) { 1 => \"one\", 2 => \"two\", _ => \"many\" }"));
+    assert_eq!(Ok(Expr::Int(-1)), meowth("evolve (-1) { -1 => -1, n => n }"));
+    assert_eq!(Ok(Expr::Int(8)), meowth("evolve (7) { 1 => 0, n => n + 1 }"));
+    assert_eq!(Ok(Expr::Int(0)), meowth("evolve (\"mew\") { \"mew\" => 0, _ => 1 }"));
+    assert_eq!(Ok(Expr::Int(1)), meowth("evolve (1 beats 2) { win => 0, lose => 1 }"));
+
+    assert_eq!(
+      Ok(Expr::Int(12)),
+      meowth("
+        type Evolution = Basic(name) | Stage(from, level);
+        evolve (Stage(Basic(\"charmander\"), 16)) {
+          Basic(_) => 0,
+          Stage(Basic(name), level) => len(name) + level - 14,
+          Stage(_, _) => 1
+        }
+      ")
+    );
+  }
+
+  #[test]
+  pub fn test_evolve_arm_scope() {
+    let element = "type Element = Fire | Grass(level);";
+
+    // what a pattern binds stays in its arm, leaving bindings outside alone
+    assert_eq!(
+      Ok(Expr::Int(2)),
+      meowth(&format!("{} bike n = 1; evolve (Grass(5)) {{ Grass(n) => n, Fire => 0 }}; n = n + 1; n", element))
+    );
+    assert_eq!(
+      Err(RuntimeError::ResolverError(ResolverError::UndefinedVariable("lvl".to_string()))),
+      meowth(&format!("{} evolve (Grass(5)) {{ Grass(lvl) => lvl, Fire => 0 }}; lvl", element))
+    );
+
+    // flee from inside an arm still leaves the attack
+    assert_eq!(
+      Ok(Expr::Int(6)),
+      meowth(&format!("{} attack f(e) {{ evolve (e) {{ Grass(n) => flee n + 1, Fire => 0 }}; 99 }}; f(Grass(5))", element))
+    );
+  }
+
+  #[test]
+  pub fn test_evolve_non_exhaustive() {
+    let _ = env_logger::init();
+
+    // missing arms are the checker's to report, so a value some arm matches runs
+    assert_eq!(Ok(Expr::Int(1)), meowth("type Element = Fire | Water | Grass(level); evolve (Fire) { Fire => 1 }"));
+    assert_eq!(Ok(Expr::Int(2)), meowth("evolve (2) { 1 => 1, 2 => 2 }"));
+    assert_eq!(Ok(Expr::Int(3)), meowth("evolve (\"pika\") { \"mew\" => 1, \"pika\" => 3 }"));
+
+    // and only one no arm matches is an error
+    assert_eq!(
+      Err(RuntimeError::NonExhaustiveMatch(vec!("Grass(2)".to_string()))),
+      meowth("type Element = Fire | Grass(level); evolve (Grass(2)) { Fire => 1, Grass(1) => 2 }")
+    );
+    assert_eq!(
+      Err(RuntimeError::NonExhaustiveMatch(vec!("3".to_string()))),
+      meowth("evolve (3) { 1 => 1, 2 => 2 }")
+    );
+  }
+
+  #[test]
+  pub fn test_flee() {
+    let _ = env_logger::init();
+
+    let clamp = "
+      attack clamp(n) {
+        battle (n < 0) { flee 0 } run { 0 };
+        battle (n beats 100) { flee 100 } run { 0 };
+        n
+      };
+    ";
+
+    assert_eq!(Ok(Expr::Int(0)), meowth(&format!("{} clamp(-5)", clamp)));
+    assert_eq!(Ok(Expr::Int(100)), meowth(&format!("{} clamp(500)", clamp)));
+    assert_eq!(Ok(Expr::Int(42)), meowth(&format!("{} clamp(42)", clamp)));
+    assert_eq!(Ok(Expr::Int(142)), meowth(&format!("{} clamp(42) + clamp(500)", clamp)));
+
+    // flee only leaves the innermost attack
+    assert_eq!(
+      Ok(Expr::Int(11)),
+      meowth("
+        attack inner() { flee 1; 2 };
+        attack outer() { inner() + 10 };
+        outer()
+      ")
+    );
+
+    assert_eq!(
+      Ok(Expr::Int(6)),
+      meowth("
+        attack find(p, x) {
+          bike i = 0;
+          defend (i < len(p)) {
+            battle (p[i] draws x) { flee i } run { 0 };
+            i = i + 1
+          };
+          -1
+        };
+        find([1, 2, 3], 3) + find([1, 2, 3], 2) + find([1, 2, 3], 5) + 4
+      ")
+    );
+
+    assert_eq!(Ok(Expr::Int(5)), meowth("attack f() { flee 2 + 3; 0 }; f()"));
+  }
+
+  #[test]
+  pub fn test_flee_pops_frames() {
+    let _ = env_logger::init();
+
+    let mut interpreter = Interpreter::new();
+
+    assert_eq!(
+      Ok(Expr::Undefined),
+      interpreter.eval("attack deep(n) { bike x = n; battle (n draws 0) { flee x } run { deep(n - 1) + 1 } };")
+    );
+    assert_eq!(Ok(Expr::Int(3)), interpreter.eval("deep(3)"));
+    assert_eq!(1, interpreter.state.mem.len());
+
+    // frames are also popped when an error escapes an attack
+    assert_eq!(
+      Err(RuntimeError::VariableNotFound("missingno".to_string())),
+      interpreter.eval("attack broken() { bike y = 1; missingno }; broken(); pokeball missingno = 1; missingno")
+    );
+    assert_eq!(1, interpreter.state.mem.len());
+    assert_eq!(Err(RuntimeError::ResolverError(ResolverError::UndefinedVariable("y".to_string()))), interpreter.eval("y"));
+  }
+
+  #[test]
+  pub fn test_flee_outside_attack() {
+    let _ = env_logger::init();
+
+    let err = Err(RuntimeError::ParserError(
+      ParserError::InvalidToken(Token::Flee, "parsing flee outside of an attack".to_string())
+    ));
+
+    assert_eq!(err, meowth("flee 1"));
+    assert_eq!(err, meowth("attack f() { 1 }; flee f()"));
+    assert_eq!(err, meowth("bike i = 0; defend (i < 1) { flee i }; 0"));
+  }
+
+  #[test]
+  pub fn test_defend_runs_condition_first() {
+    let _ = env_logger::init();
+
+    assert_eq!(Ok(Expr::Int(0)), meowth("bike i = 0; defend (lose) { i = i + 1 }; i"));
+    assert_eq!(Ok(Expr::Int(3)), meowth("bike i = 0; defend (i < 3) { 0; i = i + 1 }; i"));
+  }
+
+  #[test]
+  pub fn test_battle_without_run() {
+    let _ = env_logger::init();
+
+    assert_eq!(Ok(Expr::Int(1)), meowth("battle (win) { 1 }"));
+    assert_eq!(Ok(Expr::Undefined), meowth("battle (lose) { 1 }"));
+    assert_eq!(Ok(Expr::Undefined), meowth("battle (lose) { 1 } rebattle (lose) { 2 }"));
+    assert_eq!(Ok(Expr::Int(5)), meowth("bike x = 4; battle (x draws 4) { x = 5 }; x"));
+  }
+
+  #[test]
+  pub fn test_defend_value() {
+    let _ = env_logger::init();
+
+    assert_eq!(Ok(Expr::Int(3)), meowth("bike i = 0; defend (i < 3) { i = i + 1 }"));
+    assert_eq!(Ok(Expr::Undefined), meowth("defend (lose) { 1 }"));
+    assert_eq!(Ok(Expr::Int(9)), meowth("defend (lose) { 1 } run { 9 }"));
+    assert_eq!(Ok(Expr::Int(2)), meowth("bike i = 0; defend (i < 2) { i = i + 1 } run { 9 }"));
+    assert_eq!(
+      Ok(Expr::Int(12)),
+      meowth("bike i = 0; pokeball n = defend (i < 4) { i = i + 1; i * 3 }; n")
+    );
+
+    // break keeps the last finished pass; the run clause is only for loops that never start
+    assert_eq!(
+      Ok(Expr::Int(20)),
+      meowth("bike i = 0; defend (win) { battle (i draws 2) { break }; i = i + 1; i * 10 } run { 9 }")
+    );
+    assert_eq!(Ok(Expr::Undefined), meowth("defend (win) { break } run { 9 }"));
+  }
+
+  #[test]
+  pub fn test_break_continue() {
+    let _ = env_logger::init();
+
+    assert_eq!(
+      Ok(Expr::Int(4)),
+      meowth("
+        bike i = 0;
+        defend (win) {
+          battle (i draws 4) { break } run { 0 };
+          i = i + 1
+        };
+        i
+      ")
+    );
+
+    // sum of odd numbers below 10
+    assert_eq!(
+      Ok(Expr::Int(25)),
+      meowth("
+        bike i = 0;
+        bike total = 0;
+        defend (i < 10) {
+          i = i + 1;
+          battle (i % 2 draws 0) { continue } run { 0 };
+          battle (i beats 9) { break } run { 0 };
+          total = total + i
+        };
+        total
+      ")
+    );
+
+    // unlabelled control only affects the innermost loop
+    assert_eq!(
+      Ok(Expr::Int(6)),
+      meowth("
+        bike i = 0;
+        bike count = 0;
+        defend (i < 3) {
+          bike j = 0;
+          defend (win) {
+            battle (j draws 2) { break } run { 0 };
+            j = j + 1;
+            count = count + 1
+          };
+          i = i + 1
+        };
+        count
+      ")
+    );
+  }
+
+  #[test]
+  pub fn test_labelled_break_continue() {
+    let _ = env_logger::init();
+
+    assert_eq!(
+      Ok(Expr::Str("2,3".to_string())),
+      meowth("
+        bike found = \"none\";
+        bike i = 0;
+        defend outer (i < 5) {
+          bike j = 0;
+          defend (j < 5) {
+            battle (i * j draws 6) {
+              found = \"\" + i + \",\" + j;
+              break outer
+            } run { 0 };
+            j = j + 1
+          };
+          i = i + 1
+        };
+        found
+      ")
+    );
+
+    assert_eq!(
+      Ok(Expr::Int(3)),
+      meowth("
+        bike i = 0;
+        bike count = 0;
+        defend rows (i < 3) {
+          i = i + 1;
+          defend (win) {
+            count = count + 1;
+            continue rows
+          };
+          0
+        };
+        count
+      ")
+    );
+  }
+
+  #[test]
+  pub fn test_break_pops_frames() {
+    let _ = env_logger::init();
+
+    let mut interpreter = Interpreter::new();
+
+    assert_eq!(
+      Ok(Expr::Int(3)),
+      interpreter.eval("
+        attack check(n) { bike x = n; x draws 3 };
+        attack scan() {
+          bike i = 0;
+          defend (win) {
+            battle (check(i)) { break } run { 0 };
+            i = i + 1
+          };
+          i
+        };
+        scan()
+      ")
+    );
+    assert_eq!(1, interpreter.state.mem.len());
+  }
+
+  #[test]
+  pub fn test_loop_control_outside_loop() {
+    let _ = env_logger::init();
+
+    assert_eq!(
+      Err(RuntimeError::ParserError(ParserError::InvalidToken(
+        Token::Break,
+        "parsing loop control outside of a loop".to_string()
+      ))),
+      meowth("break")
+    );
+    assert_eq!(
+      Err(RuntimeError::ParserError(ParserError::InvalidToken(
+        Token::Continue,
+        "parsing loop control outside of a loop".to_string()
+      ))),
+      meowth("defend (win) { attack f() { continue }; f() }; 0")
+    );
+    assert_eq!(
+      Err(RuntimeError::ParserError(ParserError::InvalidToken(
+        Token::Break,
+        "parsing loop control with unknown label Some(\"outer\")".to_string()
+      ))),
+      meowth("defend inner (win) { break outer }; 0")
+    );
+  }
+
+  #[test]
+  pub fn test_for_range() {
+    let _ = env_logger::init();
+
+    assert_eq!(Ok(Expr::Int(45)), meowth("bike total = 0; for i in 0..10 { total = total + i }; total"));
+    assert_eq!(Ok(Expr::Int(0)), meowth("bike total = 0; for i in 5..5 { total = total + 1 }; total"));
+    assert_eq!(Ok(Expr::Int(20)), meowth("bike total = 0; for i in 0..10 by 2 { total = total + i }; total"));
+    assert_eq!(Ok(Expr::Int(9)), meowth("bike total = 0; for i in 0..7 by 3 { total = total + i }; total"));
+    assert_eq!(
+      Ok(Expr::Str("54321".to_string())),
+      meowth("bike s = \"\"; for i in 5..0 { s = s + i }; s")
+    );
+    assert_eq!(
+      Ok(Expr::Str("1074".to_string())),
+      meowth("bike s = \"\"; for i in 10..2 by 3 { s = s + i }; s")
+    );
+    assert_eq!(
+      Ok(Expr::Int(6)),
+      meowth("pokeball n = 3; bike total = 0; for i in n - 3..n + 1 { total = total + i }; total")
+    );
+
+    // the loop variable is fresh on each pass and gone afterwards
+    assert_eq!(
+      Err(RuntimeError::ResolverError(ResolverError::UndefinedVariable("i".to_string()))),
+      meowth("for i in 0..3 { pokeball j = i * 2; j }; i")
+    );
+    assert_eq!(
+      Err(RuntimeError::ResolverError(ResolverError::UndefinedVariable("j".to_string()))),
+      meowth("for i in 0..3 { pokeball j = i * 2; j }; j")
+    );
+
+    assert_eq!(Err(RuntimeError::ZeroStep), meowth("for i in 0..3 by 0 { 0 }"));
+  }
+
+  #[test]
+  pub fn test_for_collections() {
+    let _ = env_logger::init();
+
+    assert_eq!(
+      Ok(Expr::Int(176)),
+      meowth("bike total = 0; for mon in [25, 151] { total = total + mon }; total")
+    );
+    assert_eq!(
+      Ok(Expr::Int(5)),
+      meowth("bike caught = 0; for species in keys(dex { 25: 4, 151: 1 }) { caught = caught + 1 }; caught + 3")
+    );
+    assert_eq!(
+      Ok(Expr::Int(5)),
+      meowth("pokeball d = dex { 25: 4, 151: 1 }; bike caught = 0; for species in d { caught = caught + d[species] }; caught")
+    );
+    assert_eq!(
+      Ok(Expr::Str("u-h-c".to_string())),
+      meowth("bike s = \"\"; for c in \"chu\" { battle (s draws \"\") { s = c } run { s = c + \"-\" + s } }; s")
+    );
+    assert_eq!(Err(RuntimeError::NotIterable(Expr::Int(3))), meowth("for i in 3 { 0 }"));
+  }
+
+  #[test]
+  pub fn test_for_break_continue() {
+    let _ = env_logger::init();
+
+    assert_eq!(
+      Ok(Expr::Int(25)),
+      meowth("
+        bike total = 0;
+        for i in 0..100 {
+          battle (i % 2 draws 0) { continue } run { 0 };
+          battle (i beats 9) { break } run { 0 };
+          total = total + i
+        };
+        total
+      ")
+    );
+
+    assert_eq!(
+      Ok(Expr::Int(8)),
+      meowth("
+        bike found = 0;
+        for outer i in 1..10 {
+          for j in 1..10 {
+            battle (i * j draws 12) { found = i + j; break outer } run { 0 }
+          }
+        };
+        found
+      ")
+    );
+
+    let mut interpreter = Interpreter::new();
+
+    assert_eq!(
+      Ok(Expr::Int(3)),
+      interpreter.eval("attack first_over(p, n) { for x in p { battle (x beats n) { flee x } run { 0 } }; -1 }; first_over([1, 3, 5], 2)")
+    );
+    assert_eq!(Ok(Expr::Undefined), interpreter.eval("for i in 0..3 { battle (i draws 1) { break } run { 0 } }"));
+    assert_eq!(1, interpreter.state.mem.len());
+  }
+
+  #[test]
+  pub fn test_escape_rope() {
+    let _ = env_logger::init();
+
+    assert_eq!(Ok(Expr::Int(1)), meowth("escape { 1 } rope (e) { 2 }"));
+    assert_eq!(Ok(Expr::Int(151)), meowth("escape { faint 151; 1 } rope (e) { e }"));
+    assert_eq!(
+      Ok(Expr::Str("missingno".to_string())),
+      meowth("attack check(n) { battle (n < 0) { faint \"missingno\" }; n }; escape { check(-1) } rope (e) { e }")
+    );
+    assert_eq!(
+      Ok(Expr::Int(6)),
+      meowth("escape { escape { faint 3 } rope (e) { faint e * 2 } } rope (e) { e }")
+    );
+
+    // the bound payload does not outlive its rope
+    assert_eq!(
+      Err(RuntimeError::ResolverError(ResolverError::UndefinedVariable("e".to_string()))),
+      meowth("escape { faint 1 } rope (e) { e }; e")
+    );
+
+    assert_eq!(Err(RuntimeError::Raised(Expr::Int(7))), meowth("faint 7"));
+  }
+
+  #[test]
+  pub fn test_escape_builtin_errors() {
+    let _ = env_logger::init();
+
+    assert_eq!(Err(RuntimeError::DivisionByZero), meowth("1 / 0"));
+    assert_eq!(Err(RuntimeError::DivisionByZero), meowth("1 % 0"));
+    assert_eq!(
+      Ok(Expr::Card("Error".to_string(), vec!(
+        ("kind".to_string(), Expr::Str("DivisionByZero".to_string())),
+        ("message".to_string(), Expr::Str("Cannot divide by zero".to_string())),
+      ))),
+      meowth("escape { 1 / 0 } rope (e) { e }")
+    );
+    assert_eq!(
+      Ok(Expr::Str("No value in empty bike x".to_string())),
+      meowth("bike x = 1; give(x); escape { give(x) } rope (e) { e.message }")
+    );
+    assert_eq!(
+      Ok(Expr::Int(0)),
+      meowth("attack lost() { missingno }; bike r = escape { lost() } rope (e) { 0 }; pokeball missingno = 1; r")
+    );
+  }
+
+  #[test]
+  pub fn test_escape_error_kinds() {
+    let _ = env_logger::init();
+
+    // a rope can tell built-in errors apart by their kind
+    let kinds = "
+      attack kind(f) {
+        escape { f() } rope (e) {
+          evolve (e.kind) { \"DivisionByZero\" => 1, \"EmptyBike\" => 2, \"VariableNotFound\" => 3, _ => 4 }
+        }
+      };
+    ";
+    let mut interpreter = Interpreter::new();
+    interpreter.eval(kinds).unwrap();
+
+    assert_eq!(Ok(Expr::Int(1)), interpreter.eval("kind(attack () { 1 / 0 })"));
+    assert_eq!(Ok(Expr::Int(2)), interpreter.eval("bike b = 1; give(b); kind(attack () { give(b) })"));
+    assert_eq!(Ok(Expr::Int(3)), interpreter.eval("attack lost() { nowhere }; bike k = kind(lost); pokeball nowhere = 0; k"));
+    assert_eq!(Ok(Expr::Int(4)), interpreter.eval("kind(attack () { [1][5] })"));
+
+    // and from a faint that happens to carry the same message
+    assert_eq!(
+      Ok(Expr::Str("faint".to_string())),
+      meowth("
+        attack which(f) {
+          escape { f() } rope (e) { evolve (e) { \"Cannot divide by zero\" => \"faint\", _ => e.kind } }
+        };
+        which(attack () { faint \"Cannot divide by zero\" })
+      ")
+    );
+    assert_eq!(
+      Ok(Expr::Str("DivisionByZero".to_string())),
+      meowth("
+        attack which(f) {
+          escape { f() } rope (e) { evolve (e) { \"Cannot divide by zero\" => \"faint\", _ => e.kind } }
+        };
+        which(attack () { 1 / 0 })
+      ")
+    );
+  }
+
+  #[test]
+  pub fn test_escape_finally() {
+    let _ = env_logger::init();
+
+    assert_eq!(Ok(Expr::Int(1)), meowth("bike x = 0; escape { 1 } finally { x = 5 }"));
+    assert_eq!(Ok(Expr::Int(5)), meowth("bike x = 0; escape { 1 } finally { x = 5 }; x"));
+    assert_eq!(Ok(Expr::Int(11)), meowth("bike x = 0; escape { faint 1 } rope (e) { x = 10 } finally { x = x + 1 }; x"));
+
+    // finally runs before an uncaught error keeps going
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+      Err(RuntimeError::Raised(Expr::Int(2))),
+      interpreter.eval("bike x = 0; escape { faint 2 } finally { x = 3 }")
+    );
+    assert_eq!(Ok(Expr::Int(3)), interpreter.eval("x"));
+
+    // and before control flow leaves through it
+    assert_eq!(
+      Ok(Expr::Int(12)),
+      meowth("bike x = 0; attack f() { escape { flee 2 } finally { x = 10 } }; f() + x")
+    );
+    assert_eq!(
+      Ok(Expr::Int(3)),
+      meowth("bike x = 0; defend (win) { escape { break } finally { x = 3 } }; x")
+    );
+  }
+
+  #[test]
+  pub fn test_escape_pops_frames() {
+    let _ = env_logger::init();
+
+    let mut interpreter = Interpreter::new();
+
+    assert_eq!(
+      Ok(Expr::Int(10)),
+      interpreter.eval("
+        attack deep(n) { bike x = n; battle (n draws 0) { faint 10 } run { deep(n - 1) } };
+        escape { deep(3) } rope (e) { e }
+      ")
+    );
+    assert_eq!(1, interpreter.state.mem.len());
+
+    assert_eq!(
+      Err(RuntimeError::Raised(Expr::Int(4))),
+      interpreter.eval("escape { deep(2) } rope (e) { bike y = e; faint y - 6 }")
+    );
+    assert_eq!(1, interpreter.state.mem.len());
+    assert_eq!(Err(RuntimeError::ResolverError(ResolverError::UndefinedVariable("x".to_string()))), interpreter.eval("x"));
+  }
+
+  fn trainer_with_modules() -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.add_search_path("tests/modules");
+    interpreter
+  }
+
+  #[test]
+  pub fn test_trade() {
+    let _ = env_logger::init();
+
+    let mut interpreter = trainer_with_modules();
+
+    assert_eq!(Ok(Expr::Int(12)), interpreter.eval("trade \"kanto.meow\"; kanto.quad(3)"));
+    assert_eq!(Ok(Expr::Int(1)), interpreter.eval("trade \"kanto.meow\" as k; k.starter"));
+    assert_eq!(
+      Ok(Expr::Str("red".to_string())),
+      interpreter.eval("trade \"kanto.meow\"; kanto.rookie(\"red\").name")
+    );
+
+    // module attacks see their own module, not the importer's bindings
+    assert_eq!(
+      Ok(Expr::Int(160)),
+      interpreter.eval("trade \"johto.meow\"; pokeball starter = 0; johto.evolved(2)")
+    );
+
+    // bikes stay private to their module
+    assert_eq!(
+      Err(RuntimeError::ExportNotFound("kanto.meow".to_string(), "caught".to_string())),
+      interpreter.eval("trade \"kanto.meow\"; kanto.caught")
+    );
+    assert_eq!(1, interpreter.state.mem.len());
+  }
+
+  #[test]
+  pub fn test_trade_evaluates_once() {
+    let _ = env_logger::init();
+
+    let dir = env::temp_dir().join(format!("meowth-trade-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("once.meow"), "pokeball n = 1;").unwrap();
+
+    let mut interpreter = Interpreter::new();
+    interpreter.set_search_paths(vec!(dir.clone()));
+
+    assert_eq!(Ok(Expr::Int(1)), interpreter.eval("trade \"once.meow\"; once.n"));
+
+    fs::write(dir.join("once.meow"), "pokeball n = 2;").unwrap();
+    assert_eq!(Ok(Expr::Int(1)), interpreter.eval("trade \"once.meow\"; once.n"));
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  pub fn test_trade_errors() {
+    let _ = env_logger::init();
+
+    let mut interpreter = trainer_with_modules();
+
+    assert_eq!(
+      Err(RuntimeError::ModuleNotFound("sinnoh.meow".to_string())),
+      interpreter.eval("trade \"sinnoh.meow\"; 1")
+    );
+
+    match interpreter.eval("trade \"cycle_a.meow\"; 1") {
+      Err(RuntimeError::ImportCycle(files)) => {
+        assert_eq!(3, files.len());
+        assert!(files[0].ends_with("cycle_a.meow"));
+        assert!(files[1].ends_with("cycle_b.meow"));
+        assert!(files[2].ends_with("cycle_a.meow"));
+      },
+      other => panic!("expected an import cycle, got {:?}", other),
+    }
+
+    // a failed import leaves the importer's state alone
+    assert_eq!(Ok(Expr::Int(1)), interpreter.eval("pokeball x = 1; x"));
+    assert_eq!(1, interpreter.state.mem.len());
+  }
+
+  #[test]
+  pub fn test_shebang() {
+    let _ = env_logger::init();
+
+    assert_eq!(Ok(Expr::Int(3)), meowth("#!/usr/bin/env meowth\n1 + 2"));
+
+    // only a first line is skipped
+    assert!(meowth("1 + 2;\n#!/usr/bin/env meowth").is_err());
+  }
+
+  #[test]
+  pub fn test_annotations() {
+    let _ = env_logger::init();
+
+    assert_eq!(Ok(Expr::Int(152)), meowth("pokeball mew: pokemon = 151; attack gnaw(n: pokemon) -> pokemon { n + 1 }; gnaw(mew)"));
+    assert_eq!(Ok(Expr::Int(2)), meowth("type Element = Fire | Water; attack f(xs: party[Element]) { len(xs) }; f([Fire, Water])"));
+
+    // without the checker annotations are checked as values are bound
+    let err = meowth("attack gnaw(n: pokemon) { n + 1 }; gnaw(\"mew\")").unwrap_err();
+    assert_eq!("n is annotated pokemon but was given \"mew\"", err.to_string());
+    assert!(meowth("bike xs: party[pokemon] = [1, win]; xs").is_err());
+    assert!(meowth("pokeball f: attack(pokemon) -> pokemon = attack (a, b) { a }; 1").is_err());
+
+    // a result is checked as the attack returns it, and whatever a bike is given later
+    let err = meowth("attack gnaw(n: pokemon) -> pokemon { win }; gnaw(1)").unwrap_err();
+    assert_eq!("the result is annotated pokemon but was given win", err.to_string());
+    assert!(meowth("attack gnaw(n: pokemon) -> pokemon { flee win; n }; gnaw(1)").is_err());
+    assert_eq!(Ok(Expr::Int(1)), meowth("attack gnaw(n: pokemon) -> pokemon { flee n; win }; gnaw(1)"));
+
+    let err = meowth("bike b: pokemon = 1; b = win").unwrap_err();
+    assert_eq!("b is annotated pokemon but was given win", err.to_string());
+    assert_eq!(Ok(Expr::Int(2)), meowth("bike b: pokemon = 1; attack f() { b = 2 }; f(); b"));
+    assert!(meowth("bike b: pokemon = 1; attack f() { b = win }; f()").is_err());
+  }
 }
@@ -2,10 +2,26 @@ extern crate meowth;
 
 #[cfg(test)]
 mod test {
-  use meowth::parser::parser::{parse};
-  use meowth::expr::{Expr, BinOp};
+  use meowth::parser::parser;
+  use meowth::parser::parser_error::ParserError;
+  use meowth::expr::{Expr, BinOp, Builtin, Dec, Pattern};
+  use meowth::symbol::{Interner, Symbol};
+  use meowth::types::Type;
   extern crate env_logger;
 
+  thread_local! {
+    // names parsed on one thread compare equal to the ones sym makes there
+    static SYMBOLS: Interner = Interner::new();
+  }
+
+  fn parse(input: &str) -> Result<Expr, ParserError> {
+    SYMBOLS.with(|symbols| parser::parse(input, symbols))
+  }
+
+  fn sym(x: &str) -> Symbol {
+    SYMBOLS.with(|symbols| symbols.intern(x))
+  }
+
   #[test]
   fn test_mult_div() {
     assert_eq!(
@@ -121,4 +137,283 @@ mod test {
       parse("(4+7)-3").unwrap()
     );
   }
+
+  #[test]
+  fn test_card_field_access() {
+    assert_eq!(
+      Expr::Field(
+        Box::new(Expr::Field(Box::new(Expr::Var(sym("ash"))), "lead".to_string())),
+        "level".to_string(),
+      ),
+      parse("ash.lead.level").unwrap()
+    );
+
+    assert_eq!(
+      Expr::Field(
+        Box::new(Expr::Index(Box::new(Expr::Var(sym("party"))), Box::new(Expr::Int(0)))),
+        "level".to_string(),
+      ),
+      parse("party[0].level").unwrap()
+    );
+
+    assert_eq!(
+      Expr::CardLit(
+        "Trainer".to_string(),
+        vec!(("badges".to_string(), Expr::Int(8))),
+        Some(Box::new(Expr::Var(sym("ash")))),
+      ),
+      parse("Trainer { badges: 8, ..ash }").unwrap()
+    );
+  }
+
+  #[test]
+  fn test_evolve() {
+    assert_eq!(
+      Expr::Evolve(
+        Box::new(Expr::Var(sym("e"))),
+        vec!(
+          (Pattern::Ctor("Grass".to_string(), vec!(Pattern::Bind(sym("n")))), Expr::Var(sym("n"))),
+          (Pattern::Lit(Expr::Int(-1)), Expr::Int(0)),
+          (Pattern::Wildcard, Expr::Int(1)),
+        ),
+      ),
+      parse("evolve (e) { Grass(n) => n, -1 => 0, _ => 1 }").unwrap()
+    );
+  }
+
+  #[test]
+  fn test_for() {
+    assert_eq!(
+      Expr::For(
+        Some("outer".to_string()),
+        sym("i"),
+        Box::new(Expr::Range(Box::new(Expr::Int(10)), Box::new(Expr::Int(0)), Box::new(Expr::Int(2)))),
+        Box::new(Expr::Var(sym("i"))),
+        None,
+      ),
+      parse("for outer i in 10..0 by 2 { i }").unwrap()
+    );
+
+    assert_eq!(
+      Expr::For(
+        None,
+        sym("mon"),
+        Box::new(Expr::Var(sym("party"))),
+        Box::new(Expr::Var(sym("mon"))),
+        None,
+      ),
+      parse("for mon in party { mon }").unwrap()
+    );
+
+    // an uppercase name before the body is not a card literal
+    assert_eq!(
+      Expr::For(None, sym("mon"), Box::new(Expr::Var(sym("Team"))), Box::new(Expr::Var(sym("mon"))), None),
+      parse("for mon in Team { mon }").unwrap()
+    );
+    assert_eq!(
+      Expr::For(
+        None,
+        sym("i"),
+        Box::new(Expr::Range(Box::new(Expr::Int(0)), Box::new(Expr::Var(sym("N"))), Box::new(Expr::Var(sym("Step"))))),
+        Box::new(Expr::Var(sym("i"))),
+        None,
+      ),
+      parse("for i in 0..N by Step { i }").unwrap()
+    );
+    assert_eq!(
+      Expr::For(
+        None,
+        sym("i"),
+        Box::new(Expr::Range(Box::new(Expr::Int(0)), Box::new(Expr::Var(sym("N"))), Box::new(Expr::Int(1)))),
+        Box::new(Expr::Var(sym("i"))),
+        None,
+      ),
+      parse("for i in 0..N { i }").unwrap()
+    );
+
+    // but one in brackets is
+    assert_eq!(
+      Expr::For(
+        None,
+        sym("t"),
+        Box::new(Expr::PartyLit(vec!(Expr::CardLit("T".to_string(), vec!(("a".to_string(), Expr::Int(1))), None)))),
+        Box::new(Expr::Var(sym("t"))),
+        None,
+      ),
+      parse("for t in [T { a: 1 }] { t }").unwrap()
+    );
+  }
+
+  #[test]
+  fn test_builtin_names() {
+    // a builtin's name is only the builtin when called
+    assert_eq!(
+      Expr::Builtin(Builtin::Len, vec!(Expr::Var(sym("len")))),
+      parse("len(len)").unwrap()
+    );
+    assert_eq!(
+      Expr::Decl(Dec::DVar, Box::new(Expr::Var(sym("push"))), None, Box::new(Expr::Int(1)), Box::new(Expr::Var(sym("push")))),
+      parse("bike push = 1; push").unwrap()
+    );
+
+    // in, by and as are names outside for loops and trades
+    assert_eq!(
+      Expr::For(
+        None,
+        sym("in"),
+        Box::new(Expr::Range(Box::new(Expr::Var(sym("by"))), Box::new(Expr::Var(sym("as"))), Box::new(Expr::Var(sym("by"))))),
+        Box::new(Expr::Var(sym("in"))),
+        None,
+      ),
+      parse("for in in by..as by by { in }").unwrap()
+    );
+    assert!(parse("for x of xs { x }").is_err());
+  }
+
+  #[test]
+  fn test_battle_without_run() {
+    assert_eq!(
+      Expr::Ternary(
+        Box::new(Expr::Bool(true)),
+        Box::new(Expr::Int(1)),
+        Box::new(Expr::Undefined),
+      ),
+      parse("battle (win) { 1 }").unwrap()
+    );
+
+    assert_eq!(
+      Expr::Bop(
+        BinOp::Seq,
+        Box::new(Expr::Ternary(
+          Box::new(Expr::Bool(false)),
+          Box::new(Expr::Int(1)),
+          Box::new(Expr::Ternary(
+            Box::new(Expr::Bool(true)),
+            Box::new(Expr::Int(2)),
+            Box::new(Expr::Undefined),
+          )),
+        )),
+        Box::new(Expr::Int(3)),
+      ),
+      parse("battle (lose) { 1 } rebattle (win) { 2 }; 3").unwrap()
+    );
+
+    // without parens, the { after an uppercase name starts the branch
+    let draws = |x: &str, c: &str| Box::new(Expr::Bop(BinOp::Eq, Box::new(Expr::Var(sym(x))), Box::new(Expr::Var(sym(c)))));
+    assert_eq!(
+      Expr::Ternary(
+        draws("t", "Fire"),
+        Box::new(Expr::Int(1)),
+        Box::new(Expr::Ternary(draws("t", "Grass"), Box::new(Expr::Int(2)), Box::new(Expr::Int(3)))),
+      ),
+      parse("battle t draws Fire { 1 } rebattle t draws Grass { 2 } run { 3 }").unwrap()
+    );
+  }
+
+  #[test]
+  fn test_defend_run() {
+    let cond = Box::new(Expr::Var(sym("x")));
+    let body = Box::new(Expr::Int(1));
+
+    assert_eq!(
+      Expr::While(None, cond.clone(), cond.clone(), body.clone(), body.clone(), None, Box::new(Expr::Int(2))),
+      parse("defend (x) { 1 } run { 2 }").unwrap()
+    );
+
+    assert_eq!(
+      Expr::Bop(
+        BinOp::Seq,
+        Box::new(Expr::While(None, cond.clone(), cond, body.clone(), body, None, Box::new(Expr::Undefined))),
+        Box::new(Expr::Int(3)),
+      ),
+      parse("defend (x) { 1 }; 3").unwrap()
+    );
+  }
+
+  #[test]
+  fn test_escape() {
+    assert_eq!(
+      Expr::Escape(
+        Box::new(Expr::Faint(Box::new(Expr::Int(1)))),
+        Some((sym("e"), Box::new(Expr::Var(sym("e"))))),
+        Some(Box::new(Expr::Int(2))),
+      ),
+      parse("escape { faint 1 } rope (e) { e } finally { 2 }").unwrap()
+    );
+
+    assert_eq!(
+      Expr::Escape(Box::new(Expr::Int(1)), None, Some(Box::new(Expr::Int(2)))),
+      parse("escape { 1 } finally { 2 }").unwrap()
+    );
+
+    assert!(parse("escape { 1 }").is_err());
+  }
+
+  #[test]
+  fn test_trade() {
+    assert_eq!(
+      Expr::Decl(
+        Dec::DConst,
+        Box::new(Expr::Var(sym("kanto"))),
+        None,
+        Box::new(Expr::Import("lib/kanto.meow".to_string())),
+        Box::new(Expr::FnCall(
+          Box::new(Expr::Field(Box::new(Expr::Var(sym("kanto"))), "quad".to_string())),
+          vec!(Expr::Int(3)),
+        )),
+      ),
+      parse("trade \"lib/kanto.meow\"; kanto.quad(3)").unwrap()
+    );
+
+    assert_eq!(
+      Expr::Decl(
+        Dec::DConst,
+        Box::new(Expr::Var(sym("k"))),
+        None,
+        Box::new(Expr::Import("kanto.meow".to_string())),
+        Box::new(Expr::Undefined),
+      ),
+      parse("trade \"kanto.meow\" as k;").unwrap()
+    );
+  }
+
+  #[test]
+  fn test_annotations() {
+    assert_eq!(
+      Expr::Decl(
+        Dec::DConst,
+        Box::new(Expr::Var(sym("mew"))),
+        Some(Type::Int),
+        Box::new(Expr::Int(151)),
+        Box::new(Expr::Undefined),
+      ),
+      parse("pokeball mew: pokemon = 151;").unwrap()
+    );
+
+    let gnaw = Expr::Func(
+      Some(Box::new(Expr::Var(sym("gnaw")))),
+      Box::new(Expr::Var(sym("n"))),
+      vec!(Expr::Var(sym("n")), Expr::Var(sym("m"))),
+      vec!(Some(Type::Party(Box::new(Type::Str))), None),
+      Some(Box::new(Type::Attack(vec!(Type::Named("Element".to_string())), Box::new(Type::Unit)))),
+    );
+    assert_eq!(
+      Expr::Decl(Dec::DConst, Box::new(Expr::Var(sym("gnaw"))), None, Box::new(gnaw), Box::new(Expr::Undefined)),
+      parse("attack gnaw(n: party[move], m) -> attack(Element) -> () { n };").unwrap()
+    );
+
+    assert_eq!(
+      Expr::Decl(
+        Dec::DVar,
+        Box::new(Expr::Var(sym("d"))),
+        Some(Type::Dex(Box::new(Type::Str), Box::new(Type::Bool))),
+        Box::new(Expr::DexLit(vec!())),
+        Box::new(Expr::Undefined),
+      ),
+      parse("bike d: dex[move, battle] = dex {};").unwrap()
+    );
+
+    assert!(parse("pokeball x: 5 = 5;").is_err());
+    assert!(parse("attack f(n) -> { n }; 1").is_err());
+  }
 }
@@ -0,0 +1,215 @@
+extern crate meowth;
+extern crate proptest;
+
+#[cfg(test)]
+mod test {
+  use meowth::expr::{Expr, BinOp, UnOp, Builtin, Dec, Pattern};
+  use meowth::expr::Expr::*;
+  use meowth::parser::parser;
+  use meowth::parser::parser_error::ParserError;
+  use meowth::printer;
+  use meowth::symbol::{Interner, Symbol};
+  use meowth::types::Type;
+  use proptest::prelude::*;
+  use proptest::collection::vec;
+  use proptest::option;
+
+  thread_local! {
+    // the names every test on this thread makes, parses and prints
+    static SYMBOLS: Interner = Interner::new();
+  }
+
+  fn parse(input: &str) -> Result<Expr, ParserError> {
+    SYMBOLS.with(|symbols| parser::parse(input, symbols))
+  }
+
+  fn print(e: &Expr) -> String {
+    SYMBOLS.with(|symbols| printer::print(e, symbols))
+  }
+
+  fn sym(x: &str) -> Symbol {
+    SYMBOLS.with(|symbols| symbols.intern(x))
+  }
+
+  fn b(e: Expr) -> Box<Expr> {
+    Box::new(e)
+  }
+
+  fn name() -> impl Strategy<Value = String> {
+    prop_oneof!(Just("a"), Just("mew"), Just("pika"), Just("x_y")).prop_map(|x| x.to_string())
+  }
+
+  fn symbol() -> impl Strategy<Value = Symbol> {
+    name().prop_map(|x| sym(&x))
+  }
+
+  fn card_name() -> impl Strategy<Value = String> {
+    prop_oneof!(Just("Foo"), Just("Bar")).prop_map(|x| x.to_string())
+  }
+
+  fn binop() -> impl Strategy<Value = BinOp> {
+    prop_oneof!(
+      Just(BinOp::Plus), Just(BinOp::Minus), Just(BinOp::Times), Just(BinOp::Div),
+      Just(BinOp::Eq), Just(BinOp::Ne), Just(BinOp::Leq), Just(BinOp::Geq),
+      Just(BinOp::Lt), Just(BinOp::Gt), Just(BinOp::And), Just(BinOp::Or),
+      Just(BinOp::Mod), Just(BinOp::Seq),
+    )
+  }
+
+  fn builtin() -> impl Strategy<Value = Builtin> {
+    prop_oneof!(Just(Builtin::Len), Just(Builtin::Push), Just(Builtin::Keys), Just(Builtin::Substr))
+  }
+
+  fn leaf() -> impl Strategy<Value = Expr> {
+    prop_oneof!(
+      (0..1000isize).prop_map(Int),
+      any::<bool>().prop_map(Bool),
+      "[a-z \"\\\\\n\té]{0,5}".prop_map(Str),
+      symbol().prop_map(Var),
+      card_name().prop_map(|x| Var(sym(&x))),
+    )
+  }
+
+  // the types an annotation can spell out
+  fn ty() -> impl Strategy<Value = Type> {
+    let leaf = prop_oneof!(
+      Just(Type::Int), Just(Type::Bool), Just(Type::Str), Just(Type::Unit),
+      card_name().prop_map(Type::Named),
+    );
+
+    leaf.prop_recursive(2, 6, 3, |inner| {
+      prop_oneof!(
+        inner.clone().prop_map(|t| Type::Party(Box::new(t))),
+        (inner.clone(), inner.clone()).prop_map(|(k, t)| Type::Dex(Box::new(k), Box::new(t))),
+        (vec(inner.clone(), 0..3), inner).prop_map(|(ps, r)| Type::Attack(ps, Box::new(r))),
+      )
+    })
+  }
+
+  fn params() -> impl Strategy<Value = (Vec<Expr>, Vec<Option<Type>>)> {
+    vec((symbol(), option::of(ty())), 0..3).prop_map(|ps| ps.into_iter().map(|(x, t)| (Var(x), t)).unzip())
+  }
+
+  fn pattern() -> impl Strategy<Value = Pattern> {
+    let leaf = prop_oneof!(
+      Just(Pattern::Wildcard),
+      symbol().prop_map(Pattern::Bind),
+      (-50..50isize).prop_map(|n| Pattern::Lit(Int(n))),
+      any::<bool>().prop_map(|v| Pattern::Lit(Bool(v))),
+      "[a-z]{0,3}".prop_map(|s| Pattern::Lit(Str(s))),
+    );
+
+    leaf.prop_recursive(2, 6, 3, |inner| {
+      (card_name(), vec(inner, 0..3)).prop_map(|(c, ps)| Pattern::Ctor(c, ps))
+    })
+  }
+
+  // expressions the parser can produce, with loop control and flee only where it allows them
+  fn expr() -> impl Strategy<Value = Expr> {
+    leaf().prop_recursive(4, 48, 4, |e| {
+      prop_oneof!(
+        (binop(), e.clone(), e.clone()).prop_map(|(op, e1, e2)| Bop(op, b(e1), b(e2))),
+        (symbol(), e.clone()).prop_map(|(x, e2)| Bop(BinOp::Assign, b(Var(x)), b(e2))),
+        (any::<bool>(), e.clone()).prop_map(|(not, e1)| Uop(if not { UnOp::Not } else { UnOp::Neg }, b(e1))),
+        (e.clone(), e.clone(), option::of(e.clone())).prop_map(|(c, e2, e3)| {
+          Ternary(b(c), b(e2), b(e3.unwrap_or(Undefined)))
+        }),
+        (e.clone(), e.clone(), e.clone(), e.clone(), e.clone()).prop_map(|(c1, e1, c2, e2, e3)| {
+          Ternary(b(c1), b(e1), b(Ternary(b(c2), b(e2), b(e3))))
+        }),
+        (option::of(Just("outer".to_string())), e.clone(), e.clone(), any::<bool>(), option::of(e.clone()))
+          .prop_map(|(label, c, body, stop, run)| {
+            let control = if stop { Break(label.clone()) } else { Continue(label.clone()) };
+            let body = Bop(BinOp::Seq, b(body), b(control));
+            While(label, b(c.clone()), b(c), b(body.clone()), b(body), None, b(run.unwrap_or(Undefined)))
+          }),
+        (symbol(), e.clone(), e.clone(), option::of(e.clone()), e.clone()).prop_map(|(x, e1, e2, step, body)| {
+          For(None, x, b(Range(b(e1), b(e2), b(step.unwrap_or(Int(1))))), b(body), None)
+        }),
+        (symbol(), e.clone(), e.clone()).prop_map(|(x, iter, body)| For(None, x, b(iter), b(body), None)),
+        (any::<bool>(), symbol(), option::of(ty()), e.clone(), e.clone()).prop_map(|(constant, x, t, e2, e3)| {
+          Decl(if constant { Dec::DConst } else { Dec::DVar }, b(Var(x)), t, b(e2), b(e3))
+        }),
+        (symbol(), params(), option::of(ty()), e.clone(), e.clone(), e.clone()).prop_map(|(f, (ps, ts), r, body, out, e3)| {
+          let body = Bop(BinOp::Seq, b(body), b(Flee(b(out))));
+          let func = Func(Some(b(Var(f))), b(body), ps, ts, r.map(Box::new));
+          Decl(Dec::DConst, b(Var(f)), None, b(func), b(e3))
+        }),
+        (symbol(), e.clone()).prop_map(|(x, e3)| Decl(Dec::DConst, b(Var(x)), None, b(Import("kanto.meow".to_string())), b(e3))),
+        (params(), option::of(ty()), e.clone(), option::of(vec(e.clone(), 0..3))).prop_map(|((ps, ts), r, body, args)| {
+          let func = Func(None, b(body), ps, ts, r.map(Box::new));
+          match args {
+            Some(args) => FnCall(b(func), args),
+            None => func,
+          }
+        }),
+        (symbol(), vec(e.clone(), 0..3)).prop_map(|(f, args)| FnCall(b(Var(f)), args)),
+        (e.clone(), name(), vec(e.clone(), 0..3)).prop_map(|(m, f, args)| FnCall(b(Field(b(m), f)), args)),
+        (builtin(), vec(e.clone(), 0..3)).prop_map(|(f, args)| Expr::Builtin(f, args)),
+        e.clone().prop_map(|e1| Print(b(e1))),
+        symbol().prop_map(|x| PrintVarName(b(Var(x)))),
+        symbol().prop_map(|x| Give(b(Var(x)))),
+        vec(e.clone(), 0..3).prop_map(PartyLit),
+        (e.clone(), e.clone()).prop_map(|(e1, e2)| Index(b(e1), b(e2))),
+        (e.clone(), name()).prop_map(|(e1, x)| Field(b(e1), x)),
+        vec((e.clone(), e.clone()), 0..3).prop_map(DexLit),
+        (card_name(), vec(name(), 0..3), e.clone()).prop_map(|(c, xs, e3)| CardDecl(c, xs, b(e3))),
+        (card_name(), vec((name(), e.clone()), 0..3), option::of(e.clone())).prop_map(|(c, fields, base)| {
+          CardLit(c, fields, base.map(b))
+        }),
+        (card_name(), vec((card_name(), vec(name(), 0..3)), 1..3), e.clone()).prop_map(|(t, variants, e3)| {
+          TypeDecl(t, variants, b(e3))
+        }),
+        (e.clone(), vec((pattern(), e.clone()), 0..3)).prop_map(|(e1, arms)| Evolve(b(e1), arms)),
+        e.clone().prop_map(|e1| Faint(b(e1))),
+        (e.clone(), option::of((symbol(), e.clone())), option::of(e.clone()))
+          .prop_filter("escape needs a rope or finally", |t| t.1.is_some() || t.2.is_some())
+          .prop_map(|(body, rope, finally)| Escape(b(body), rope.map(|(x, e2)| (x, b(e2))), finally.map(b))),
+      )
+    })
+  }
+
+  proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2000))]
+
+    #[test]
+    fn test_print_round_trip(e in expr()) {
+      let source = print(&e);
+      prop_assert_eq!(Ok(e), parse(&source), "printed as {}", source);
+    }
+  }
+
+  #[test]
+  fn test_print_keeps_grouping() {
+    for input in &[
+      "(1 + 2) * 3",
+      "1 - (2 - 3)",
+      "(1 * 2) * 3",
+      "(pokedex(1)) + 2",
+      "-(1 + 2)",
+      "x = y = 3",
+      "{ pokeball x = 1; x }; 2",
+      "battle (a) { 1 } rebattle (b) { 2 } run { 3 }",
+      "for x in Y { x }",
+      "for i in 0..N by Step { i }",
+      "\"say \\\"hi\\\"\\n\"",
+    ] {
+      let e = parse(input).unwrap();
+      assert_eq!(e, parse(&print(&e)).unwrap(), "{}", print(&e));
+    }
+  }
+
+  #[test]
+  fn test_print_keywords() {
+    assert_eq!("pokeball mew = 151; bike b = 5; mew + b", print(&parse("pokeball mew = 151; bike b = 5; mew + b").unwrap()));
+    assert_eq!("attack gnaw(n) { n - 10 }; gnaw(1)", print(&parse("attack gnaw(n) {n-10}; gnaw(1)").unwrap()));
+    assert_eq!("a beats b && c draws d", print(&parse("a beats b && c draws d").unwrap()));
+    assert_eq!("battle (a) { 1 } run { 2 }", print(&parse("a ? 1 : 2").unwrap()));
+  }
+
+  #[test]
+  fn test_display_shows_code() {
+    let e = parse("attack (n) { n * 2 }").unwrap();
+    assert_eq!("attack (n) { n * 2 }", SYMBOLS.with(|symbols| symbols.display(&e).to_string()));
+  }
+}
@@ -0,0 +1,226 @@
+extern crate meowth;
+
+#[cfg(test)]
+mod test {
+  use meowth::parser::parser::is_incomplete;
+  use meowth::repl::{Repl, Reply, PROMPT, CONTINUATION_PROMPT};
+  use meowth::symbol::Interner;
+  use std::env;
+  use std::fs;
+  use std::process;
+
+  fn output(reply: Reply) -> String {
+    match reply {
+      Reply::Output(out) => out,
+      Reply::More => panic!("expected output, asked for more"),
+      Reply::Quit => panic!("expected output, quit"),
+    }
+  }
+
+  #[test]
+  fn test_is_incomplete() {
+    let symbols = Interner::new();
+    assert!(!is_incomplete("1 + 2", &symbols));
+    assert!(!is_incomplete("attack double(n) { n * 2 };", &symbols));
+    assert!(is_incomplete("attack double(n) {", &symbols));
+    assert!(is_incomplete("pokedex(1,", &symbols));
+    assert!(is_incomplete("[1, 2", &symbols));
+    assert!(is_incomplete("\"two\nlines", &symbols));
+
+    // braces inside strings and comments do not count
+    assert!(!is_incomplete("\"{\"", &symbols));
+    assert!(!is_incomplete("1 // {", &symbols));
+
+    // too many closers are left for the parser to report
+    assert!(!is_incomplete("1 }", &symbols));
+  }
+
+  #[test]
+  fn test_multi_line_input() {
+    let mut repl = Repl::new();
+
+    assert_eq!(PROMPT, repl.prompt());
+    assert!(matches!(repl.feed("attack double(n) {"), Reply::More));
+    assert_eq!(CONTINUATION_PROMPT, repl.prompt());
+    assert!(matches!(repl.feed("  n * 2"), Reply::More));
+    assert_eq!(" => ()", output(repl.feed("};")));
+    assert_eq!(PROMPT, repl.prompt());
+    assert_eq!(" => 42", output(repl.feed("double(21)")));
+  }
+
+  #[test]
+  fn test_cancel_and_exit() {
+    let mut repl = Repl::new();
+
+    assert!(matches!(repl.feed("attack broken() {"), Reply::More));
+    repl.cancel();
+    assert_eq!(PROMPT, repl.prompt());
+    assert_eq!("", output(repl.feed("")));
+    assert!(output(repl.feed("missingno")).starts_with("Error: "));
+    assert!(matches!(repl.feed("exit"), Reply::Quit));
+  }
+
+  #[test]
+  fn test_bindings_command() {
+    let mut repl = Repl::new();
+
+    output(repl.feed("pokeball mew = 151; bike name = \"pikachu\"; bike empty = 0; give(empty);"));
+    output(repl.feed("attack gnaw(n, m) { n + m };"));
+    output(repl.feed("bike name = \"raichu\";"));
+
+    assert_eq!(
+      "bike empty (empty)\nattack gnaw(n, m)\npokeball mew = 151\nbike name = \"raichu\"",
+      output(repl.feed(":bindings"))
+    );
+
+    assert_eq!("", output(repl.feed(":reset")));
+    assert_eq!("", output(repl.feed(":bindings")));
+    assert!(output(repl.feed("mew")).starts_with("Error: "));
+  }
+
+  #[test]
+  fn test_load_command() {
+    let mut repl = Repl::new();
+
+    assert_eq!(" => ()", output(repl.feed(":load tests/modules/kanto.meow")));
+    assert_eq!(" => 12", output(repl.feed("quad(3)")));
+    assert!(output(repl.feed(":load tests/modules/missing.meow")).starts_with("Error: cannot read"));
+    assert_eq!("Error: :load needs an argument", output(repl.feed(":load")));
+  }
+
+  #[test]
+  fn test_inspection_commands() {
+    let mut repl = Repl::new();
+
+    assert_eq!("Int(1) Plus Int(2)", output(repl.feed(":tokens 1 + 2")));
+    assert_eq!(
+      "Bop(\n    Plus,\n    Int(\n        1,\n    ),\n    Int(\n        2,\n    ),\n)",
+      output(repl.feed(":ast 1 + 2"))
+    );
+    assert!(output(repl.feed(":ast 1 +* 2")).starts_with("Error: "));
+    assert!(output(repl.feed(":tokens \"open")).starts_with("Error: "));
+    assert_eq!("Error: unknown command :pokeflute, try :help", output(repl.feed(":pokeflute")));
+  }
+
+  #[test]
+  fn test_type_commands() {
+    let mut repl = Repl::new();
+
+    assert_eq!("attack('a) -> 'a", output(repl.feed(":type attack (x) { x }")));
+    assert_eq!("Error: 1:1: Expected pokemon and found battle", output(repl.feed(":type win + 3")));
+    assert_eq!("Error: :type needs an argument", output(repl.feed(":type")));
+
+    // the session's bindings are known, and bad inputs never run
+    output(repl.feed("bike hp = 10;"));
+    assert_eq!("pokemon", output(repl.feed(":type hp * 2")));
+    assert_eq!("typecheck is on", output(repl.feed(":typecheck on")));
+    assert_eq!("Error: 1:19: Expected pokemon and found move", output(repl.feed("pokedex(hp); hp = \"full\"")));
+    assert_eq!(" => 10", output(repl.feed("hp")));
+    assert_eq!("typecheck is off", output(repl.feed(":typecheck off")));
+    assert_eq!(" => full", output(repl.feed("hp = \"full\"")));
+  }
+
+  #[test]
+  fn test_completions() {
+    let mut repl = Repl::new();
+
+    output(repl.feed("attack gnaw(n, m) { n + m }; bike pikachu = 25; pokeball pidgey = 16;"));
+    let completions = repl.completions();
+
+    assert_eq!((0, vec!("pokeball".to_string(), "pokedex".to_string())), completions.complete("poke", 4));
+    assert_eq!(
+      (8, vec!("pidgey".to_string(), "pikachu".to_string())),
+      completions.complete("pokedex(pi", 10)
+    );
+    assert_eq!((0, vec!("gnaw".to_string())), completions.complete("gn", 2));
+    assert_eq!((0, vec!("defend".to_string())), completions.complete("def", 3));
+    assert_eq!((3, Vec::<String>::new()), completions.complete("1 +", 3));
+    assert_eq!((0, vec!(":load".to_string())), completions.complete(":lo", 3));
+
+    // only the part before the cursor is completed
+    assert_eq!((0, vec!("gnaw".to_string())), completions.complete("gn + 1", 2));
+
+    // delimiters wider than a byte
+    assert_eq!(
+      (12, vec!("pokeball".to_string(), "pokedex".to_string(), "pop".to_string())),
+      completions.complete("pokedex(🐱po", 14)
+    );
+  }
+
+  #[test]
+  fn test_hints() {
+    let mut repl = Repl::new();
+
+    output(repl.feed("attack gnaw(n, m) { n + m }; attack rest() { 0 }; pokeball mew = 151;"));
+    let completions = repl.completions();
+
+    assert_eq!(Some("n, m)".to_string()), completions.hint("gnaw(", 5));
+    assert_eq!(Some("n, m)".to_string()), completions.hint("1 + gnaw(", 9));
+    assert_eq!(Some(")".to_string()), completions.hint("rest(", 5));
+    assert_eq!(None, completions.hint("mew(", 4));
+    assert_eq!(None, completions.hint("gnaw(1", 6));
+    assert_eq!(None, completions.hint("gnaw(", 4));
+    assert_eq!(Some("n, m)".to_string()), completions.hint("🐱gnaw(", 9));
+  }
+
+  fn temp_file(name: &str) -> String {
+    let dir = env::temp_dir().join(format!("meowth-repl-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(name).to_str().unwrap().to_string()
+  }
+
+  #[test]
+  fn test_save_and_load() {
+    let path = temp_file("session.meow");
+    let mut repl = Repl::new();
+
+    output(repl.feed("1 + 1 // a warm up"));
+    output(repl.feed("bike x = 1;"));
+    assert!(matches!(repl.feed("attack gnaw(n) {"), Reply::More));
+    assert!(matches!(repl.feed("  n + x"), Reply::More));
+    output(repl.feed("};"));
+    assert!(output(repl.feed("missingno")).starts_with("Error: "));
+    output(repl.feed("x = 2"));
+
+    assert_eq!(
+      "1 + 1 // a warm up\n;\nbike x = 1;\nattack gnaw(n) {\n  n + x\n};\nx = 2;\n",
+      repl.script()
+    );
+    assert_eq!("", output(repl.feed(&format!(":save {}", path))));
+
+    let mut replayed = Repl::new();
+    assert_eq!(" => ()", output(replayed.feed(&format!(":load {}", path))));
+    assert_eq!(" => 12", output(replayed.feed("gnaw(10)")));
+    assert_eq!(" => 2", output(replayed.feed("give(x)")));
+
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn test_snapshot_and_restore() {
+    let path = temp_file("session.json");
+    let mut repl = Repl::new();
+
+    output(repl.feed("bike x = 1; x = 2; x = 3; pokeball party = [1, \"two\", dex { 3: win }];"));
+    output(repl.feed("attack gnaw(n) { n + x }; card Trainer { name }; type Element = | Fire | Grass(n);"));
+    assert_eq!("", output(repl.feed(&format!(":snapshot {}", path))));
+
+    let mut restored = Repl::new();
+    assert_eq!("", output(restored.feed(&format!(":restore {}", path))));
+    assert_eq!(output(repl.feed(":bindings")), output(restored.feed(":bindings")));
+    assert_eq!(" => 13", output(restored.feed("gnaw(10)")));
+    assert_eq!(" => Trainer { name: \"red\" }", output(restored.feed("Trainer { name: \"red\" }")));
+    assert_eq!(" => Grass(1)", output(restored.feed("Grass(1)")));
+
+    // the whole history of a bike comes back, not just its last value
+    assert_eq!(" => 3", output(restored.feed("give(x)")));
+    assert_eq!(" => 2", output(restored.feed("give(x)")));
+    assert_eq!(" => 1", output(restored.feed("give(x)")));
+
+    fs::write(&path, "{ \"mem\": 1 }").unwrap();
+    assert!(output(restored.feed(&format!(":restore {}", path))).starts_with("Error: cannot restore"));
+
+    fs::remove_file(&path).unwrap();
+  }
+}
+
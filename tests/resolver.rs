@@ -0,0 +1,190 @@
+extern crate meowth;
+
+#[cfg(test)]
+mod test {
+  use meowth::expr::{Expr, BinOp, Dec};
+  use meowth::expr::Expr::*;
+  use meowth::interpreter::Interpreter;
+  use meowth::parser::parser;
+  use meowth::resolver;
+  use meowth::resolver_error::ResolverError;
+  use meowth::runtime_error::RuntimeError;
+  use meowth::state::State;
+  use meowth::symbol::{Interner, Symbol};
+
+  thread_local! {
+    // what the tests on this thread parse and resolve with
+    static SYMBOLS: Interner = Interner::new();
+  }
+
+  fn sym(x: &str) -> Symbol {
+    SYMBOLS.with(|symbols| symbols.intern(x))
+  }
+
+  fn parse(input: &str) -> Expr {
+    SYMBOLS.with(|symbols| parser::parse(input, symbols)).unwrap()
+  }
+
+  fn resolve(e: Expr, state: &mut State) -> Result<Expr, ResolverError> {
+    SYMBOLS.with(|symbols| resolver::resolve(e, state, symbols))
+  }
+
+  fn b(e: Expr) -> Box<Expr> {
+    Box::new(e)
+  }
+
+  fn local(x: &str, depth: usize, index: usize) -> Expr {
+    Local(sym(x), depth, index)
+  }
+
+  fn resolved(input: &str) -> Expr {
+    resolve(parse(input), &mut State::new()).unwrap()
+  }
+
+  // the body of the attack a program starts by declaring, and the names its frame is laid out with
+  fn attack_frame(e: Expr) -> (Vec<Symbol>, Expr) {
+    match e {
+      Decl(_, _, _, func, _) => match *func {
+        Func(_, body, _, _, _) => match *body {
+          Framed(names, body) => (names, *body),
+          e => panic!("expected a framed body, got {:?}", e),
+        },
+        e => panic!("expected an attack, got {:?}", e),
+      },
+      e => panic!("expected a pokeball, got {:?}", e),
+    }
+  }
+
+  fn attack_body(e: Expr) -> Expr {
+    attack_frame(e).1
+  }
+
+  #[test]
+  fn test_slots() {
+    assert_eq!(
+      Decl(Dec::DConst, b(local("a", 0, 0)), None, b(Int(1)),
+        b(Decl(Dec::DVar, b(local("b", 0, 1)), None, b(local("a", 0, 0)), b(local("b", 0, 1))))),
+      resolved("pokeball a = 1; bike b = a; b")
+    );
+
+    // params come first in an attack's frame, then its name, and a for loop's variable starts its own
+    assert_eq!(
+      For(
+        None,
+        sym("i"),
+        b(Range(b(Int(0)), b(local("n", 0, 0)), b(Int(1)))),
+        b(Framed(
+          vec!(sym("i")),
+          b(FnCall(b(local("f", 1, 1)), vec!(Bop(BinOp::Plus, b(local("n", 1, 0)), b(local("i", 0, 0)))))),
+        )),
+        None,
+      ),
+      attack_body(resolved("attack f(n) { for i in 0..n { f(n + i) } }; 1"))
+    );
+  }
+
+  #[test]
+  fn test_frame_layout() {
+    // an attack's frame has a slot for each param, its name and what its body declares
+    let (names, _) = attack_frame(resolved("attack f(n) { bike m = n; battle (n beats 0) { pokeball k = m; k } run { m } }; 1"));
+    assert_eq!(vec!(sym("n"), sym("f"), sym("m"), sym("k")), names);
+
+    // the top frame gets its slots once the input resolves, and keeps them for the next
+    let mut state = State::new();
+    resolve(parse("pokeball a = 1; battle (lose) { bike q = 2; q } run { a }"), &mut state).unwrap();
+    let slots: Vec<(Symbol, bool)> = state.mem[0].iter().map(|slot| (slot.0, slot.1.is_some())).collect();
+    assert_eq!(vec!((sym("a"), false), (sym("q"), false)), slots);
+
+    assert!(resolve(parse("bike r = nope; r"), &mut state).is_err());
+    assert_eq!(2, state.mem[0].len());
+  }
+
+  #[test]
+  fn test_undefined_names() {
+    assert_eq!(
+      Err(ResolverError::UndefinedVariable("nope".to_string())),
+      resolve(parse("pokeball a = 1; a + nope"), &mut State::new())
+    );
+
+    // nothing runs when a name is never bound
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+      Err(RuntimeError::ResolverError(ResolverError::UndefinedVariable("nope".to_string()))),
+      interpreter.eval("bike x = 1; battle (lose) { nope }")
+    );
+    let x = interpreter.symbols().intern("x");
+    assert!(interpreter.state.get(x).is_err());
+
+    // attacks see their caller's bindings, so a name free in one is looked up when it runs
+    assert_eq!(Var(sym("later")), attack_body(resolved("attack f() { later }; pokeball later = 2; 1")));
+    assert_eq!(Ok(Int(2)), interpreter.eval("attack f() { later }; pokeball later = 2; f()"));
+
+    // but something around the attack has to bind it
+    assert_eq!(
+      Err(ResolverError::UndefinedVariable("zz".to_string())),
+      resolve(parse("attack f() { zz }; f()"), &mut State::new())
+    );
+    assert_eq!(
+      Err(RuntimeError::ResolverError(ResolverError::UndefinedVariable("zz".to_string()))),
+      interpreter.eval("attack g() { attack h() { zz }; h() }; g()")
+    );
+  }
+
+  #[test]
+  fn test_session_bindings() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval("pokeball a = 1; bike b = 2;").unwrap();
+
+    // the session's names are interned by its interpreter
+    let symbols = interpreter.symbols().clone();
+    let e = parser::parse("b", &symbols).unwrap();
+    assert_eq!(Local(symbols.intern("b"), 0, 1), resolver::resolve(e, &mut interpreter.state, &symbols).unwrap());
+    assert_eq!(Ok(Int(3)), interpreter.eval("a + b"));
+  }
+
+  #[test]
+  fn test_slots_of_skipped_branches() {
+    let mut interpreter = Interpreter::new();
+
+    // a skipped bike leaves its slot empty, and later slots where they were laid out
+    assert_eq!(Ok(Int(3)), interpreter.eval("battle (lose) { bike q = 1; q }; bike r = 2; r = r + 1; r"));
+    assert_eq!(Err(RuntimeError::VariableNotFound("q".to_string())), interpreter.eval("q"));
+
+    // a defend loop comes back around to a use written before its bike
+    assert_eq!(
+      Ok(Int(10)),
+      interpreter.eval("
+        attack f() {
+          bike seen = 0;
+          for i in 0..1 { bike x = 0; bike n = 0; defend (n < 2) { seen = seen + x; bike x = 10; n = n + 1 } };
+          seen
+        };
+        f()
+      ")
+    );
+
+    // but a slot is not filled by an outer binding of the same name before its own bike runs
+    assert_eq!(
+      Err(RuntimeError::VariableNotFound("x".to_string())),
+      interpreter.eval("attack g() { bike x = 0; for i in 0..1 { pokedex(x); bike x = 10; x } }; g()")
+    );
+
+    // and the builtins that change a party or dex in place go through the slot as well
+    assert_eq!(
+      Ok(Party(vec!(Int(1), Int(2)))),
+      interpreter.eval("attack h() { bike p = [1]; for i in 0..1 { push(p, 2) }; p }; h()")
+    );
+    assert_eq!(
+      Err(RuntimeError::VariableNotFound("p".to_string())),
+      interpreter.eval("attack k() { bike p = [1]; for i in 0..1 { push(p, 2); bike p = []; p }; p }; k()")
+    );
+    assert_eq!(
+      Err(RuntimeError::VariableNotFound("d".to_string())),
+      interpreter.eval("attack m() { bike d = dex {}; for i in 0..1 { set(d, 1, 2); bike d = dex {}; d }; d }; m()")
+    );
+    assert_eq!(
+      Err(RuntimeError::VariableNotFound("b".to_string())),
+      interpreter.eval("attack n() { bike b = 1; for i in 0..1 { give(b); bike b = 2; b }; b }; n()")
+    );
+  }
+}
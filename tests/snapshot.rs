@@ -0,0 +1,138 @@
+extern crate meowth;
+extern crate serde_json;
+
+#[cfg(test)]
+mod test {
+  use meowth::interpreter::Interpreter;
+  use meowth::expr::{Expr, BinOp, Pattern};
+  use meowth::parser::parser::parse;
+  use meowth::snapshot::{save_state, load_state, save_value, load_value, SNAPSHOT_VERSION};
+  use meowth::snapshot_error::SnapshotError;
+  use meowth::state::State;
+  use meowth::symbol::{Interner, Symbol};
+  use meowth::types::Type;
+  use serde_json::Value;
+
+  thread_local! {
+    static SYMBOLS: Interner = Interner::new();
+  }
+
+  fn sym(x: &str) -> Symbol {
+    SYMBOLS.with(|symbols| symbols.intern(x))
+  }
+
+  fn round_trip(e: Expr) {
+    SYMBOLS.with(|symbols| assert_eq!(Ok(e.clone()), load_value(&save_value(&e, symbols).unwrap(), symbols)));
+  }
+
+  #[test]
+  fn test_value_round_trip() {
+    round_trip(Expr::Int(-151));
+    round_trip(Expr::Bool(true));
+    round_trip(Expr::Str("mew\n\"two\"".to_string()));
+    round_trip(Expr::Undefined);
+    round_trip(Expr::Party(vec!(Expr::Int(1), Expr::Str("pika".to_string()))));
+    round_trip(Expr::Dex(vec!((Expr::Str("hp".to_string()), Expr::Party(vec!())))));
+    round_trip(Expr::Card("Trainer".to_string(), vec!(("name".to_string(), Expr::Str("red".to_string())))));
+    round_trip(Expr::Ctor("Element".to_string(), "Grass".to_string(), 1));
+    round_trip(Expr::Variant("Element".to_string(), "Grass".to_string(), vec!(Expr::Int(5))));
+    round_trip(Expr::Range(Box::new(Expr::Int(0)), Box::new(Expr::Int(10)), Box::new(Expr::Int(2))));
+  }
+
+  #[test]
+  fn test_function_round_trip() {
+    // function values carry their whole body, whatever it contains
+    let mut interpreter = Interpreter::new();
+    let gnaw = interpreter.eval("
+      attack gnaw(n) {
+        bike total = 0;
+        for i in 0..n { total = total + i };
+        escape { evolve (total) { 0 => faint \"none\", t => t } } rope (e) { -1 }
+      };
+      gnaw
+    ").unwrap();
+
+    let json = save_value(&gnaw, interpreter.symbols()).unwrap();
+    assert_eq!(Ok(gnaw), load_value(&json, interpreter.symbols()));
+
+    // names are saved as text, so another interpreter interns them afresh
+    let mut restored = Interpreter::new();
+    restored.symbols().intern("unrelated");
+    let gnaw = load_value(&json, restored.symbols()).unwrap();
+    let name = restored.symbols().intern("gnaw");
+    restored.state.alloc_const(name, gnaw).unwrap();
+    assert_eq!(Ok(Expr::Int(10)), restored.eval("gnaw(5)"));
+    assert_eq!(Ok(Expr::Int(-1)), restored.eval("gnaw(1)"));
+
+    round_trip(Expr::Func(
+      None,
+      Box::new(Expr::Evolve(
+        Box::new(Expr::Var(sym("x"))),
+        vec!((Pattern::Ctor("Grass".to_string(), vec!(Pattern::Bind(sym("n")))), Expr::Bop(BinOp::Plus, Box::new(Expr::Var(sym("n"))), Box::new(Expr::Int(1))))),
+      )),
+      vec!(Expr::Var(sym("x"))),
+      vec!(Some(Type::Named("Element".to_string()))),
+      Some(Box::new(Type::Party(Box::new(Type::Int)))),
+    ));
+  }
+
+  #[test]
+  fn test_state_round_trip() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval("
+      bike x = 1; x = 2; x = 3;
+      bike emptied = 0; give(emptied);
+      pokeball party = [1, \"two\", dex { 3: win }];
+      attack gnaw(n) { n + x };
+      card Trainer { name, badges };
+      type Element = | Fire | Grass(n);
+    ").unwrap();
+
+    // an inner frame as if paused inside an attack
+    let n = interpreter.symbols().intern("n");
+    interpreter.state.begin_scope(&[n]);
+    interpreter.state.alloc(n, Expr::Int(7)).unwrap();
+
+    let json = save_state(&interpreter.state, interpreter.symbols()).unwrap();
+    assert_eq!(Ok(interpreter.state.clone()), load_state(&json, interpreter.symbols()));
+
+    let mut resumed = Interpreter::new();
+    resumed.state = load_state(&json, resumed.symbols()).unwrap();
+    resumed.state.end_scope();
+
+    assert_eq!(Ok(Expr::Int(13)), resumed.eval("gnaw(10)"));
+    assert_eq!(Ok(Expr::Int(3)), resumed.eval("give(x)"));
+    assert_eq!(Ok(Expr::Int(2)), resumed.eval("give(x)"));
+    assert_eq!(Ok(Expr::Int(1)), resumed.eval("x"));
+    assert!(resumed.eval("give(emptied)").is_err());
+    assert_eq!(Ok(Expr::Str("Grass(1) red".to_string())), resumed.eval("Grass(1) + \" \" + Trainer { name: \"red\", badges: 0 }.name"));
+  }
+
+  #[test]
+  fn test_compatibility_check() {
+    let symbols = Interner::new();
+    let json = save_state(&State::new(), &symbols).unwrap();
+    let mut envelope: Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(Some(SNAPSHOT_VERSION), envelope["version"].as_u64());
+
+    envelope["version"] = Value::from(SNAPSHOT_VERSION + 1);
+    assert_eq!(
+      Err(SnapshotError::IncompatibleVersion(SNAPSHOT_VERSION + 1, SNAPSHOT_VERSION)),
+      load_state(&envelope.to_string(), &symbols)
+    );
+
+    assert_eq!(Err(SnapshotError::MissingVersion), load_state("{ \"mem\": [] }", &symbols));
+    assert_eq!(
+      Err(SnapshotError::WrongKind("value".to_string(), "state".to_string())),
+      load_value(&json, &symbols)
+    );
+    assert!(matches!(load_state("not json", &symbols), Err(SnapshotError::Json(_))));
+  }
+
+  #[test]
+  fn test_only_values_are_saved() {
+    let symbols = Interner::new();
+    let e = parse("1 + 2", &symbols).unwrap();
+    assert_eq!(Err(SnapshotError::NotAValue(e.clone())), save_value(&e, &symbols));
+  }
+}
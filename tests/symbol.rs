@@ -0,0 +1,147 @@
+extern crate meowth;
+
+#[cfg(test)]
+mod test {
+  use meowth::expr::Expr;
+  use meowth::interpreter::Interpreter;
+  use meowth::parser::lexer::tokenize;
+  use meowth::parser::parser::parse;
+  use meowth::snapshot::{save_value, load_value};
+  use meowth::symbol::{Interner, Symbol};
+  use std::collections::HashSet;
+  use std::fmt;
+  use std::panic::{self, AssertUnwindSafe};
+
+  #[test]
+  fn test_interning() {
+    let symbols = Interner::new();
+    let a = symbols.intern("mew");
+    let b = symbols.intern("mew");
+
+    assert_eq!(a, b);
+    assert_ne!(a, symbols.intern("mewtwo"));
+    assert_eq!(2, symbols.len());
+    assert_eq!(Some(a), symbols.lookup("mew"));
+    assert_eq!(None, symbols.lookup("pika"));
+    assert_eq!(Some("mew"), symbols.name(a).as_deref());
+
+    // symbols are plain numbers, so they hash and compare by id
+    let set: HashSet<Symbol> = vec!(a, b, symbols.intern("mewtwo")).into_iter().collect();
+    assert_eq!(2, set.len());
+
+    // clones share their names
+    let other = symbols.clone();
+    assert_eq!(a, other.intern("mew"));
+    other.intern("pika");
+    assert_eq!(3, symbols.len());
+  }
+
+  #[test]
+  fn test_display() {
+    let symbols = Interner::new();
+    let mew = symbols.intern("mew");
+
+    assert_eq!("mew", symbols.display(&mew).to_string());
+    assert_eq!("#0", mew.to_string());
+
+    // the same number is another name in another interner
+    let other = Interner::new();
+    other.intern("pika");
+    assert_eq!("pika mew", format!("{} {}", other.display(&mew), symbols.display(&mew)));
+
+    // values and errors from an interpreter show with its names
+    let mut interpreter = Interpreter::new();
+    let f = interpreter.eval("attack f(n) { n + 1 }; f").unwrap();
+    assert_eq!("attack f(n) { n + 1 }", interpreter.symbols().display(&f).to_string());
+
+    let err = interpreter.eval("pokeball p = [1]; p = 2").unwrap_err();
+    assert!(interpreter.symbols().display(&err).to_string().contains(" p"));
+  }
+
+  #[test]
+  fn test_display_panics_put_back_the_outer_interner() {
+    struct Panics;
+
+    impl fmt::Display for Panics {
+      fn fmt(&self, _: &mut fmt::Formatter) -> fmt::Result {
+        panic!("while shown")
+      }
+    }
+
+    let symbols = Interner::new();
+    let mew = symbols.intern("mew");
+
+    assert!(panic::catch_unwind(AssertUnwindSafe(|| symbols.display(&Panics).to_string())).is_err());
+    assert_eq!("#0", mew.to_string());
+  }
+
+  #[test]
+  fn test_parses_share_symbols() {
+    let symbols = Interner::new();
+    let first = parse("pokeball mew = 1; mew + mew", &symbols).unwrap();
+    let second = parse("mew * 2", &symbols).unwrap();
+    assert_eq!(1, symbols.len());
+
+    let names: Vec<Symbol> = [first, second].iter().flat_map(vars).collect();
+    assert_eq!(4, names.len());
+    assert!(names.iter().all(|x| *x == names[0]));
+
+    // the lexer interns into the same names
+    tokenize("mew + 1", &symbols).unwrap();
+    assert_eq!(1, symbols.len());
+
+    // names read before a parse error stay interned
+    assert!(parse("pokeball pika =", &symbols).is_err());
+    assert_eq!(2, symbols.len());
+  }
+
+  #[test]
+  fn test_interpreter_interns_every_input() {
+    let mut interpreter = Interpreter::new();
+    interpreter.eval("pokeball mew = 1;").unwrap();
+    let before = interpreter.symbols().len();
+
+    interpreter.eval("mew + mew").unwrap();
+    assert_eq!(before, interpreter.symbols().len());
+    assert!(interpreter.symbols().lookup("mew").is_some());
+
+    // so a later input calls an attack by the symbol it was bound with
+    interpreter.eval("bike n = 1; attack f(m) { m + n };").unwrap();
+    assert_eq!(Ok(Expr::Int(3)), interpreter.eval("f(2)"));
+  }
+
+  #[test]
+  fn test_snapshots_hold_names() {
+    let symbols = Interner::new();
+    let mew = symbols.intern("mew");
+    let v = Expr::Func(None, Box::new(Expr::Var(mew)), vec!(Expr::Var(mew)), vec!(None), None);
+    let json = save_value(&v, &symbols).unwrap();
+
+    assert!(json.contains("\"mew\""));
+    assert_eq!(Ok(v.clone()), load_value(&json, &symbols));
+
+    // another interner gives the name its own number
+    let other = Interner::new();
+    other.intern("pika");
+    let loaded = load_value(&json, &other).unwrap();
+    assert_ne!(v, loaded);
+    assert_eq!(json, save_value(&loaded, &other).unwrap());
+  }
+
+  #[test]
+  fn test_exprs_are_send() {
+    fn send<T: Send>(_: T) {}
+
+    let e = parse("attack f(n) { n * 2 }; f(3)", &Interner::new()).unwrap();
+    send(e);
+  }
+
+  fn vars(e: &Expr) -> Vec<Symbol> {
+    match *e {
+      Expr::Var(x) => vec!(x),
+      Expr::Decl(_, ref x, _, ref e2, ref e3) => [x, e2, e3].iter().flat_map(|e| vars(e)).collect(),
+      Expr::Bop(_, ref e1, ref e2) => vars(e1).into_iter().chain(vars(e2)).collect(),
+      _ => Vec::new(),
+    }
+  }
+}
@@ -0,0 +1,95 @@
+extern crate meowth;
+
+#[cfg(test)]
+mod test {
+  use meowth::interpreter::Interpreter;
+  use meowth::parser::parser::parse;
+  use meowth::symbol::Interner;
+  use meowth::trace::TraceFormatter;
+  use std::cell::RefCell;
+  use std::rc::Rc;
+
+  // (depth, rule) of every step, and the formatted lines
+  fn trace(input: &str, top_level_only: bool) -> (Vec<(usize, String)>, Vec<String>) {
+    let steps = Rc::new(RefCell::new(Vec::new()));
+    let lines = Rc::new(RefCell::new(Vec::new()));
+    let mut formatter = TraceFormatter::new(top_level_only);
+
+    let mut interpreter = Interpreter::new();
+    let (s, l) = (steps.clone(), lines.clone());
+    interpreter.set_tracer(move |step| {
+      s.borrow_mut().push((step.depth, step.rule.to_string()));
+      l.borrow_mut().extend(formatter.format(step));
+    });
+    interpreter.eval(input).unwrap();
+
+    let steps = steps.borrow().clone();
+    let lines = lines.borrow().clone();
+    (steps, lines)
+  }
+
+  fn top_level_rules(steps: &[(usize, String)]) -> Vec<&str> {
+    steps.iter().filter(|s| s.0 == 0).map(|s| s.1.as_str()).collect()
+  }
+
+  #[test]
+  fn test_rules() {
+    let (steps, _) = trace("attack f(n) { n * 2 }; f(3)", false);
+
+    assert_eq!(
+      vec!("Decl", "Var", "FnCall", "Var", "Times", "Scope exit"),
+      top_level_rules(&steps)
+    );
+
+    // the multiplication fired inside the scope, one level down
+    assert!(steps.contains(&(1, "Times".to_string())));
+  }
+
+  #[test]
+  fn test_caught_errors_fire_where_they_are_caught() {
+    let (steps, _) = trace("escape { faint 1 } rope (e) { e }", false);
+    assert_eq!(vec!("Escape", "Var", "Rope exit", "Escape"), top_level_rules(&steps));
+
+    let (steps, _) = trace("attack f() { flee 1; 2 }; f()", false);
+    assert_eq!(vec!("Decl", "Var", "FnCall", "Scope exit"), top_level_rules(&steps));
+  }
+
+  #[test]
+  fn test_formatter() {
+    let (_, lines) = trace("pokeball x = 1; x + 2", false);
+    assert_eq!(vec!(
+      "   0. pokeball x = 1; x + 2",
+      "   1. [Decl] x + 2",
+      "   2. [Var] 1 + 2",
+      "      x ~> 1",
+      "   3. [Plus] 3",
+    ), lines);
+
+    let (_, lines) = trace("pokeball x = 1; x + 2", true);
+    assert_eq!(vec!(
+      "   0. pokeball x = 1; x + 2",
+      "   1. [Decl] x + 2",
+      "   2. [Var] 1 + 2",
+      "   3. [Plus] 3",
+    ), lines);
+  }
+
+  #[test]
+  fn test_no_tracer() {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_tracer(|_| panic!("cleared tracers are not called"));
+    interpreter.clear_tracer();
+    assert_eq!("3", interpreter.eval("1 + 2").unwrap().to_string());
+  }
+
+  #[test]
+  fn test_printed_steps_parse() {
+    let (_, lines) = trace("bike i = 0; defend (i < 2) { i = i + 1 }; pokedex(i * (2 + 3)) ", true);
+
+    // all but the last, which is the () that pokedex leaves
+    for line in lines[..lines.len() - 1].iter() {
+      let program = &line[line.find(']').map_or(6, |i| i + 2)..];
+      assert!(parse(program, &Interner::new()).is_ok(), "{}", program);
+    }
+  }
+}